@@ -0,0 +1,247 @@
+//! Compares signature verification with the affine arithmetic that Point used to have,
+//! where every addition and doubling inverted a field element, with the jacobian one.
+//! Also compares verifying the signatures of a block one by one and in a batch.
+//!
+//! cargo bench -p ecdsa
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ecdsa::secp256k1::{sign_digest, verify_batch, verify_batch_parallel, verify_digest, BatchItem, Point, Secp256k1, Signature};
+use num_bigint::BigInt;
+
+/// The affine implementation, kept here as a baseline
+mod legacy {
+    #![allow(clippy::all)]
+
+    use ecdsa::secp256k1::{Secp256k1, W};
+    use num_bigint::BigInt;
+    use num_traits::{one, zero, ToPrimitive};
+
+    /// An affine point with BigInt coordinates, as Point used to be
+    #[derive(Clone)]
+    pub struct Point {
+        pub x: BigInt,
+        pub y: BigInt
+    }
+
+    impl From<&ecdsa::secp256k1::Point> for Point {
+        fn from(point: &ecdsa::secp256k1::Point) -> Self {
+            Point { x: point.x.to_bigint(), y: point.y.to_bigint() }
+        }
+    }
+
+    const FP: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F";
+
+    fn bigint(num: &str) -> BigInt {
+        BigInt::parse_bytes(num.as_bytes(), 16).unwrap()
+    }
+
+    fn modulo(x: &BigInt, m: &BigInt) -> BigInt {
+        ((x % m) + m) % m
+    }
+
+    fn calculate_wnaf(w: u32, mut n: BigInt) -> Vec<i8> {
+        let mut wnaf: Vec<i8> = Vec::new();
+
+        let modulus: BigInt = BigInt::from(1 << w);
+        let mut i: usize = 0;
+
+        while n >= one() {
+            if &n & &one() == one() {
+                let remainder: BigInt = modulo(&n, &modulus);
+
+                if remainder > BigInt::from((1 << (w - 1)) - 1) {
+                    wnaf.push((remainder - &modulus).to_i8().unwrap());
+                } else {
+                    wnaf.push(remainder.to_i8().unwrap());
+                }
+
+                n = n - wnaf[i];
+            } else {
+                wnaf.push(0);
+            }
+
+            n >>= 1;
+            i += 1;
+        }
+
+        wnaf
+    }
+
+    fn modular_multiplicative_inverse(n: &BigInt, mut b: BigInt, t1: Option<BigInt>, t2: Option<BigInt>) -> BigInt {
+        let t1: BigInt = t1.unwrap_or(zero());
+        let t2: BigInt = t2.unwrap_or(one());
+
+        if n == &zero() || b == zero() {
+            return zero();
+        }
+
+        if b < zero() {
+            b = modulo(&b, n);
+        }
+
+        let q: BigInt = n / &b;
+        let r: BigInt = modulo(n, &b);
+
+        let t3: BigInt = t1 - &q * &t2;
+
+        if r == zero() && b != one() {
+            return zero();
+        }
+
+        if r == zero() {
+            t2
+        } else {
+            modular_multiplicative_inverse(&b, r, Some(t2), Some(t3))
+        }
+    }
+
+    fn is_identity(p: &Point) -> bool {
+        p.x == zero() && p.y == zero()
+    }
+
+    fn double(p: &Point) -> Point {
+        let fp: &BigInt = &bigint(FP);
+
+        let lambda: BigInt = modulo(&(3 * &p.x * &p.x * modular_multiplicative_inverse(fp, 2 * &p.y, None, None)), fp);
+        let rx: BigInt = modulo(&(&lambda * &lambda - &p.x - &p.x), fp);
+        let ry: BigInt = modulo(&(lambda * (&p.x - &rx) - &p.y), fp);
+
+        Point { x: rx, y: ry }
+    }
+
+    fn add(p: Point, other: &Point) -> Point {
+        let fp: &BigInt = &bigint(FP);
+
+        if is_identity(&p) {
+            other.clone()
+        } else if is_identity(other) {
+            p
+        } else if p.x == other.x && modulo(&(&p.y + &other.y), fp) == zero() {
+            Point { x: zero(), y: zero() }
+        } else if p.x == other.x {
+            double(&p)
+        } else {
+            let lambda: BigInt = modulo(&((&other.y - &p.y) * modular_multiplicative_inverse(fp, &other.x - &p.x, None, None)), fp);
+            let rx: BigInt = modulo(&(&lambda * &lambda - &other.x - &p.x), fp);
+            let ry: BigInt = modulo(&(lambda * (&p.x - &rx) - &p.y), fp);
+
+            Point { x: rx, y: ry }
+        }
+    }
+
+    fn multiply(n: BigInt, width: u32, pre_comp: &[Point]) -> Point {
+        let wnaf: Vec<i8> = calculate_wnaf(width, n);
+
+        let mut q: Point = Point { x: zero(), y: zero() };
+
+        for &digit in wnaf.iter().rev() {
+            q = double(&q);
+
+            if digit > 0 {
+                q = add(q, &pre_comp[((digit - 1) / 2) as usize]);
+            } else if digit < 0 {
+                let d: &Point = &pre_comp[((-digit - 1) / 2) as usize];
+                q = add(q, &Point { x: d.x.clone(), y: d.y.clone() * -1 });
+            }
+        }
+
+        q
+    }
+
+    fn precompute_points(mut q: Point, w: u32) -> Vec<Point> {
+        let mut p: Vec<Point> = vec![q.clone()];
+
+        q = double(&q);
+
+        for j in 1..(1 << (w - 1)) {
+            let buffer: Point = add(q.clone(), &p[(j - 1) as usize]);
+            p.push(buffer);
+        }
+
+        p
+    }
+
+    /// the precomputed points of the generator, computed once like the crate does
+    fn generator_points() -> &'static [Point] {
+        static POINTS: std::sync::OnceLock<Vec<Point>> = std::sync::OnceLock::new();
+        POINTS.get_or_init(|| precompute_points(Point::from(&Secp256k1::new().g), W))
+    }
+
+    pub fn verify(r: &BigInt, s: &BigInt, z: &BigInt, public_key: &Point) -> bool {
+        let secp256k1: Secp256k1 = Secp256k1::new();
+
+        let w: BigInt = modulo(&modular_multiplicative_inverse(&secp256k1.n, s.clone(), None, None), &secp256k1.n);
+
+        let u1: BigInt = modulo(&(z * &w), &secp256k1.n);
+        let u2: BigInt = modulo(&(r * &w), &secp256k1.n);
+
+        let p1: Point = multiply(u1, W, generator_points());
+        let p2: Point = multiply(u2, W, &precompute_points(public_key.clone(), W));
+
+        modulo(&add(p1, &p2).x, &secp256k1.n) == *r
+    }
+}
+
+fn compare_verification(c: &mut Criterion) {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+    let private_key: BigInt = BigInt::parse_bytes(b"f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181", 16).unwrap();
+    let public_key: Point = secp256k1.g.multiply_secret(&private_key);
+
+    let digest: [u8; 32] = sha256::digest(b"Alan Turing");
+    let signature: Signature = sign_digest(&digest, &private_key);
+    let z: BigInt = BigInt::from_bytes_be(num_bigint::Sign::Plus, &digest);
+
+    let legacy_public_key: legacy::Point = legacy::Point::from(&public_key);
+
+    assert!(legacy::verify(&signature.get_r(), &signature.get_s(), &z, &legacy_public_key));
+
+    let mut group = c.benchmark_group("verify");
+
+    group.bench_function("legacy", |b| b.iter(|| {
+        legacy::verify(&signature.get_r(), &signature.get_s(), black_box(&z), &legacy_public_key)
+    }));
+
+    group.bench_function("current", |b| b.iter(|| {
+        verify_digest(&signature, black_box(&digest), &public_key)
+    }));
+
+    group.finish();
+
+    c.bench_function("sign", |b| b.iter(|| sign_digest(black_box(&digest), &private_key)));
+}
+
+fn compare_batch_verification(c: &mut Criterion) {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+
+    // 256 transactions from 32 senders
+    let keys: Vec<BigInt> = (0..32u8).map(|i| BigInt::from_bytes_be(num_bigint::Sign::Plus, &sha256::digest(&[i]))).collect();
+    let public_keys: Vec<Point> = keys.iter().map(|key| secp256k1.g.multiply_secret(key)).collect();
+
+    let signatures: Vec<(Signature, [u8; 32], usize)> = (0..256u32).map(|i| {
+        let digest: [u8; 32] = sha256::digest(&i.to_be_bytes());
+        (sign_digest(&digest, &keys[i as usize % 32]), digest, i as usize % 32)
+    }).collect();
+
+    let items: Vec<BatchItem> = signatures.iter()
+        .map(|(signature, digest, key)| BatchItem { signature, digest: *digest, public_key: &public_keys[*key] })
+        .collect();
+
+    let threads: usize = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    let mut group = c.benchmark_group("batch of 256");
+
+    group.bench_function("one by one", |b| b.iter(|| {
+        items.iter().all(|item| verify_digest(item.signature, &item.digest, item.public_key))
+    }));
+
+    group.bench_function("batch", |b| b.iter(|| verify_batch(black_box(&items))));
+
+    group.bench_function(format!("batch on {} threads", threads), |b| b.iter(|| {
+        verify_batch_parallel(black_box(&items), threads)
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, compare_verification, compare_batch_verification);
+criterion_main!(benches);
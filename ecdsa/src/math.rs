@@ -1,8 +1,4 @@
-use num_bigint::{BigInt, Sign};
-use rand::{thread_rng, Rng};
-use rand::rngs::ThreadRng;
-use num_traits::{zero, one};
-use num_traits::ToPrimitive;
+use num_bigint::BigInt;
 
 /// Helper functions for BigInt operations
 /// 
@@ -29,101 +25,164 @@ pub fn modulo(x: &BigInt, m: &BigInt) -> BigInt {
     ((x % m) + m) % m
 }
 
-/// Helper function to calculate the w-ary non-adjacent form of a number
+/// Helper function to write a non-negative BigInt below 2^256 as 32 big-endian bytes
 /// 
 /// # Arguments
-/// * `w` - A u32 that is the window size
-/// * `n` - A BigInt that is the number to be converted to wnaf
+/// * `num` - A reference to a BigInt that is the number to be converted
 /// 
 /// # Returns
-/// A Vec<i8> representation of the wnaf
+/// The number left padded with zeros to 32 bytes
 /// 
-pub fn calculate_wnaf(w: u32, mut n: BigInt) -> Vec<i8> {
-    let mut wnaf: Vec<i8> = Vec::new();
+pub fn to_bytes_32(num: &BigInt) -> [u8; 32] {
+    let (_, bytes) = num.to_bytes_be();
 
-    let modulus: BigInt = BigInt::from(1 << w);
-    let mut i: usize = 0;
+    let mut padded: [u8; 32] = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
 
-    while n >= one() {
-        // if n is odd
-        if &n & &one() == one() {
-            let remainder: BigInt = modulo(&n, &modulus);
+    padded
+}
 
-            // if remainder is greater than 2^(w-1) - 1
-            if remainder > BigInt::from((1 << (w - 1)) - 1) {
-                wnaf.push((remainder - &modulus).to_i8().unwrap());
-            } else {
-                wnaf.push(remainder.to_i8().unwrap());
-            }
+/// Helper function to add two 64 bits limbs and a carry
+/// 
+/// # Arguments
+/// * `a` - A u64 that is the first limb
+/// * `b` - A u64 that is the second limb
+/// * `carry` - A u64 that is the carry in, 0 or 1
+/// 
+/// # Returns
+/// The low 64 bits of the sum and the carry out, 0 or 1
+/// 
+pub fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let sum: u128 = a as u128 + b as u128 + carry as u128;
+    (sum as u64, (sum >> 64) as u64)
+}
 
-            n = n - wnaf[i];
-        } else {
-            wnaf.push(0);
-        }
+/// Helper function to subtract a 64 bits limb and a borrow from another
+/// 
+/// # Arguments
+/// * `a` - A u64 that is the limb to subtract from
+/// * `b` - A u64 that is the limb to subtract
+/// * `borrow` - A u64 that is the borrow in, 0 or 1
+/// 
+/// # Returns
+/// The low 64 bits of the difference and the borrow out, 0 or 1
+/// 
+pub fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let diff: u128 = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (diff as u64, (diff >> 127) as u64)
+}
 
-        n >>= 1;
-        i += 1;
+/// Helper function to multiply two 64 bits limbs and add a limb and a carry to the product, which can't overflow 128 bits
+/// 
+/// # Arguments
+/// * `a` - A u64 that is the limb to add
+/// * `b` - A u64 that is the first factor
+/// * `c` - A u64 that is the second factor
+/// * `carry` - A u64 that is the carry in
+/// 
+/// # Returns
+/// The low and the high 64 bits of a + b * c + carry
+/// 
+pub fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let sum: u128 = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (sum as u64, (sum >> 64) as u64)
+}
+
+/// Helper function to add two 256 bits numbers written as four limbs, least significant first
+/// 
+/// # Returns
+/// The low 256 bits of the sum and the carry out, 0 or 1
+/// 
+pub fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], u64) {
+    let mut sum: [u64; 4] = [0; 4];
+    let mut carry: u64 = 0;
+
+    for i in 0..4 {
+        (sum[i], carry) = adc(a[i], b[i], carry);
     }
 
-    wnaf
+    (sum, carry)
 }
 
-/// Helper function to get a truly random number
+/// Helper function to subtract a 256 bits number written as four limbs from another
 /// 
 /// # Returns
-/// A BigInt representation of the random number
+/// The difference modulo 2^256 and the borrow out, 1 if b was greater than a
 /// 
-pub fn entropy() -> BigInt {
-    let mut rng: ThreadRng = thread_rng();
+pub fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], u64) {
+    let mut diff: [u64; 4] = [0; 4];
+    let mut borrow: u64 = 0;
+
+    for i in 0..4 {
+        (diff[i], borrow) = sbb(a[i], b[i], borrow);
+    }
+
+    (diff, borrow)
+}
+
+/// Helper function to multiply two 256 bits numbers written as four limbs into their 512 bits product
+/// 
+/// # Returns
+/// The eight limbs of the product, least significant first
+/// 
+pub fn mul_limbs(a: &[u64; 4], b: &[u64; 4]) -> [u64; 8] {
+    let mut product: [u64; 8] = [0; 8];
+
+    for (i, &a_i) in a.iter().enumerate() {
+        let mut carry: u64 = 0;
 
-    let mut bytes: [u8; 32] = [0u8; 32]; // 32 bytes is 256 bits
+        for (j, &b_j) in b.iter().enumerate() {
+            (product[i + j], carry) = mac(product[i + j], a_i, b_j, carry);
+        }
 
-    rng.fill(&mut bytes[..]);
+        product[i + 4] = carry;
+    }
 
-    BigInt::from_bytes_be(Sign::Plus, &bytes)
-}      
+    product
+}
 
-/// Helper function to calculate the modular multiplicative inverse of a number.
-/// This function uses the extended euclidean algorithm to calculate the modular multiplicative inverse.
+/// Helper function to pick one of two numbers written as four limbs without branching on the choice
 /// 
 /// # Arguments
-/// * `n` - A reference to a BigInt that is the number to be modded
-/// * `b` - A BigInt that is the modulo
-/// * `t1` - An optional BigInt that is the first value in the calculation (default is 0)
-/// * `t2` - An optional BigInt that is the second value in the calculation (default is 1)
+/// * `a` - A reference to the limbs returned when choice is 0
+/// * `b` - A reference to the limbs returned when choice is 1
+/// * `choice` - A u64 that is 0 or 1
 /// 
 /// # Returns
-/// A BigInt representation of the modular multiplicative inverse
-/// 
-pub fn modular_multiplicative_inverse(
-    n: &BigInt,
-    mut b: BigInt,
-    t1: Option<BigInt>,
-    t2: Option<BigInt>,
-) -> BigInt {
-    let t1: BigInt = t1.unwrap_or(zero()); // set default value for t1
-    let t2: BigInt = t2.unwrap_or(one());// set default value for t2
-
-    if n == &zero() || b == zero() {
-        return zero();
-    }
+/// a or b
+/// 
+pub fn select_limbs(a: &[u64; 4], b: &[u64; 4], choice: u64) -> [u64; 4] {
+    // the mask has every bit set to pick b and none to pick a
+    let mask: u64 = 0u64.wrapping_sub(choice);
+    let mut selected: [u64; 4] = [0; 4];
 
-    if b < zero() {
-        b = modulo(&b, n);
+    for i in 0..4 {
+        selected[i] = a[i] ^ ((a[i] ^ b[i]) & mask);
     }
 
-    let q: BigInt = n / &b;
-    let r: BigInt = modulo(n, &b);
+    selected
+}
 
-    let t3: BigInt = t1 - &q * &t2;
+/// Helper function to read 32 big-endian bytes as four limbs, least significant first
+pub fn limbs_from_bytes(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs: [u64; 4] = [0; 4];
 
-    if r == zero() && b != one() {
-        return zero();
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start: usize = 32 - 8 * (i + 1);
+        *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
     }
 
-    if r == zero() {
-        t2
-    } else {
-        modular_multiplicative_inverse(&b, r, Some(t2), Some(t3))
+    limbs
+}
+
+/// Helper function to write four limbs, least significant first, as 32 big-endian bytes
+pub fn limbs_to_bytes(limbs: &[u64; 4]) -> [u8; 32] {
+    let mut bytes: [u8; 32] = [0; 32];
+
+    for (i, limb) in limbs.iter().enumerate() {
+        let start: usize = 32 - 8 * (i + 1);
+        bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
     }
-}
\ No newline at end of file
+
+    bytes
+}
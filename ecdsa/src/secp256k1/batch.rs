@@ -0,0 +1,118 @@
+//! verifies many signatures together, such as the ones of the transactions of a block.
+//!
+//! an ECDSA signature only carries the x coordinate of R, so unlike Schnorr signatures they can't be added up
+//! into a single equation. the batch shares the work instead: one scalar inversion for every s, one table of
+//! precomputed points per distinct public key converted with one field inversion, u1 * G + u2 * Q with
+//! Shamir's trick, and one field inversion for every R. the check of each signature comes out of it,
+//! so a failing batch tells which signature is invalid
+
+use std::collections::HashMap;
+use std::thread;
+use super::{get_curve_precomputed_points, Point, Scalar, Signature, W};
+use super::jacobian::JacobianPoint;
+use super::point::precompute_tables;
+
+/// the least number of signatures a thread gets, smaller batches aren't worth the threads
+const MIN_ITEMS_PER_THREAD: usize = 16;
+
+/// A signature to verify in a batch, with the digest of its message and the public key that signed it
+#[derive(Clone)]
+pub struct BatchItem<'a> {
+    /// The signature to verify
+    pub signature: &'a Signature,
+
+    /// The 32 bytes digest of the message, usually its SHA-256
+    pub digest: [u8; 32],
+
+    /// The public key the signature has to be from
+    pub public_key: &'a Point
+}
+
+/// verifies a batch of signatures, every one of them is checked as verify_digest would
+///
+/// # Arguments
+/// * `items` - A slice of the signatures with their digests and public keys
+///
+/// # Returns
+/// Ok if every signature is valid, otherwise the index of the first invalid one
+///
+pub fn verify_batch(items: &[BatchItem]) -> Result<(), usize> {
+    // the checks that don't need the curve, r and s are scalars so they are already below n
+    for (i, item) in items.iter().enumerate() {
+        let signature: &Signature = item.signature;
+
+        if signature.get_r_scalar().is_zero() || signature.get_s_scalar().is_zero() || !signature.is_low_s() {
+            return Err(i);
+        }
+
+        // no arithmetic is done with a key that isn't on the curve, the identity included
+        if !item.public_key.is_on_curve() {
+            return Err(i);
+        }
+    }
+
+    let s: Vec<Scalar> = items.iter().map(|item| *item.signature.get_s_scalar()).collect();
+    let w: Vec<Scalar> = Scalar::batch_invert(&s);
+
+    // a key that signed several times, like a sender with several transactions, only gets one table
+    let mut key_indexes: HashMap<&Point, usize> = HashMap::new();
+    let mut keys: Vec<&Point> = Vec::new();
+
+    for item in items {
+        key_indexes.entry(item.public_key).or_insert_with(|| {
+            keys.push(item.public_key);
+            keys.len() - 1
+        });
+    }
+
+    let tables: Vec<Vec<Point>> = precompute_tables(&keys, W);
+
+    let sums: Vec<JacobianPoint> = items.iter().zip(&w).map(|(item, w)| {
+        let u1: Scalar = Scalar::from_bytes_reduced(&item.digest) * *w;
+        let u2: Scalar = *item.signature.get_r_scalar() * *w;
+
+        let table: &[Point] = &tables[key_indexes[item.public_key]];
+        Point::strauss_multiply(&[(&u1, get_curve_precomputed_points()), (&u2, table)], W)
+    }).collect();
+
+    for (i, (item, res)) in items.iter().zip(JacobianPoint::batch_to_affine(&sums)).enumerate() {
+        // the x coordinate is in the field of the curve, it has to be reduced mod n to be compared with r
+        if res.is_identity() || Scalar::from_bytes_reduced(&res.x.to_bytes()) != *item.signature.get_r_scalar() {
+            return Err(i);
+        }
+    }
+
+    Ok(())
+}
+
+/// verifies a batch of signatures on several threads, each one verifying a part of the batch with verify_batch.
+/// a batch too small to give each thread enough signatures uses fewer threads, one thread being the current one
+///
+/// # Arguments
+/// * `items` - A slice of the signatures with their digests and public keys
+/// * `threads` - A usize which is the most threads to use
+///
+/// # Returns
+/// Ok if every signature is valid, otherwise the index of the first invalid one
+///
+pub fn verify_batch_parallel(items: &[BatchItem], threads: usize) -> Result<(), usize> {
+    let threads: usize = threads.min(items.len() / MIN_ITEMS_PER_THREAD).max(1);
+    if threads == 1 {
+        return verify_batch(items);
+    }
+
+    let chunk_size: usize = items.len().div_ceil(threads);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = items.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || verify_batch(chunk)))
+            .collect();
+
+        // the chunks are in order, the first error is the first invalid signature
+        for (i, handle) in handles.into_iter().enumerate() {
+            handle.join().unwrap().map_err(|index| i * chunk_size + index)?;
+        }
+
+        Ok(())
+    })
+}
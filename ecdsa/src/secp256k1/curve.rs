@@ -1,6 +1,6 @@
 use num_bigint::BigInt;
 use crate::math::bigint;
-use super::Point;
+use super::{FieldElement, Point};
 
 /// Curve struct that holds the parameters of the secp256k1 curve
 /// The constants for the curve are defined in the super module
@@ -23,8 +23,8 @@ impl Secp256k1 {
             p: bigint(super::P),
             n: bigint(super::N),
             g: Point {
-                x: bigint(super::X),
-                y: bigint(super::Y)
+                x: FieldElement::from(&bigint(super::X)),
+                y: FieldElement::from(&bigint(super::Y))
             }
         }
     }
@@ -0,0 +1,260 @@
+//! elements of the field secp256k1 is over, the integers modulo P = 2^256 - 2^32 - 977.
+//!
+//! an element is four 64 bits limbs, least significant first, always kept in [0, P-1] so that equal elements
+//! have equal limbs. nothing is allocated and no operation branches on the values. since 2^256 = 2^32 + 977 mod P,
+//! reducing a 512 bits product only takes a couple of multiplications by that small number
+
+use core::fmt;
+use core::ops::{Add, Mul, Neg, Sub};
+use num_bigint::{BigInt, Sign};
+use crate::math::{add_limbs, adc, limbs_from_bytes, limbs_to_bytes, mac, modulo, mul_limbs, select_limbs, sub_limbs, to_bytes_32};
+
+/// The prime P, least significant limb first
+const P: [u64; 4] = [0xFFFFFFFEFFFFFC2F, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF];
+
+/// 2^256 mod P, ie. 2^32 + 977
+const R: u64 = 0x1000003D1;
+
+/// P - 2, the exponent that inverts an element
+const P_MINUS_2: [u64; 4] = [0xFFFFFFFEFFFFFC2D, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF];
+
+/// (P + 1) / 4, the exponent that gives a square root since P = 3 mod 4
+const P_PLUS_1_DIV_4: [u64; 4] = [0xFFFFFFFFBFFFFF0C, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0x3FFFFFFFFFFFFFFF];
+
+/// An integer modulo P, the coordinates of the points of the curve
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldElement([u64; 4]);
+
+impl FieldElement {
+    /// The element 0
+    pub const ZERO: FieldElement = FieldElement([0; 4]);
+
+    /// The element 1
+    pub const ONE: FieldElement = FieldElement([1, 0, 0, 0]);
+
+    /// reads an element from its 32 big-endian bytes
+    ///
+    /// # Arguments
+    /// * `bytes` - The 32 bytes of the element
+    ///
+    /// # Returns
+    /// The element, None if the bytes are P or more
+    ///
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs: [u64; 4] = limbs_from_bytes(bytes);
+
+        // the subtraction borrows only if the value is below P
+        let (_, borrow) = sub_limbs(&limbs, &P);
+        if borrow == 1 { Some(FieldElement(limbs)) } else { None }
+    }
+
+    /// returns the 32 big-endian bytes of the element
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    /// returns the element as a BigInt in [0, P-1]
+    pub fn to_bigint(&self) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &self.to_bytes())
+    }
+
+    /// returns the element of a BigInt in [0, P-1]
+    ///
+    /// # Arguments
+    /// * `value` - A reference to a BigInt that is the value of the element
+    ///
+    /// # Returns
+    /// The element, None if the BigInt is negative or P or more
+    ///
+    pub fn from_bigint(value: &BigInt) -> Option<Self> {
+        if value.sign() == Sign::Minus || value.bits() > 256 {
+            return None;
+        }
+
+        FieldElement::from_bytes(&to_bytes_32(value))
+    }
+
+    /// returns true if the element is 0
+    pub fn is_zero(&self) -> bool {
+        (self.0[0] | self.0[1] | self.0[2] | self.0[3]) == 0
+    }
+
+    /// returns true if the element is odd, as an integer in [0, P-1]
+    pub fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    /// returns the element times itself
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// returns the inverse of the element with Fermat's little theorem, ie. x^(P-2).
+    /// the exponent is public so the squarings and multiplications are the same whatever the element is.
+    /// the inverse of 0 is 0
+    pub fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// returns a square root of the element, x^((P+1)/4) since P = 3 mod 4. the other root is its opposite
+    ///
+    /// # Returns
+    /// The square root, None if the element isn't a square
+    ///
+    pub fn sqrt(&self) -> Option<Self> {
+        let root: FieldElement = self.pow(&P_PLUS_1_DIV_4);
+        if root.square() == *self { Some(root) } else { None }
+    }
+
+    /// swaps two elements when swap is true, without branching on swap
+    ///
+    /// # Arguments
+    /// * `a` - A mutable reference to the first element
+    /// * `b` - A mutable reference to the second element
+    /// * `swap` - A bool that is true if the elements have to be swapped
+    ///
+    pub fn conditional_swap(a: &mut FieldElement, b: &mut FieldElement, swap: bool) {
+        let a_limbs: [u64; 4] = a.0;
+
+        a.0 = select_limbs(&a.0, &b.0, swap as u64);
+        b.0 = select_limbs(&b.0, &a_limbs, swap as u64);
+    }
+
+    /// returns b when choice is true and a otherwise, without branching on choice
+    ///
+    /// # Arguments
+    /// * `a` - A reference to the element returned when choice is false
+    /// * `b` - A reference to the element returned when choice is true
+    /// * `choice` - A bool that picks the element
+    ///
+    pub fn conditional_select(a: &FieldElement, b: &FieldElement, choice: bool) -> Self {
+        FieldElement(select_limbs(&a.0, &b.0, choice as u64))
+    }
+
+    /// returns the element raised to a public exponent, from its most significant bit down
+    fn pow(&self, exponent: &[u64; 4]) -> Self {
+        let mut result: FieldElement = FieldElement::ONE;
+
+        for i in (0..256).rev() {
+            result = result.square();
+
+            if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+
+        result
+    }
+
+    /// reduces a 512 bits number written as eight limbs modulo P
+    fn reduce_wide(wide: &[u64; 8]) -> Self {
+        // the high half weighs 2^256 = R mod P, folding it in leaves less than 2^290
+        let mut limbs: [u64; 4] = [0; 4];
+        let mut carry: u64 = 0;
+
+        for i in 0..4 {
+            (limbs[i], carry) = mac(wide[i], wide[i + 4], R, carry);
+        }
+
+        // the carry is below 2^34, folding it in again can only overflow 2^256 by a little
+        let mut overflow: u64 = 0;
+        (limbs[0], overflow) = mac(limbs[0], carry, R, overflow);
+        for limb in limbs[1..].iter_mut() {
+            (*limb, overflow) = adc(*limb, 0, overflow);
+        }
+
+        // when it overflows the limbs are small, adding R once more can't overflow again
+        (limbs[0], carry) = mac(limbs[0], overflow, R, 0);
+        for limb in limbs[1..].iter_mut() {
+            (*limb, carry) = adc(*limb, 0, carry);
+        }
+
+        FieldElement::reduce_once(&limbs, 0)
+    }
+
+    /// brings a number below 2P, given as four limbs and the carry above them, in [0, P-1]
+    fn reduce_once(limbs: &[u64; 4], carry: u64) -> Self {
+        let (reduced, borrow) = sub_limbs(limbs, &P);
+
+        // the limbs are kept only if they are below P and there is no carry
+        FieldElement(select_limbs(&reduced, limbs, borrow & (carry ^ 1)))
+    }
+}
+
+impl Add for FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, other: FieldElement) -> FieldElement {
+        let (sum, carry) = add_limbs(&self.0, &other.0);
+        FieldElement::reduce_once(&sum, carry)
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, other: FieldElement) -> FieldElement {
+        let (diff, borrow) = sub_limbs(&self.0, &other.0);
+
+        // P is added back when the difference went below 0
+        let (diff, _) = add_limbs(&diff, &select_limbs(&[0; 4], &P, borrow));
+        FieldElement(diff)
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, other: FieldElement) -> FieldElement {
+        FieldElement::reduce_wide(&mul_limbs(&self.0, &other.0))
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        FieldElement::ZERO - self
+    }
+}
+
+/// converts a small number, such as a constant of a formula
+impl From<u64> for FieldElement {
+    fn from(value: u64) -> Self {
+        FieldElement([value, 0, 0, 0])
+    }
+}
+
+/// converts any BigInt, negative or P and more included, by taking it modulo P
+impl From<&BigInt> for FieldElement {
+    fn from(value: &BigInt) -> Self {
+        let p: BigInt = BigInt::from_bytes_be(Sign::Plus, &limbs_to_bytes(&P));
+        FieldElement(limbs_from_bytes(&to_bytes_32(&modulo(value, &p))))
+    }
+}
+
+impl From<FieldElement> for BigInt {
+    fn from(value: FieldElement) -> Self {
+        value.to_bigint()
+    }
+}
+
+/// displays the element in decimal, as the BigInt coordinates used to be
+impl fmt::Display for FieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_bigint())
+    }
+}
+
+/// displays the element in hex without leading zeros, as the BigInt coordinates used to be
+impl fmt::LowerHex for FieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}", self.to_bigint())
+    }
+}
+
+impl fmt::Debug for FieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FieldElement({:x})", self)
+    }
+}
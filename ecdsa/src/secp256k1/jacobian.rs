@@ -0,0 +1,219 @@
+//! points in Jacobian coordinates, used inside the scalar multiplications.
+//! https://hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html
+//!
+//! (X, Y, Z) is the affine point (X / Z^2, Y / Z^3) so additions and doublings don't divide,
+//! a whole multiplication only needs one inversion when converting back to affine.
+
+use super::{FieldElement, Point};
+
+/// A point in Jacobian coordinates. Z is 0 for the identity
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct JacobianPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement
+}
+
+impl JacobianPoint {
+    /// returns the identity point
+    pub(crate) fn identity() -> Self {
+        JacobianPoint { x: FieldElement::ONE, y: FieldElement::ONE, z: FieldElement::ZERO }
+    }
+
+    /// returns true if the point is the identity
+    pub(crate) fn is_identity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// returns the point of the given affine point, (0, 0) being the identity
+    pub(crate) fn from_affine(point: &Point) -> Self {
+        if point.is_identity() {
+            return JacobianPoint::identity();
+        }
+
+        JacobianPoint { x: point.x, y: point.y, z: FieldElement::ONE }
+    }
+
+    /// returns the affine point, the inversion of Z doesn't depend on the point
+    pub(crate) fn to_affine(self) -> Point {
+        if self.is_identity() {
+            return Point::identity();
+        }
+
+        self.to_affine_with(self.z.invert())
+    }
+
+    /// returns the affine points of several points with a single inversion, using Montgomery's trick:
+    /// the product of every Z is inverted and the inverse of each Z is taken out of it
+    ///
+    /// # Arguments
+    /// * `points` - A slice of the points to convert
+    ///
+    /// # Returns
+    /// A Vec<Point> of the affine points, in the same order
+    ///
+    pub(crate) fn batch_to_affine(points: &[JacobianPoint]) -> Vec<Point> {
+        // prefixes[i] is the product of the Z before the point i, the identities being skipped
+        let mut prefixes: Vec<FieldElement> = Vec::with_capacity(points.len());
+        let mut product: FieldElement = FieldElement::ONE;
+
+        for point in points {
+            prefixes.push(product);
+            if !point.is_identity() {
+                product = product * point.z;
+            }
+        }
+
+        let mut inverse: FieldElement = product.invert();
+        let mut affine: Vec<Point> = vec![Point::identity(); points.len()];
+
+        for (i, point) in points.iter().enumerate().rev() {
+            if point.is_identity() {
+                continue;
+            }
+
+            // inverse is the inverse of the product of the Z up to this point
+            affine[i] = point.to_affine_with(inverse * prefixes[i]);
+            inverse = inverse * point.z;
+        }
+
+        affine
+    }
+
+    /// returns the affine point from the inverse of Z
+    fn to_affine_with(self, z_inv: FieldElement) -> Point {
+        let z_inv_2: FieldElement = z_inv.square();
+
+        Point {
+            x: self.x * z_inv_2,
+            y: self.y * z_inv_2 * z_inv
+        }
+    }
+
+    /// doubles the point with the dbl-2009-l formulas for a = 0, doubling the identity gives the identity
+    pub(crate) fn double(&self) -> Self {
+        let a: FieldElement = self.x.square();
+        let b: FieldElement = self.y.square();
+        let c: FieldElement = b.square();
+
+        // D = 2 * ((X + B)^2 - A - C)
+        let d: FieldElement = (self.x + b).square() - a - c;
+        let d: FieldElement = d + d;
+
+        let e: FieldElement = a + a + a;
+        let f: FieldElement = e.square();
+
+        let x: FieldElement = f - d - d;
+        let y: FieldElement = e * (d - x) - c * FieldElement::from(8);
+
+        let y_z: FieldElement = self.y * self.z;
+        let z: FieldElement = y_z + y_z;
+
+        JacobianPoint { x, y, z }
+    }
+
+    /// adds two points with the add-2007-bl formulas, falling back to doubling when they are equal
+    pub(crate) fn add(&self, other: &JacobianPoint) -> Self {
+        if self.is_identity() {
+            return *other;
+        }
+        if other.is_identity() {
+            return *self;
+        }
+
+        let z1_2: FieldElement = self.z.square();
+        let z2_2: FieldElement = other.z.square();
+
+        let u1: FieldElement = self.x * z2_2;
+        let u2: FieldElement = other.x * z1_2;
+        let s1: FieldElement = self.y * other.z * z2_2;
+        let s2: FieldElement = other.y * self.z * z1_2;
+
+        self.add_with(other, u1, u2, s1, s2, z1_2, z2_2)
+    }
+
+    /// adds an affine point, cheaper than add since its Z is 1. used with precomputed points
+    pub(crate) fn add_affine(&self, other: &Point) -> Self {
+        if other.is_identity() {
+            return *self;
+        }
+        if self.is_identity() {
+            return JacobianPoint::from_affine(other);
+        }
+
+        let other: JacobianPoint = JacobianPoint::from_affine(other);
+        let z1_2: FieldElement = self.z.square();
+
+        let u2: FieldElement = other.x * z1_2;
+        let s2: FieldElement = other.y * self.z * z1_2;
+
+        self.add_with(&other, self.x, u2, self.y, s2, z1_2, FieldElement::ONE)
+    }
+
+    /// adds two points for the Montgomery ladder, with the add-2007-bl formulas and no branch on the points.
+    /// the ladder adds points that differ by the point being multiplied, so they are never equal and the
+    /// doubling case of add is left out. opposite points give a Z of 0, the identity, without any test,
+    /// and an identity operand (the ladder only meets one for the scalars 0 and 1) is handled by selecting the other point
+    pub(crate) fn add_ladder(&self, other: &JacobianPoint) -> Self {
+        let z1_2: FieldElement = self.z.square();
+        let z2_2: FieldElement = other.z.square();
+
+        let u1: FieldElement = self.x * z2_2;
+        let u2: FieldElement = other.x * z1_2;
+        let s1: FieldElement = self.y * other.z * z2_2;
+        let s2: FieldElement = other.y * self.z * z1_2;
+
+        let sum: JacobianPoint = self.add_distinct(other, u1, u2, s1, s2, z1_2, z2_2);
+        let sum: JacobianPoint = JacobianPoint::conditional_select(&sum, other, self.is_identity());
+
+        JacobianPoint::conditional_select(&sum, self, other.is_identity())
+    }
+
+    /// finishes an addition from X1 * Z2^2, X2 * Z1^2, Y1 * Z2^3 and Y2 * Z1^3
+    #[allow(clippy::too_many_arguments)]
+    fn add_with(&self, other: &JacobianPoint, u1: FieldElement, u2: FieldElement, s1: FieldElement, s2: FieldElement, z1_2: FieldElement, z2_2: FieldElement) -> Self {
+        // same x, the points are either equal or opposite
+        if u1 == u2 {
+            return if s1 == s2 { self.double() } else { JacobianPoint::identity() };
+        }
+
+        self.add_distinct(other, u1, u2, s1, s2, z1_2, z2_2)
+    }
+
+    /// the formulas of add_with for points that aren't equal, none of the values is tested
+    #[allow(clippy::too_many_arguments)]
+    fn add_distinct(&self, other: &JacobianPoint, u1: FieldElement, u2: FieldElement, s1: FieldElement, s2: FieldElement, z1_2: FieldElement, z2_2: FieldElement) -> Self {
+        let h: FieldElement = u2 - u1;
+        let i: FieldElement = (h + h).square();
+        let j: FieldElement = h * i;
+        let r: FieldElement = s2 - s1;
+        let r: FieldElement = r + r;
+        let v: FieldElement = u1 * i;
+
+        let x: FieldElement = r.square() - j - v - v;
+
+        let s1_j: FieldElement = s1 * j;
+        let y: FieldElement = r * (v - x) - (s1_j + s1_j);
+
+        // Z3 = ((Z1 + Z2)^2 - Z1^2 - Z2^2) * H
+        let z: FieldElement = ((self.z + other.z).square() - z1_2 - z2_2) * h;
+
+        JacobianPoint { x, y, z }
+    }
+
+    /// swaps two points when swap is true, without branching on swap
+    pub(crate) fn conditional_swap(a: &mut JacobianPoint, b: &mut JacobianPoint, swap: bool) {
+        FieldElement::conditional_swap(&mut a.x, &mut b.x, swap);
+        FieldElement::conditional_swap(&mut a.y, &mut b.y, swap);
+        FieldElement::conditional_swap(&mut a.z, &mut b.z, swap);
+    }
+
+    /// returns b when choice is true and a otherwise, without branching on choice
+    pub(crate) fn conditional_select(a: &JacobianPoint, b: &JacobianPoint, choice: bool) -> Self {
+        JacobianPoint {
+            x: FieldElement::conditional_select(&a.x, &b.x, choice),
+            y: FieldElement::conditional_select(&a.y, &b.y, choice),
+            z: FieldElement::conditional_select(&a.z, &b.z, choice)
+        }
+    }
+}
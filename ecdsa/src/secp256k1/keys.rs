@@ -0,0 +1,312 @@
+//! private and public keys, so that a private key is never a bare number that can be out of range or left in memory.
+//!
+//! a private key is a scalar in [1, n-1], drawn from the OS random number generator and written in hex or in
+//! WIF (Wallet Import Format): Base58Check of a version byte, the 32 bytes of the key and a 0x01 flag when the
+//! public key is compressed. its limbs are zeroed when it is dropped, and so are the bytes and strings it is written to.
+//! a public key is a point of the curve, never the identity
+
+use core::fmt;
+use std::str::FromStr;
+use sha256::Base58Error;
+use zeroize::{Zeroize, Zeroizing};
+use super::{get_curve, Point, PointError, Scalar, SchnorrSignature, Signature, XOnlyPublicKey};
+use super::schnorr::schnorr_sign_scalar;
+use super::signature::sign_digest_scalar;
+
+/// The flag that follows the key in WIF when the public key is written compressed
+const WIF_COMPRESSED: u8 = 0x01;
+
+/// Reasons for which a key can't be generated or read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    /// The OS random number generator failed
+    Rng(getrandom::Error),
+
+    /// The private key is 0 or isn't below n
+    OutOfRange,
+
+    /// The string isn't 32 bytes written in hex
+    InvalidHex,
+
+    /// The string isn't Base58Check
+    InvalidBase58(Base58Error),
+
+    /// The data isn't a version byte, 32 bytes and an optional compressed flag
+    InvalidWif,
+
+    /// The WIF is from another network
+    WrongNetwork { version: u8 },
+
+    /// The public key isn't a point of the curve
+    InvalidPublicKey(PointError)
+}
+
+/// implement display for KeyError for easy printing of rejected keys
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Rng(error) => write!(f, "random number generator failed: {}", error),
+            KeyError::OutOfRange => write!(f, "private key is not in [1, n-1]"),
+            KeyError::InvalidHex => write!(f, "key is not 32 bytes in hex"),
+            KeyError::InvalidBase58(error) => write!(f, "invalid WIF: {}", error),
+            KeyError::InvalidWif => write!(f, "invalid WIF: wrong length or compressed flag"),
+            KeyError::WrongNetwork { version } => write!(f, "WIF is for another network: version {:02x}", version),
+            KeyError::InvalidPublicKey(error) => write!(f, "invalid public key: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// A private key, a scalar in [1, n-1] that is zeroed when dropped
+#[derive(Clone)]
+pub struct PrivateKey(Scalar);
+
+impl PrivateKey {
+    /// draws a private key from the OS random number generator. 32 random bytes that are 0 or n or more
+    /// are drawn again rather than reduced, so that every key in [1, n-1] is as likely
+    ///
+    /// # Returns
+    /// The private key, an error if the random number generator failed
+    ///
+    pub fn generate() -> Result<Self, KeyError> {
+        let mut bytes: Zeroizing<[u8; 32]> = Zeroizing::new([0; 32]);
+
+        loop {
+            getrandom::getrandom(bytes.as_mut()).map_err(KeyError::Rng)?;
+
+            if let Ok(private_key) = PrivateKey::from_bytes(&bytes) {
+                return Ok(private_key);
+            }
+        }
+    }
+
+    /// reads a private key from its 32 big-endian bytes
+    ///
+    /// # Arguments
+    /// * `bytes` - The 32 bytes of the private key
+    ///
+    /// # Returns
+    /// The private key, an error if it isn't in [1, n-1]
+    ///
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self, KeyError> {
+        Scalar::from_bytes(bytes)
+            .filter(|d| !d.is_zero())
+            .map(PrivateKey)
+            .ok_or(KeyError::OutOfRange)
+    }
+
+    /// returns the 32 big-endian bytes of the private key, zeroed when dropped
+    pub fn to_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.0.to_bytes())
+    }
+
+    /// reads a private key from the hex of its 32 bytes
+    ///
+    /// # Arguments
+    /// * `hex` - A string slice of 64 hexadecimal characters
+    ///
+    /// # Returns
+    /// The private key, an error if the string isn't 32 bytes in hex or the key isn't in [1, n-1]
+    ///
+    pub fn from_hex(hex: &str) -> Result<Self, KeyError> {
+        let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(sha256::from_hex(hex).ok_or(KeyError::InvalidHex)?);
+        let bytes: &[u8; 32] = bytes.as_slice().try_into().map_err(|_| KeyError::InvalidHex)?;
+
+        PrivateKey::from_bytes(bytes)
+    }
+
+    /// returns the hex of the 32 bytes of the private key, zeroed when dropped
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(sha256::to_hex(self.to_bytes().as_ref()))
+    }
+
+    /// reads a private key from WIF, with or without the compressed flag
+    ///
+    /// # Arguments
+    /// * `wif` - A string slice that holds the WIF
+    /// * `version` - The version byte of the network the key is for
+    ///
+    /// # Returns
+    /// The private key, an error if the string isn't WIF of a key in [1, n-1] for the network
+    ///
+    pub fn from_wif(wif: &str, version: u8) -> Result<Self, KeyError> {
+        let payload: Zeroizing<Vec<u8>> = Zeroizing::new(sha256::base58check_decode(wif).map_err(KeyError::InvalidBase58)?);
+
+        let bytes: &[u8; 32] = match payload.len() {
+            33 => payload[1..].try_into().unwrap(),
+            34 if payload[33] == WIF_COMPRESSED => payload[1..33].try_into().unwrap(),
+            _ => return Err(KeyError::InvalidWif)
+        };
+
+        if payload[0] != version {
+            return Err(KeyError::WrongNetwork { version: payload[0] });
+        }
+
+        PrivateKey::from_bytes(bytes)
+    }
+
+    /// returns the WIF of the private key with the compressed flag, zeroed when dropped.
+    /// keys are only written compressed since addresses are hashes of compressed public keys
+    ///
+    /// # Arguments
+    /// * `version` - The version byte of the network the key is for
+    ///
+    pub fn to_wif(&self, version: u8) -> Zeroizing<String> {
+        let mut payload: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(34));
+        payload.push(version);
+        payload.extend_from_slice(self.to_bytes().as_ref());
+        payload.push(WIF_COMPRESSED);
+
+        Zeroizing::new(sha256::base58check_encode(&payload))
+    }
+
+    /// returns the public key of the private key, d * G
+    pub fn get_public_key(&self) -> PublicKey {
+        PublicKey(get_curve().g.multiply_secret_scalar(&self.0))
+    }
+
+    /// returns the BIP-340 public key of the private key
+    pub fn get_x_only_public_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from(&get_curve().g.multiply_secret_scalar(&self.0))
+    }
+
+    /// signs a 32 bytes message digest with ECDSA, as sign_digest
+    ///
+    /// # Arguments
+    /// * `digest` - The 32 bytes digest of the message, usually its SHA-256
+    ///
+    /// # Returns
+    /// The signature, with a low s
+    ///
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> Signature {
+        sign_digest_scalar(digest, &self.0)
+    }
+
+    /// signs a message with BIP-340 Schnorr, as schnorr_sign
+    ///
+    /// # Arguments
+    /// * `message` - A byte slice that holds the message, usually a 32 bytes digest
+    /// * `aux_rand` - The 32 auxiliary random bytes
+    ///
+    /// # Returns
+    /// The signature
+    ///
+    pub fn sign_schnorr(&self, message: &[u8], aux_rand: &[u8; 32]) -> SchnorrSignature {
+        schnorr_sign_scalar(message, &self.0, aux_rand)
+    }
+}
+
+/// the limbs are zeroed so that the key doesn't stay in freed memory
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// the key itself is never printed, not even by accident in a log
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PrivateKey(..)")
+    }
+}
+
+/// A public key, a point of the curve that isn't the identity
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PublicKey(Point);
+
+impl PublicKey {
+    /// reads a public key from its compressed or uncompressed SEC1 encoding
+    ///
+    /// # Arguments
+    /// * `bytes` - A byte slice that holds the encoding
+    ///
+    /// # Returns
+    /// The public key, an error if the bytes aren't the encoding of a point of the curve
+    ///
+    pub fn from_sec1(bytes: &[u8]) -> Result<Self, KeyError> {
+        let point: Point = Point::from_sec1(bytes).map_err(KeyError::InvalidPublicKey)?;
+        PublicKey::try_from(point)
+    }
+
+    /// returns the SEC1 encoding of the public key, 33 bytes compressed or 65 bytes uncompressed
+    pub fn to_sec1(&self, compressed: bool) -> Vec<u8> {
+        self.0.to_sec1(compressed)
+    }
+
+    /// returns the point of the public key
+    pub fn as_point(&self) -> &Point {
+        &self.0
+    }
+}
+
+/// checks that the point is on the curve, which the identity isn't
+impl TryFrom<Point> for PublicKey {
+    type Error = KeyError;
+
+    fn try_from(point: Point) -> Result<Self, Self::Error> {
+        if !point.is_on_curve() {
+            return Err(KeyError::InvalidPublicKey(PointError::NotOnCurve));
+        }
+
+        Ok(PublicKey(point))
+    }
+}
+
+impl From<PublicKey> for Point {
+    fn from(public_key: PublicKey) -> Self {
+        public_key.0
+    }
+}
+
+/// displays the public key as the hex of its compressed SEC1 encoding
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// parses a public key from the hex of its compressed or uncompressed SEC1 encoding
+impl FromStr for PublicKey {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PublicKey::from_sec1(&sha256::from_hex(s).ok_or(KeyError::InvalidHex)?)
+    }
+}
+
+/// A private key with its public key, which is computed once
+#[derive(Clone, Debug)]
+pub struct KeyPair {
+    private_key: PrivateKey,
+    public_key: PublicKey
+}
+
+impl KeyPair {
+    /// draws a private key from the OS random number generator, as PrivateKey::generate
+    ///
+    /// # Returns
+    /// The key pair, an error if the random number generator failed
+    ///
+    pub fn generate() -> Result<Self, KeyError> {
+        Ok(KeyPair::from(PrivateKey::generate()?))
+    }
+
+    /// returns the private key
+    pub fn get_private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+
+    /// returns the public key
+    pub fn get_public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+}
+
+/// the pair of a private key and the public key it gives
+impl From<PrivateKey> for KeyPair {
+    fn from(private_key: PrivateKey) -> Self {
+        let public_key: PublicKey = private_key.get_public_key();
+        KeyPair { private_key, public_key }
+    }
+}
@@ -3,7 +3,6 @@ const P: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2
 const N: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141";
 const X: &str = "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
 const Y: &str = "483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8";
-const FP: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F"; // prime field for points
 
 pub const W: u32 = 4; // width used for precomps
 
@@ -11,11 +10,11 @@ pub const W: u32 = 4; // width used for precomps
 /// precomputes the points for the curve only once and stores it in a static ref. 
 /// It's initialized on the first access and not computed again.
 mod precomputed_points {
-    use super::{point::precompute_points, Secp256k1, Point, W};
+    use super::{point::precompute_points, get_curve, Point, W};
 
     pub static PRECOMPUTED_POINTS: once_cell::sync::Lazy<Vec<Point>> =
         once_cell::sync::Lazy::new(|| {
-            precompute_points(Secp256k1::new().g, W)
+            precompute_points(get_curve().g.clone(), W)
         });
 }
 
@@ -24,10 +23,35 @@ pub fn get_curve_precomputed_points() -> &'static Vec<Point> {
     &precomputed_points::PRECOMPUTED_POINTS
 }
 
+/// parses the curve's parameters only once
+mod constants {
+    use super::Secp256k1;
+
+    pub static CURVE: once_cell::sync::Lazy<Secp256k1> = once_cell::sync::Lazy::new(Secp256k1::new);
+}
+
+/// returns a reference to the secp256k1 parameters, parsed on the first access
+pub fn get_curve() -> &'static Secp256k1 {
+    &constants::CURVE
+}
+
+mod batch;
 mod curve;
+mod field;
+mod jacobian;
+mod keys;
 mod point;
 mod signature;
+mod rfc6979;
+mod scalar;
+mod schnorr;
 
+pub use batch::{verify_batch, verify_batch_parallel, BatchItem};
 pub use curve::Secp256k1;
-pub use point::Point;
-pub use signature::{Signature, sign, verify_signature};
+pub use field::FieldElement;
+pub use keys::{KeyError, KeyPair, PrivateKey, PublicKey};
+pub use point::{Point, PointError};
+pub use scalar::Scalar;
+pub use schnorr::{schnorr_sign, schnorr_verify, x_only_public_key, SchnorrSignature, XOnlyPublicKey};
+pub use signature::{Signature, sign, sign_digest, verify_digest, verify_signature};
+pub use rfc6979::{rfc6979_nonce, NonceGenerator};
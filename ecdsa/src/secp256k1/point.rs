@@ -1,66 +1,110 @@
 use core::fmt;
 use std::hash::Hash;
 use num_bigint::BigInt;
-use num_traits::zero;
-use serde::ser::{Serialize, Serializer, SerializeStruct};
-use crate::{math::{modular_multiplicative_inverse, modulo, bigint, calculate_wnaf}, secp256k1::FP};
-use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use super::{jacobian::JacobianPoint, FieldElement, Scalar};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use zeroize::Zeroize;
 
-#[derive(Eq, PartialEq, Hash, Clone)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
 pub struct Point {
-    pub x: BigInt,
-    pub y: BigInt
+    pub x: FieldElement,
+    pub y: FieldElement
     // fp prime field is now in curve or a constant from mod.rs
 }
 
+/// The first byte of a compressed point whose y is even, odd y gives 0x03
+const SEC1_COMPRESSED_EVEN: u8 = 0x02;
+
+/// The first byte of an uncompressed point
+const SEC1_UNCOMPRESSED: u8 = 0x04;
+
+/// Reasons for which coordinates or an encoding don't give a point of the curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointError {
+    /// The encoding has an unknown first byte or the wrong length for it
+    InvalidEncoding,
+
+    /// A coordinate is P or more
+    InvalidCoordinate,
+
+    /// The coordinates don't satisfy y^2 = x^3 + 7, or no point has the x of a compressed encoding
+    NotOnCurve
+}
+
+/// implement display for PointError for easy printing of rejected public keys
+impl fmt::Display for PointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointError::InvalidEncoding => write!(f, "invalid SEC1 encoding"),
+            PointError::InvalidCoordinate => write!(f, "coordinate is not below the field prime"),
+            PointError::NotOnCurve => write!(f, "point is not on secp256k1")
+        }
+    }
+}
+
+impl std::error::Error for PointError {}
+
 /// implement display for Point
-/// displays the point as x_y
+/// displays the point as the hex of its compressed SEC1 encoding
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "x{}_y{}", self.x, self.y)
+        write!(f, "{}", sha256::to_hex(&self.to_sec1(true)))
     }
 }
 
 /// implement for serialization for Point
-/// manual implementation needed because BigInt is not directly serializable
-/// implementation is done by serializing the bigint as a hex string
+/// implementation is done by serializing the compressed SEC1 encoding,
+/// as a hex string for human readable formats such as JSON and as raw bytes otherwise
 impl Serialize for Point {
 
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer 
     {
-        let mut state = serializer.serialize_struct("Point", 2)?;
+        let bytes: Vec<u8> = self.to_sec1(true);
+
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&sha256::to_hex(&bytes))
+        } else {
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+/// visitor reading a point from its SEC1 encoding, as a hex string or as raw bytes
+struct PointVisitor;
+
+impl<'de> Visitor<'de> for PointVisitor {
+    type Value = Point;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a SEC1 encoded point of the curve")
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<Point, E> {
+        let bytes: Vec<u8> = sha256::from_hex(hex).ok_or_else(|| E::custom(format!("invalid point {}: not hex", hex)))?;
+        self.visit_bytes(&bytes)
+    }
 
-        // encode bigint as hex
-        state.serialize_field("x", &format!("{:x}", &self.x))?; 
-        state.serialize_field("y", &format!("{:x}", &self.y))?;
-        state.end()
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Point, E> {
+        Point::from_sec1(bytes).map_err(|e| E::custom(format!("invalid point {}: {}", sha256::to_hex(bytes), e)))
     }
 }
 
 /// implement for deserialization for Point
-/// manual implementation needed because BigInt is not directly deserializable
-/// implementation is done by deserializing the hex string as a bigint
+/// implementation is done by decoding the SEC1 encoding, compressed or not,
+/// an encoding that isn't valid or a point that isn't on the curve is an error
 impl<'de> Deserialize<'de> for Point {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de> 
     {
-        // need to use intermediary struct with strings because 
-        // bigint is not directly serializable and desrializable
-        #[derive(serde::Deserialize)]
-        struct PointFields {
-            x: String,
-            y: String
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(PointVisitor)
+        } else {
+            deserializer.deserialize_bytes(PointVisitor)
         }
-
-        let fields: PointFields = PointFields::deserialize(deserializer)?;
-
-        Ok(Point {
-            x: bigint(&fields.x),
-            y: bigint(&fields.y)
-        })
     }
 }
 
@@ -69,119 +113,247 @@ impl Point {
     /// returns the point multiplied by n using non-adjacent scalar representation.
     /// https://en.wikipedia.org/wiki/Non-adjacent_form
     ///
-    /// it allows for much less additions and doubling, especially using pre comps
-    /// 
+    /// it allows for much less additions and doubling, especially using pre comps.
+    /// the point is kept in jacobian coordinates during the multiplication so there is only one inversion
+    ///
     /// # Arguments
-    /// * `n` - A BigInt that is the number to multiply the point by
+    /// * `n` - A BigInt that is the number to multiply the point by, taken mod n
     /// * `width` - A u32 that is the width of the window
     /// * `pre_comp` - A reference to a Vec<Point> that is the precomputed points
-    /// 
+    ///
     /// # Returns
     /// A Point that is the result of the multiplication
-    /// 
+    ///
     pub fn multiply(self, n: BigInt, width: u32, pre_comp: &std::vec::Vec<Point>) -> Point {
-        let wnaf: Vec<i8> = calculate_wnaf(width, n);
+        Point::wnaf_multiply(&Scalar::from(&n), width, pre_comp).to_affine()
+    }
 
-        let mut q: Point = Point::identity();
+    /// the wnaf multiplication of multiply, the result stays in jacobian coordinates so that
+    /// several products can be added together before the only inversion
+    pub(crate) fn wnaf_multiply(n: &Scalar, width: u32, pre_comp: &[Point]) -> JacobianPoint {
+        let wnaf: Vec<i8> = n.wnaf(width);
 
-        let mut i: i32 = (wnaf.len() as i32) - 1;
+        let mut q: JacobianPoint = JacobianPoint::identity();
 
-        while i > -1 {
+        for &digit in wnaf.iter().rev() {
             q = q.double();
 
-            let n: usize = i as usize;
+            if digit > 0 {
+                q = q.add_affine(&pre_comp[((digit - 1) / 2) as usize]);
+            } else if digit < 0 {
+                q = q.add_affine(&pre_comp[((-digit - 1) / 2) as usize].negate());
+            }
+        }
+
+        q
+    }
+
+    /// multiplies several points by several scalars and adds the products, ie. n1 * P1 + n2 * P2 + ...,
+    /// with Strauss' algorithm (Shamir's trick for two points): the wnaf digits of every scalar are read
+    /// together so the doublings are shared, there are as many as for a single multiplication.
+    /// https://en.wikipedia.org/wiki/Elliptic_curve_point_multiplication#Shamir's_trick
+    ///
+    /// # Arguments
+    /// * `terms` - A slice of the scalars with the precomputed points of the point they multiply
+    /// * `width` - A u32 that is the width of the window the points were precomputed with
+    ///
+    /// # Returns
+    /// A JacobianPoint that is the sum of the products
+    ///
+    pub(crate) fn strauss_multiply(terms: &[(&Scalar, &[Point])], width: u32) -> JacobianPoint {
+        let wnafs: Vec<Vec<i8>> = terms.iter().map(|(n, _)| n.wnaf(width)).collect();
+        let len: usize = wnafs.iter().map(Vec::len).max().unwrap_or(0);
 
-            if wnaf[n] > 0 {
-                let d: i8 = (wnaf[n] - 1) / 2;
+        let mut q: JacobianPoint = JacobianPoint::identity();
 
-                q = q.add(&pre_comp[d as usize]);
-            } else if wnaf[n] < 0 {
-                let d: i8 = (-wnaf[n] - 1) / 2;
+        for i in (0..len).rev() {
+            q = q.double();
 
-                let z: Point = Point {
-                    x: pre_comp[d as usize].x.clone(),
-                    y: pre_comp[d as usize].y.clone() * -1
-                };
+            for (wnaf, (_, pre_comp)) in wnafs.iter().zip(terms) {
+                let digit: i8 = wnaf.get(i).copied().unwrap_or(0);
 
-                q = q.add(&z);
+                if digit > 0 {
+                    q = q.add_affine(&pre_comp[((digit - 1) / 2) as usize]);
+                } else if digit < 0 {
+                    q = q.add_affine(&pre_comp[((-digit - 1) / 2) as usize].negate());
+                }
             }
-
-            i = i - 1;
         }
 
         q
-    } 
+    }
 
-    /// returns the identity point (0, 0)
-    pub fn identity() -> Self {
-        Point {
-            x: zero(),
-            y: zero()
+    /// multiplies the point by a secret scalar, such as a private key or a signing nonce, with a Montgomery ladder.
+    /// https://en.wikipedia.org/wiki/Elliptic_curve_point_multiplication#Montgomery_ladder
+    ///
+    /// every bit of the scalar costs one addition and one doubling whatever its value, the points are swapped
+    /// without branching and the field operations and the inversion don't depend on the values either.
+    /// multiply is faster and stays for public scalars, when verifying signatures
+    ///
+    /// # Arguments
+    /// * `k` - A reference to a BigInt that is the secret scalar, taken mod n
+    ///
+    /// # Returns
+    /// A Point that is the result of the multiplication
+    ///
+    pub fn multiply_secret(&self, k: &BigInt) -> Point {
+        self.multiply_secret_scalar(&Scalar::from(k))
+    }
+
+    /// the ladder of multiply_secret, for a scalar that is already reduced
+    pub(crate) fn multiply_secret_scalar(&self, k: &Scalar) -> Point {
+        // k + n or k + 2n, whose bit 256 is set, so the ladder always starts from the point itself,
+        // does 256 steps and only goes through the identity for the scalars 0 and 1
+        let mut scalar: [u64; 4] = k.ladder_limbs();
+
+        let mut r0: JacobianPoint = JacobianPoint::from_affine(self);
+        let mut r1: JacobianPoint = r0.double();
+
+        // r1 is always r0 + P, each bit picks which of them is doubled into r0
+        for i in (0..256).rev() {
+            let bit: bool = (scalar[i / 64] >> (i % 64)) & 1 == 1;
+
+            JacobianPoint::conditional_swap(&mut r0, &mut r1, bit);
+            r1 = r0.add_ladder(&r1);
+            r0 = r0.double();
+            JacobianPoint::conditional_swap(&mut r0, &mut r1, bit);
         }
+
+        // the limbs are the secret scalar plus a public constant
+        scalar.zeroize();
+
+        r0.to_affine()
     }
 
-    /// doubles a point ie, it adds the point to itself (mod fp) using these formulas
-    /// L = [ (3*X^2) / 2*Y ] mod P
-    /// Xr = [ L^2 - 2*X ] mod P
-    /// Yr = [ L*(X - Xr) - Y ] mod P
+    /// returns the point with the given x coordinate and an even y, the lift_x function of BIP-340
+    /// 
+    /// # Arguments
+    /// * `x` - A reference to a FieldElement that is the x coordinate
     /// 
     /// # Returns
-    /// A Point that is the result of the doubling
+    /// The point, None if no point of the curve has this x coordinate
     /// 
-    fn double(&self) -> Point {
-        let fp: &BigInt = &bigint(FP);
+    pub fn lift_x(x: &FieldElement) -> Option<Point> {
+        // y^2 = x^3 + 7
+        let y: FieldElement = (x.square() * *x + FieldElement::from(7)).sqrt()?;
+
+        Some(Point {
+            x: *x,
+            y: if y.is_odd() { -y } else { y }
+        })
+    }
+
+    /// returns the SEC1 encoding of the point: 0x04 followed by x and y when uncompressed,
+    /// 0x02 or 0x03 as y is even or odd followed by x when compressed, and the single byte 0x00 for the identity.
+    /// https://www.secg.org/sec1-v2.pdf section 2.3.3
+    ///
+    /// # Arguments
+    /// * `compressed` - A bool that is true for the 33 bytes encoding and false for the 65 bytes one
+    ///
+    /// # Returns
+    /// A Vec<u8> that is the encoding of the point
+    ///
+    pub fn to_sec1(&self, compressed: bool) -> Vec<u8> {
+        if self.is_identity() {
+            return vec![0x00];
+        }
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(if compressed { 33 } else { 65 });
+
+        if compressed {
+            bytes.push(SEC1_COMPRESSED_EVEN | self.y.is_odd() as u8);
+            bytes.extend_from_slice(&self.x.to_bytes());
+        } else {
+            bytes.push(SEC1_UNCOMPRESSED);
+            bytes.extend_from_slice(&self.x.to_bytes());
+            bytes.extend_from_slice(&self.y.to_bytes());
+        }
+
+        bytes
+    }
+
+    /// reads a point from its SEC1 encoding, compressed or not. the y of a compressed point is
+    /// recovered from y^2 = x^3 + 7 with a square root, the one with the parity the first byte gives
+    ///
+    /// # Arguments
+    /// * `bytes` - A byte slice that holds the encoding
+    ///
+    /// # Returns
+    /// The point, or why the bytes don't encode a point of the curve
+    ///
+    pub fn from_sec1(bytes: &[u8]) -> Result<Point, PointError> {
+        let coordinate = |bytes: &[u8]| FieldElement::from_bytes(bytes.try_into().unwrap()).ok_or(PointError::InvalidCoordinate);
+
+        match (bytes.first(), bytes.len()) {
+            (Some(0x00), 1) => Ok(Point::identity()),
+            (Some(&prefix), 33) if prefix & !1 == SEC1_COMPRESSED_EVEN => {
+                let point: Point = Point::lift_x(&coordinate(&bytes[1..])?).ok_or(PointError::NotOnCurve)?;
+
+                // lift_x gives the even y
+                if prefix & 1 == 1 { Ok(point.negate()) } else { Ok(point) }
+            },
+            (Some(&SEC1_UNCOMPRESSED), 65) => Point::new(coordinate(&bytes[1..33])?, coordinate(&bytes[33..])?),
+            _ => Err(PointError::InvalidEncoding)
+        }
+    }
+
+    /// returns the point of the given coordinates, checking that it is on the curve.
+    /// the points built with the public fields aren't checked, so everything read from outside goes through here
+    ///
+    /// # Arguments
+    /// * `x` - A FieldElement that is the x coordinate
+    /// * `y` - A FieldElement that is the y coordinate
+    ///
+    /// # Returns
+    /// The point, NotOnCurve if y^2 isn't x^3 + 7
+    ///
+    pub fn new(x: FieldElement, y: FieldElement) -> Result<Point, PointError> {
+        let point: Point = Point { x, y };
+        if point.is_on_curve() { Ok(point) } else { Err(PointError::NotOnCurve) }
+    }
 
-        // we use the modular multiplicative inverse to not have to divide
-        let lambda: BigInt = modulo(&(3 * &self.x * &self.x 
-            * modular_multiplicative_inverse(fp, 2 * &self.y, None, None)), 
-            fp);
-        let rx: BigInt = modulo(&(&lambda * &lambda - &self.x - &self.x), fp);
-        let ry: BigInt = modulo(&(lambda * (&self.x - &rx) - &self.y), fp);
+    /// returns true if the point satisfies y^2 = x^3 + 7. the order of the curve is prime, so there is no
+    /// small subgroup and every point of the curve is in the group of G. the identity (0, 0) isn't on the curve
+    pub fn is_on_curve(&self) -> bool {
+        self.y.square() == self.x.square() * self.x + FieldElement::from(7)
+    }
 
+    /// returns the identity point (0, 0)
+    pub fn identity() -> Self {
         Point {
-            x: rx,
-            y: ry
+            x: FieldElement::ZERO,
+            y: FieldElement::ZERO
         }
     }
 
-    /// add implementation adds a point to another using following formulas
-    /// L = [ (Y' - Y) / (X' - X) ] mod P
-    /// Xr = [ L^2 - X - X' ] mod P
-    /// Yr = [ L*(X - Xr) - Y ] mod P
-    /// 
+    /// returns true if the point is the identity (0, 0)
+    pub fn is_identity(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero()
+    }
+
+    /// returns the opposite point, (x, -y)
+    fn negate(&self) -> Point {
+        Point {
+            x: self.x,
+            y: -self.y
+        }
+    }
+
+    /// add implementation adds a point to another, the identity and opposite points included
+    ///
     /// # Arguments
     /// * `other` - A reference to a Point that is the point to add
-    /// 
+    ///
     /// # Returns
     /// A Point that is the result of the addition
-    /// 
+    ///
     pub fn add(self, other: &Point) -> Point {
-        if self.x == other.x && self.y == (&other.y * -1) { // check P2 = -P1, vertical line, thus P1 + P2 = 0
-            Point::identity()
-        } else if self.x == other.x && self.y == other.y { // P1 == P2, use point doubling
-            self.double()
-        } else if self.x == zero() && self.y == zero() { // 0 + P2 = P2
-            other.clone()
-        } else if other.x == zero() && other.y == zero() { // P1 + 0 = P1
-            self
-        } else {
-            let fp: &BigInt = &bigint(FP);
-            let lambda: BigInt = modulo(
-                &((&other.y - &self.y) 
-                * modular_multiplicative_inverse(fp, &other.x - &self.x, None, None)
-            ), fp);
-            let rx: BigInt = modulo(&(&lambda * &lambda - &other.x - &self.x), fp);
-            let ry: BigInt = modulo(&(lambda * (&self.x - &rx) - &self.y), fp);
-
-            Point {
-                x: rx,
-                y: ry
-            }
-        }
+        JacobianPoint::from_affine(&self).add_affine(other).to_affine()
     }
 }
 
-/// precomputes the points for the sec256k1 curve at the given point
+/// precomputes the points for the sec256k1 curve at the given point, ie. the odd multiples q, 3q, 5q...
+/// they are computed in jacobian coordinates and converted together with a single inversion
 /// 
 /// # Arguments
 /// * `q` - A Point that is the point from which to precompute
@@ -190,16 +362,36 @@ impl Point {
 /// # Returns
 /// A Vec<Point> that is the precomputed points
 /// 
-pub fn precompute_points(mut q: Point, w: u32) -> Vec<Point> {
-    let mut p: Vec<Point> = vec![q.clone()];
+pub fn precompute_points(q: Point, w: u32) -> Vec<Point> {
+    precompute_tables(&[&q], w).remove(0)
+}
+
+/// precomputes the points of several points, all of them being converted to affine with a single inversion
+/// 
+/// # Arguments
+/// * `points` - A slice of the points from which to precompute
+/// * `w` - A u32 that is the width of the window
+/// 
+/// # Returns
+/// A Vec<Vec<Point>> that is the precomputed points of every point, in the same order
+/// 
+pub(crate) fn precompute_tables(points: &[&Point], w: u32) -> Vec<Vec<Point>> {
+    let size: usize = 1 << (w - 1);
+    let mut jacobian: Vec<JacobianPoint> = Vec::with_capacity(points.len() * size);
 
-    q = q.double();
+    for point in points {
+        let q: JacobianPoint = JacobianPoint::from_affine(point);
+        let double: JacobianPoint = q.double();
 
-    for j in 1..(1 << (w - 1)) {
-        let mut buffer: Point = q.clone();
-        buffer = buffer.add(&p[(j - 1) as usize]);
-        p.push(buffer);
+        jacobian.push(q);
+        for _ in 1..size {
+            let buffer: JacobianPoint = double.add(&jacobian[jacobian.len() - 1]);
+            jacobian.push(buffer);
+        }
     }
 
-    p
-}
\ No newline at end of file
+    JacobianPoint::batch_to_affine(&jacobian)
+        .chunks(size)
+        .map(<[Point]>::to_vec)
+        .collect()
+}
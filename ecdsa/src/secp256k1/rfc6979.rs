@@ -0,0 +1,103 @@
+//! deterministic nonces for ECDSA, as described in RFC 6979 section 3.2 with HMAC-SHA256
+//! https://www.rfc-editor.org/rfc/rfc6979#section-3.2
+//!
+//! the nonce is derived from the private key and the digest of the message, so signing never
+//! depends on the quality of a random number generator and the same message always gets the same signature.
+//! the key and the nonces are scalars read straight from the HMAC output, never BigInts, and every
+//! buffer that holds them is zeroed once it isn't needed
+
+use sha256::HmacSha256;
+use zeroize::Zeroize;
+use super::Scalar;
+
+/// Generator of the candidate nonces for a private key and a message digest, in the order of RFC 6979.
+/// the first one is the nonce to use, the next ones are only needed if a candidate gives a zero r or s
+pub struct NonceGenerator {
+    /// The HMAC key
+    k: [u8; 32],
+
+    /// The chaining value, a candidate nonce is read from it
+    v: [u8; 32]
+}
+
+impl NonceGenerator {
+    /// returns the generator seeded with the private key and the message digest
+    ///
+    /// # Arguments
+    /// * `private_key` - A reference to the scalar that is the private key
+    /// * `digest` - The 32 bytes digest of the message to sign
+    ///
+    pub fn new(private_key: &Scalar, digest: &[u8; 32]) -> Self {
+        let mut x: [u8; 32] = private_key.to_bytes();
+        let h1: [u8; 32] = Scalar::from_bytes_reduced(digest).to_bytes();
+
+        let mut generator: NonceGenerator = NonceGenerator { k: [0; 32], v: [1; 32] };
+
+        for separator in [0x00, 0x01] {
+            let mut mac: HmacSha256 = HmacSha256::new(&generator.k);
+            mac.update(&generator.v);
+            mac.update(&[separator]);
+            mac.update(&x);
+            mac.update(&h1);
+
+            generator.k = mac.finalize();
+            generator.v = sha256::hmac(&generator.k, &generator.v);
+        }
+
+        x.zeroize();
+        generator
+    }
+
+    /// Moves K and V on after a candidate was read, so that the next one is different
+    ///
+    /// # Modifications
+    /// * Changes the generator's K and V, hence the mut self
+    ///
+    fn reseed(&mut self) {
+        let mut mac: HmacSha256 = HmacSha256::new(&self.k);
+        mac.update(&self.v);
+        mac.update(&[0x00]);
+
+        self.k = mac.finalize();
+        self.v = sha256::hmac(&self.k, &self.v);
+    }
+}
+
+/// K and V are secrets, anyone that knows them can compute the nonces
+impl Drop for NonceGenerator {
+    fn drop(&mut self) {
+        self.k.zeroize();
+        self.v.zeroize();
+    }
+}
+
+/// the candidates never run out, next always returns Some.
+/// a candidate is rejected if it is 0 or n or more, which happens with a probability of about 2^-128
+impl Iterator for NonceGenerator {
+    type Item = Scalar;
+
+    fn next(&mut self) -> Option<Scalar> {
+        loop {
+            self.v = sha256::hmac(&self.k, &self.v);
+            let candidate: Option<Scalar> = Scalar::from_bytes(&self.v).filter(|k| !k.is_zero());
+            self.reseed();
+
+            if candidate.is_some() {
+                return candidate;
+            }
+        }
+    }
+}
+
+/// returns the RFC 6979 nonce for signing a message digest with a private key
+///
+/// # Arguments
+/// * `private_key` - A reference to the scalar that is the private key
+/// * `digest` - The 32 bytes digest of the message to sign
+///
+/// # Returns
+/// A scalar in [1, n-1] that is the nonce
+///
+pub fn rfc6979_nonce(private_key: &Scalar, digest: &[u8; 32]) -> Scalar {
+    NonceGenerator::new(private_key, digest).next().unwrap()
+}
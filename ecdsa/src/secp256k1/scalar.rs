@@ -0,0 +1,340 @@
+//! integers modulo the order n of the curve: private keys, nonces and the r and s of signatures.
+//!
+//! a scalar is four 64 bits limbs, least significant first, always kept in [0, n-1]. like field elements,
+//! nothing is allocated and no operation branches on the values. 2^256 - n only has 129 bits, so a 512 bits
+//! product is reduced by folding its high half times 2^256 - n into the low one a few times
+
+use core::fmt;
+use core::ops::{Add, Mul, Neg, Sub};
+use num_bigint::{BigInt, Sign};
+use zeroize::Zeroize;
+use crate::math::{add_limbs, adc, limbs_from_bytes, limbs_to_bytes, mac, modulo, mul_limbs, select_limbs, sub_limbs, to_bytes_32};
+
+/// The order n, least significant limb first
+const N: [u64; 4] = [0xBFD25E8CD0364141, 0xBAAEDCE6AF48A03B, 0xFFFFFFFFFFFFFFFE, 0xFFFFFFFFFFFFFFFF];
+
+/// 2^256 - n, ie. 2^256 mod n
+const N_COMPLEMENT: [u64; 3] = [0x402DA1732FC9BEBF, 0x4551231950B75FC4, 0x1];
+
+/// n / 2 rounded down, the greatest low scalar
+const HALF_N: [u64; 4] = [0xDFE92F46681B20A0, 0x5D576E7357A4501D, 0xFFFFFFFFFFFFFFFF, 0x7FFFFFFFFFFFFFFF];
+
+/// n - 2, the exponent that inverts a scalar
+const N_MINUS_2: [u64; 4] = [0xBFD25E8CD036413F, 0xBAAEDCE6AF48A03B, 0xFFFFFFFFFFFFFFFE, 0xFFFFFFFFFFFFFFFF];
+
+/// An integer modulo the order of the curve
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Scalar([u64; 4]);
+
+impl Scalar {
+    /// The scalar 0
+    pub const ZERO: Scalar = Scalar([0; 4]);
+
+    /// The scalar 1
+    pub const ONE: Scalar = Scalar([1, 0, 0, 0]);
+
+    /// reads a scalar from its 32 big-endian bytes
+    ///
+    /// # Arguments
+    /// * `bytes` - The 32 bytes of the scalar
+    ///
+    /// # Returns
+    /// The scalar, None if the bytes are n or more
+    ///
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs: [u64; 4] = limbs_from_bytes(bytes);
+
+        // the subtraction borrows only if the value is below n
+        let (_, borrow) = sub_limbs(&limbs, &N);
+        if borrow == 1 { Some(Scalar(limbs)) } else { None }
+    }
+
+    /// reads 32 big-endian bytes as a number and reduces it modulo n, used for message digests and x coordinates
+    ///
+    /// # Arguments
+    /// * `bytes` - The 32 bytes of the number
+    ///
+    /// # Returns
+    /// The scalar of the number mod n
+    ///
+    pub fn from_bytes_reduced(bytes: &[u8; 32]) -> Self {
+        // any 256 bits number is below 2n
+        Scalar::reduce_once(&limbs_from_bytes(bytes), 0)
+    }
+
+    /// returns the 32 big-endian bytes of the scalar
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    /// returns the scalar as a BigInt in [0, n-1]
+    pub fn to_bigint(&self) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &self.to_bytes())
+    }
+
+    /// returns the scalar of a BigInt in [0, n-1]
+    ///
+    /// # Arguments
+    /// * `value` - A reference to a BigInt that is the value of the scalar
+    ///
+    /// # Returns
+    /// The scalar, None if the BigInt is negative or n or more
+    ///
+    pub fn from_bigint(value: &BigInt) -> Option<Self> {
+        if value.sign() == Sign::Minus || value.bits() > 256 {
+            return None;
+        }
+
+        Scalar::from_bytes(&to_bytes_32(value))
+    }
+
+    /// returns true if the scalar is 0
+    pub fn is_zero(&self) -> bool {
+        (self.0[0] | self.0[1] | self.0[2] | self.0[3]) == 0
+    }
+
+    /// returns true if the scalar is more than n/2, ie. if it is the high one of the scalar and its opposite
+    pub fn is_high(&self) -> bool {
+        let (_, borrow) = sub_limbs(&HALF_N, &self.0);
+        borrow == 1
+    }
+
+    /// returns the inverse of the scalar with Fermat's little theorem, ie. x^(n-2).
+    /// the exponent is public so the squarings and multiplications are the same whatever the scalar is.
+    /// the inverse of 0 is 0
+    pub fn invert(&self) -> Self {
+        let mut result: Scalar = Scalar::ONE;
+
+        for i in (0..256).rev() {
+            result = result * result;
+
+            if (N_MINUS_2[i / 64] >> (i % 64)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+
+        result
+    }
+
+    /// returns the inverses of several scalars with a single inversion, using Montgomery's trick:
+    /// the product of every scalar is inverted and the inverse of each scalar is taken out of it.
+    /// the inverse of 0 is 0, as with invert
+    ///
+    /// # Arguments
+    /// * `scalars` - A slice of the scalars to invert
+    ///
+    /// # Returns
+    /// A Vec<Scalar> of the inverses, in the same order
+    ///
+    pub(crate) fn batch_invert(scalars: &[Scalar]) -> Vec<Scalar> {
+        // prefixes[i] is the product of the scalars before the scalar i, the zeros being skipped
+        let mut prefixes: Vec<Scalar> = Vec::with_capacity(scalars.len());
+        let mut product: Scalar = Scalar::ONE;
+
+        for scalar in scalars {
+            prefixes.push(product);
+            if !scalar.is_zero() {
+                product = product * *scalar;
+            }
+        }
+
+        let mut inverse: Scalar = product.invert();
+        let mut inverses: Vec<Scalar> = vec![Scalar::ZERO; scalars.len()];
+
+        for (i, scalar) in scalars.iter().enumerate().rev() {
+            if scalar.is_zero() {
+                continue;
+            }
+
+            // inverse is the inverse of the product of the scalars up to this one
+            inverses[i] = inverse * prefixes[i];
+            inverse = inverse * *scalar;
+        }
+
+        inverses
+    }
+
+    /// returns the width-w non-adjacent form of the scalar, least significant digit first.
+    /// https://en.wikipedia.org/wiki/Non-adjacent_form
+    ///
+    /// every digit is 0 or odd in ]-2^(w-1), 2^(w-1)[ and a non zero digit is followed by at least w-1 zeros.
+    /// the number of digits depends on the scalar, it is only used with public scalars
+    ///
+    /// # Arguments
+    /// * `w` - A u32 that is the window size, at most 8
+    ///
+    /// # Returns
+    /// A Vec<i8> representation of the wnaf
+    ///
+    pub(crate) fn wnaf(&self, w: u32) -> Vec<i8> {
+        let mut wnaf: Vec<i8> = Vec::with_capacity(257);
+
+        // subtracting a negative digit can carry above 256 bits
+        let mut k: [u64; 5] = [self.0[0], self.0[1], self.0[2], self.0[3], 0];
+        let modulus: i64 = 1 << w;
+
+        while k != [0; 5] {
+            if k[0] & 1 == 1 {
+                let mut digit: i64 = (k[0] & (modulus as u64 - 1)) as i64;
+                if digit >= modulus / 2 {
+                    digit -= modulus;
+                }
+
+                if digit > 0 {
+                    // the low bits of k are the digit, there is no borrow
+                    k[0] -= digit as u64;
+                } else {
+                    let mut carry: u64 = (-digit) as u64;
+                    for limb in k.iter_mut() {
+                        (*limb, carry) = adc(*limb, 0, carry);
+                    }
+                }
+
+                wnaf.push(digit as i8);
+            } else {
+                wnaf.push(0);
+            }
+
+            for i in 0..4 {
+                k[i] = (k[i] >> 1) | (k[i + 1] << 63);
+            }
+            k[4] >>= 1;
+        }
+
+        wnaf
+    }
+
+    /// returns the low 256 bits of k + n or k + 2n, whichever has exactly 257 bits, for the Montgomery ladder.
+    /// both give the same point as k, and with the bit 256 always set the ladder does the same steps for every scalar
+    pub(crate) fn ladder_limbs(&self) -> [u64; 4] {
+        let (short, carry) = add_limbs(&self.0, &N);
+        let (long, _) = add_limbs(&short, &N);
+
+        // k + n has 257 bits when it carries, otherwise k + 2n does since k + n >= n > 2^255
+        select_limbs(&long, &short, carry)
+    }
+
+    /// brings a number below 2n, given as four limbs and the carry above them, in [0, n-1]
+    fn reduce_once(limbs: &[u64; 4], carry: u64) -> Self {
+        let (reduced, borrow) = sub_limbs(limbs, &N);
+
+        // the limbs are kept only if they are below n and there is no carry
+        Scalar(select_limbs(&reduced, limbs, borrow & (carry ^ 1)))
+    }
+
+    /// returns the low half plus the high half times 2^256 - n, which is the same number mod n
+    fn fold(wide: &[u64; 8]) -> [u64; 8] {
+        let mut folded: [u64; 8] = [0; 8];
+        folded[..4].copy_from_slice(&wide[..4]);
+
+        for (i, &high) in wide[4..].iter().enumerate() {
+            let mut carry: u64 = 0;
+
+            for (j, &c) in N_COMPLEMENT.iter().enumerate() {
+                (folded[i + j], carry) = mac(folded[i + j], high, c, carry);
+            }
+
+            for limb in folded[i + 3..].iter_mut() {
+                (*limb, carry) = adc(*limb, 0, carry);
+            }
+        }
+
+        folded
+    }
+
+    /// reduces a 512 bits number written as eight limbs modulo n
+    fn reduce_wide(wide: &[u64; 8]) -> Self {
+        // each fold shrinks the number: below 2^386, then 2^260, then 2^256 + 2^133 and finally 2^256
+        let mut folded: [u64; 8] = *wide;
+        for _ in 0..4 {
+            folded = Scalar::fold(&folded);
+        }
+
+        Scalar::reduce_once(&[folded[0], folded[1], folded[2], folded[3]], 0)
+    }
+}
+
+impl Add for Scalar {
+    type Output = Scalar;
+
+    fn add(self, other: Scalar) -> Scalar {
+        let (sum, carry) = add_limbs(&self.0, &other.0);
+        Scalar::reduce_once(&sum, carry)
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Scalar;
+
+    fn sub(self, other: Scalar) -> Scalar {
+        let (diff, borrow) = sub_limbs(&self.0, &other.0);
+
+        // n is added back when the difference went below 0
+        let (diff, _) = add_limbs(&diff, &select_limbs(&[0; 4], &N, borrow));
+        Scalar(diff)
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Scalar;
+
+    fn mul(self, other: Scalar) -> Scalar {
+        Scalar::reduce_wide(&mul_limbs(&self.0, &other.0))
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Scalar;
+
+    fn neg(self) -> Scalar {
+        Scalar::ZERO - self
+    }
+}
+
+/// converts a small number
+impl From<u64> for Scalar {
+    fn from(value: u64) -> Self {
+        Scalar([value, 0, 0, 0])
+    }
+}
+
+/// converts any BigInt, negative or n and more included, by taking it modulo n
+impl From<&BigInt> for Scalar {
+    fn from(value: &BigInt) -> Self {
+        let n: BigInt = BigInt::from_bytes_be(Sign::Plus, &limbs_to_bytes(&N));
+        Scalar(limbs_from_bytes(&to_bytes_32(&modulo(value, &n))))
+    }
+}
+
+/// sets the limbs to 0, for the scalars that are private keys
+impl Zeroize for Scalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl From<Scalar> for BigInt {
+    fn from(value: Scalar) -> Self {
+        value.to_bigint()
+    }
+}
+
+/// displays the scalar in decimal, as the BigInt values used to be
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_bigint())
+    }
+}
+
+/// displays the scalar in hex without leading zeros, as the BigInt values used to be
+impl fmt::LowerHex for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}", self.to_bigint())
+    }
+}
+
+impl fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scalar({:x})", self)
+    }
+}
@@ -0,0 +1,252 @@
+//! Schnorr signatures as BIP-340 specifies them: https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki
+//!
+//! a public key is only the x coordinate of a point, the point being the one with an even y. a signature is
+//! the x coordinate of R = k * G, with an even y too, and s = k + e * d where e is a hash of R, the key and the message.
+//! every hash is tagged, ie. prefixed with the hash of a tag twice, so that it can't be mistaken for another protocol's
+
+use core::fmt;
+use num_bigint::BigInt;
+use sha256::Sha256;
+use zeroize::Zeroize;
+use super::{get_curve, get_curve_precomputed_points, FieldElement, Point, Scalar, W};
+use super::point::precompute_points;
+use serde::ser::{Serialize, Serializer, SerializeStruct};
+use serde::de::{self, Deserialize, Deserializer};
+
+/// A BIP-340 public key, the x coordinate of a point of the curve whose y is even
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct XOnlyPublicKey {
+    x: FieldElement
+}
+
+impl XOnlyPublicKey {
+    /// reads a public key from the 32 bytes of its x coordinate
+    ///
+    /// # Arguments
+    /// * `bytes` - The 32 big-endian bytes of the x coordinate
+    ///
+    /// # Returns
+    /// The public key, None if no point of the curve has this x coordinate
+    ///
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let x: FieldElement = FieldElement::from_bytes(bytes)?;
+        Point::lift_x(&x).map(|_| XOnlyPublicKey { x })
+    }
+
+    /// returns the 32 big-endian bytes of the x coordinate
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.x.to_bytes()
+    }
+
+    /// returns the point of the public key, the one with an even y
+    pub fn to_point(&self) -> Option<Point> {
+        Point::lift_x(&self.x)
+    }
+}
+
+/// the x-only key of a point, a point and its opposite have the same one
+impl From<&Point> for XOnlyPublicKey {
+    fn from(point: &Point) -> Self {
+        XOnlyPublicKey { x: point.x }
+    }
+}
+
+/// A BIP-340 signature, the x coordinate of R and s
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SchnorrSignature {
+    r: FieldElement,
+    s: Scalar
+}
+
+impl SchnorrSignature {
+    /// reads a signature from its 64 bytes, the x coordinate of R followed by s
+    ///
+    /// # Arguments
+    /// * `bytes` - The 64 bytes of the signature
+    ///
+    /// # Returns
+    /// The signature, None if r isn't below P or s isn't below n
+    ///
+    pub fn from_bytes(bytes: &[u8; 64]) -> Option<Self> {
+        let r: FieldElement = FieldElement::from_bytes(bytes[..32].try_into().unwrap())?;
+        let s: Scalar = Scalar::from_bytes(bytes[32..].try_into().unwrap())?;
+
+        Some(SchnorrSignature { r, s })
+    }
+
+    /// returns the 64 bytes of the signature, the x coordinate of R followed by s
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes: [u8; 64] = [0; 64];
+        bytes[..32].copy_from_slice(&self.r.to_bytes());
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+
+        bytes
+    }
+}
+
+/// implement for serialization for SchnorrSignature
+/// implementation is done by serializing r and s as hex strings, like an ECDSA signature
+impl Serialize for SchnorrSignature {
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        let mut state = serializer.serialize_struct("SchnorrSignature", 2)?;
+
+        state.serialize_field("r", &format!("{:x}", &self.r))?;
+        state.serialize_field("s", &format!("{:x}", &self.s))?;
+        state.end()
+    }
+}
+
+/// implement for deserialization for SchnorrSignature
+/// a value that isn't hex, an r that isn't below P or an s that isn't below n is an error
+impl<'de> Deserialize<'de> for SchnorrSignature {
+
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        #[derive(serde::Deserialize)]
+        struct SchnorrSignatureFields {
+            r: String,
+            s: String
+        }
+
+        let fields: SchnorrSignatureFields = SchnorrSignatureFields::deserialize(deserializer)?;
+        let invalid = |hex: &str| de::Error::custom(format!("invalid signature value {}", hex));
+
+        let r: BigInt = BigInt::parse_bytes(fields.r.as_bytes(), 16).ok_or_else(|| invalid(&fields.r))?;
+        let s: BigInt = BigInt::parse_bytes(fields.s.as_bytes(), 16).ok_or_else(|| invalid(&fields.s))?;
+
+        Ok(SchnorrSignature {
+            r: FieldElement::from_bigint(&r).ok_or_else(|| invalid(&fields.r))?,
+            s: Scalar::from_bigint(&s).ok_or_else(|| invalid(&fields.s))?
+        })
+    }
+}
+
+/// displays the signature as the hex of its 64 bytes
+impl fmt::Display for SchnorrSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", sha256::to_hex(&self.to_bytes()))
+    }
+}
+
+/// returns the tagged hash of the data, SHA-256(SHA-256(tag) || SHA-256(tag) || data)
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash: [u8; 32] = sha256::digest(tag.as_bytes());
+
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(&tag_hash);
+    hasher.update(&tag_hash);
+    for part in data {
+        hasher.update(part);
+    }
+
+    hasher.finalize()
+}
+
+/// returns the challenge e, the hash of R, the public key and the message taken mod n
+fn challenge(r: &FieldElement, public_key: &FieldElement, message: &[u8]) -> Scalar {
+    Scalar::from_bytes_reduced(&tagged_hash("BIP0340/challenge", &[&r.to_bytes(), &public_key.to_bytes(), message]))
+}
+
+/// returns the x-only public key of a private key
+///
+/// # Arguments
+/// * `private_key` - A reference to a BigInt that is the private key, in [1, n-1]
+///
+/// # Returns
+/// The public key, None if the private key isn't in [1, n-1]
+///
+pub fn x_only_public_key(private_key: &BigInt) -> Option<XOnlyPublicKey> {
+    let d: Scalar = Scalar::from_bigint(private_key).filter(|d| !d.is_zero())?;
+    Some(XOnlyPublicKey::from(&get_curve().g.multiply_secret_scalar(&d)))
+}
+
+/// signs a message as BIP-340 describes. the nonce is derived from the private key, the message and
+/// the auxiliary random bytes, fresh ones protect against side channels but the signature is secure with any of them
+///
+/// # Arguments
+/// * `message` - A byte slice that holds the message, usually a 32 bytes digest
+/// * `private_key` - A reference to a BigInt that is the private key, in [1, n-1]
+/// * `aux_rand` - The 32 auxiliary random bytes
+///
+/// # Returns
+/// The signature, None if the private key isn't in [1, n-1]
+///
+pub fn schnorr_sign(message: &[u8], private_key: &BigInt, aux_rand: &[u8; 32]) -> Option<SchnorrSignature> {
+    let mut d: Scalar = Scalar::from_bigint(private_key).filter(|d| !d.is_zero())?;
+    let signature: SchnorrSignature = schnorr_sign_scalar(message, &d, aux_rand);
+    d.zeroize();
+
+    Some(signature)
+}
+
+/// signs a message as schnorr_sign, with a private key that is already a scalar in [1, n-1]
+pub(crate) fn schnorr_sign_scalar(message: &[u8], private_key: &Scalar, aux_rand: &[u8; 32]) -> SchnorrSignature {
+    let g: &Point = &get_curve().g;
+
+    let p: Point = g.multiply_secret_scalar(private_key);
+
+    // the key is the one of the point with an even y, which is -P when P's y is odd
+    let mut d: Scalar = if p.y.is_odd() { -*private_key } else { *private_key };
+
+    let aux_hash: [u8; 32] = tagged_hash("BIP0340/aux", &[aux_rand]);
+    let mut t: [u8; 32] = d.to_bytes();
+    for (byte, aux) in t.iter_mut().zip(aux_hash) {
+        *byte ^= aux;
+    }
+
+    let mut rand: [u8; 32] = tagged_hash("BIP0340/nonce", &[&t, &p.x.to_bytes(), message]);
+    let mut k: Scalar = Scalar::from_bytes_reduced(&rand);
+    t.zeroize();
+    rand.zeroize();
+
+    // the hash is 0 mod n with a negligible probability, signing fails then in BIP-340
+    assert!(!k.is_zero(), "BIP-340 nonce is 0");
+
+    let r: Point = g.multiply_secret_scalar(&k);
+    if r.y.is_odd() {
+        k = -k;
+    }
+
+    let e: Scalar = challenge(&r.x, &p.x, message);
+    let signature: SchnorrSignature = SchnorrSignature { r: r.x, s: k + e * d };
+
+    k.zeroize();
+    d.zeroize();
+    signature
+}
+
+/// verifies a BIP-340 signature, ie. that s * G - e * P is a point with an even y whose x is r
+///
+/// # Arguments
+/// * `signature` - A reference to the signature
+/// * `message` - A byte slice that holds the message, usually a 32 bytes digest
+/// * `public_key` - A reference to the x-only public key
+///
+/// # Returns
+/// A boolean that is true if the signature is valid and false otherwise
+///
+pub fn schnorr_verify(signature: &SchnorrSignature, message: &[u8], public_key: &XOnlyPublicKey) -> bool {
+    let p: Point = match public_key.to_point() {
+        Some(p) => p,
+        None => return false
+    };
+
+    let e: Scalar = challenge(&signature.r, &p.x, message);
+
+    // s * G + (n - e) * P with the doublings shared
+    let public_key_precomp: Vec<Point> = precompute_points(p, W);
+    let minus_e: Scalar = -e;
+
+    let r: Point = Point::strauss_multiply(&[
+        (&signature.s, get_curve_precomputed_points()),
+        (&minus_e, &public_key_precomp)
+    ], W).to_affine();
+
+    !r.is_identity() && !r.y.is_odd() && r.x == signature.r
+}
@@ -1,20 +1,20 @@
 //! for more info on the maths here: https://cryptobook.nakov.com/digital-signatures/ecdsa-sign-verify-messages
+//! signing and verifying follow SEC1 section 4.1: https://www.secg.org/sec1-v2.pdf
 
 use core::fmt;
 use num_bigint::BigInt;
-use num_traits::zero;
-use sha256::hash;
-use super::{Secp256k1, Point, W};
-use crate::{math::{self, bigint, entropy, modular_multiplicative_inverse, modulo}, 
-            secp256k1::get_curve_precomputed_points};
+use sha256::digest;
+use super::{get_curve, verify_batch, BatchItem, Secp256k1, Point, Scalar};
+use super::rfc6979::NonceGenerator;
+use zeroize::Zeroize;
 use serde::ser::{Serialize, Serializer, SerializeStruct};
-use serde::de::{Deserialize, Deserializer};
+use serde::de::{self, Deserialize, Deserializer};
 
 /// Signature struct that holds the r and sigma values of a digital signature
 #[derive(Clone)]
 pub struct Signature {
-    r: BigInt,
-    s: BigInt
+    r: Scalar,
+    s: Scalar
 }
 
 impl Signature {
@@ -25,13 +25,131 @@ impl Signature {
     /// an empty signature struct 
     /// 
     pub fn get_empty() -> Self {
-        Signature { r: zero(), s: zero() }
+        Signature { r: Scalar::ZERO, s: Scalar::ZERO }
     }
+
+    /// returns a signature with the given r and s, whether they are a valid signature is only checked when it is verified
+    /// 
+    /// # Arguments
+    /// * `r` - A BigInt that is the r value of the signature
+    /// * `s` - A BigInt that is the s value of the signature
+    /// 
+    /// # Returns
+    /// The signature, None if r or s isn't in [0, n-1] since it can't be a scalar
+    /// 
+    pub fn new(r: BigInt, s: BigInt) -> Option<Self> {
+        Some(Signature { r: Scalar::from_bigint(&r)?, s: Scalar::from_bigint(&s)? })
+    }
+
+    /// returns the r value of the signature
+    pub fn get_r(&self) -> BigInt { self.r.to_bigint() }
+
+    /// returns the s value of the signature
+    pub fn get_s(&self) -> BigInt { self.s.to_bigint() }
+
+    /// returns the r value of the signature as a scalar
+    pub(crate) fn get_r_scalar(&self) -> &Scalar { &self.r }
+
+    /// returns the s value of the signature as a scalar
+    pub(crate) fn get_s_scalar(&self) -> &Scalar { &self.s }
+
+    /// returns true if s is at most n/2, the only s that verify_digest accepts
+    pub fn is_low_s(&self) -> bool {
+        !self.s.is_high()
+    }
+
+    /// encodes the signature in DER as SEC1 section C.5 describes, ie. SEQUENCE { INTEGER r, INTEGER s }
+    /// 
+    /// # Returns
+    /// The DER bytes of the signature
+    /// 
+    pub fn to_der(&self) -> Vec<u8> {
+        let r: Vec<u8> = der_integer(&self.r);
+        let s: Vec<u8> = der_integer(&self.s);
+
+        let mut der: Vec<u8> = vec![0x30, (r.len() + s.len()) as u8];
+        der.extend(r);
+        der.extend(s);
+
+        der
+    }
+
+    /// decodes a DER signature. only the one valid DER encoding is accepted, BER variants such as
+    /// long form lengths, padded or negative integers and trailing bytes are rejected, and so are integers that are n or more
+    /// 
+    /// # Arguments
+    /// * `der` - A byte slice that holds the DER encoded signature
+    /// 
+    /// # Returns
+    /// The signature, None if the bytes aren't a DER encoded signature
+    /// 
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (&tag, rest) = der.split_first()?;
+        let (&len, rest) = rest.split_first()?;
+
+        // a signature is always shorter than 128 bytes, so its length has the short form
+        if tag != 0x30 || len >= 0x80 || rest.len() != len as usize {
+            return None;
+        }
+
+        let (r, rest) = read_der_integer(rest)?;
+        let (s, rest) = read_der_integer(rest)?;
+
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(Signature { r, s })
+    }
+}
+
+/// encodes a scalar as a DER INTEGER, big endian without leading zeros but with a leading 0 when the high bit is set
+fn der_integer(value: &Scalar) -> Vec<u8> {
+    let bytes: [u8; 32] = value.to_bytes();
+    let start: usize = bytes.iter().position(|&byte| byte != 0).unwrap_or(31);
+
+    let mut bytes: Vec<u8> = bytes[start..].to_vec();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+
+    let mut integer: Vec<u8> = vec![0x02, bytes.len() as u8];
+    integer.extend(bytes);
+
+    integer
+}
+
+/// reads a DER INTEGER in [0, n-1] with its minimal encoding, returns it with the bytes that follow it
+fn read_der_integer(der: &[u8]) -> Option<(Scalar, &[u8])> {
+    let (&tag, rest) = der.split_first()?;
+    let (&len, rest) = rest.split_first()?;
+    let len: usize = len as usize;
+
+    if tag != 0x02 || len == 0 || len >= 0x80 || rest.len() < len {
+        return None;
+    }
+
+    let (value, rest) = rest.split_at(len);
+
+    // negative, or a leading 0 that isn't needed to keep the value positive
+    if value[0] & 0x80 != 0 || (len > 1 && value[0] == 0 && value[1] & 0x80 == 0) {
+        return None;
+    }
+
+    // the leading 0 of a positive value aside, it has to fit in 32 bytes
+    let value: &[u8] = if value[0] == 0 { &value[1..] } else { value };
+    if value.len() > 32 {
+        return None;
+    }
+
+    let mut bytes: [u8; 32] = [0; 32];
+    bytes[32 - value.len()..].copy_from_slice(value);
+
+    Some((Scalar::from_bytes(&bytes)?, rest))
 }
 
 /// implement for serialization for Signature
-/// manual implementation needed because BigInt is not directly serializable
-/// implementation is done by serializing the bigint as a hex string
+/// implementation is done by serializing r and s as hex strings, as when they were BigInts
 impl Serialize for Signature {
 
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -40,7 +158,7 @@ impl Serialize for Signature {
     {
         let mut state = serializer.serialize_struct("Signature", 2)?;
 
-        // encode bigint as hex
+        // encode scalars as hex
         state.serialize_field("r", &format!("{:x}", &self.r))?; 
         state.serialize_field("s", &format!("{:x}", &self.s))?;
         state.end()
@@ -48,8 +166,8 @@ impl Serialize for Signature {
 }
 
 /// implement for deserialization for Signature
-/// manual implementation needed because BigInt is not directly deserializable
-/// implementation is done by deserializing the hex string as a bigint
+/// implementation is done by deserializing the hex strings as scalars,
+/// a value that isn't hex or isn't below n is an error
 impl<'de> Deserialize<'de> for Signature {
 
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -57,7 +175,7 @@ impl<'de> Deserialize<'de> for Signature {
         D: Deserializer<'de> 
     {
         // need to use intermediary struct with strings because 
-        // bigint is not directly serializable and desrializable
+        // r and s are written as hex strings
         #[derive(serde::Deserialize)]
         struct SignatureFields {
             r: String,
@@ -66,9 +184,15 @@ impl<'de> Deserialize<'de> for Signature {
 
         let fields: SignatureFields = SignatureFields::deserialize(deserializer)?;
 
+        let scalar = |hex: &str| {
+            BigInt::parse_bytes(hex.as_bytes(), 16)
+                .and_then(|value| Scalar::from_bigint(&value))
+                .ok_or_else(|| de::Error::custom(format!("invalid signature value {}", hex)))
+        };
+
         Ok(Signature {
-            r: math::bigint(&fields.r),
-            s: math::bigint(&fields.s)
+            r: scalar(&fields.r)?,
+            s: scalar(&fields.s)?
         })
     }
 }
@@ -82,43 +206,96 @@ impl fmt::Display for Signature {
     }
 }
 
-/// returns signature using "sigma = ( H(m) + n * rx ) / k"
+/// returns signature using "sigma = ( H(m) + n * rx ) / k", where H(m) is the SHA-256 digest of the message
+/// 
+/// the nonce k is derived from the private key and the message's digest as in RFC 6979,
+/// so the same message signed with the same key always gives the same signature
 /// 
 /// # Arguments
 /// * `message` - A string slice that holds the message to be signed
 /// * `d` - A BigInt that is the private key
-/// * `k` - An optional BigInt that is the nonce
+/// * `k` - An optional BigInt that is the nonce, only meant for tests. it is tried first and
+///   the RFC 6979 nonces are used if it gives a zero r or s
 /// 
 /// # Returns
 /// A Signature struct that holds the r and sigma values of the signature
 /// 
 pub fn sign(message: &str, d: BigInt, k: Option<BigInt>) -> Signature {
-    let secp256k1: Secp256k1 = Secp256k1::new(); // gets parameters for secp256k1 curve
+    let m: [u8; 32] = digest(message.as_bytes());
+    let mut d: Scalar = Scalar::from(&d);
 
-    let k: BigInt = k.unwrap_or(modulo(&entropy(), &secp256k1.p));
+    let nonces = k.map(|k| Scalar::from(&k)).into_iter().chain(NonceGenerator::new(&d, &m));
+    let signature: Signature = sign_with_nonces(&m, &d, nonces);
 
-    let p: Point = secp256k1.g.multiply(k.clone(), W, get_curve_precomputed_points());
+    d.zeroize();
+    signature
+}
 
-    let r: BigInt = modulo(&p.x, &secp256k1.p);
-    if r == zero() {
-        return sign(message, d, Some(k));
-    }
+/// signs a 32 bytes message digest as SEC1 section 4.1.3 describes, the nonce being the RFC 6979 one.
+/// the signature always has a low s (at most n/2) since verify_digest rejects the other one
+/// 
+/// # Arguments
+/// * `digest` - The 32 bytes digest of the message, usually its SHA-256
+/// * `d` - A reference to a BigInt that is the private key, in [1, n-1]
+/// 
+/// # Returns
+/// A Signature struct that holds the r and sigma values of the signature
+/// 
+pub fn sign_digest(digest: &[u8; 32], d: &BigInt) -> Signature {
+    let mut d: Scalar = Scalar::from(d);
+    let signature: Signature = sign_digest_scalar(digest, &d);
+
+    d.zeroize();
+    signature
+}
 
-    let m: String = hash(message.to_owned() + &secp256k1.p.to_string());
+/// signs a 32 bytes message digest as sign_digest, with a private key that is already a scalar
+pub(crate) fn sign_digest_scalar(digest: &[u8; 32], d: &Scalar) -> Signature {
+    sign_with_nonces(digest, d, NonceGenerator::new(d, digest))
+}
+
+/// signs a digest with the first nonce that gives a non zero r and s.
+/// the nonce and the private key are secrets, k * G uses the constant-time ladder and k is inverted with Fermat
+fn sign_with_nonces(digest: &[u8; 32], d: &Scalar, nonces: impl Iterator<Item = Scalar>) -> Signature {
+    let secp256k1: &Secp256k1 = get_curve(); // gets parameters for secp256k1 curve
+
+    let z: Scalar = Scalar::from_bytes_reduced(digest);
+
+    for mut k in nonces {
+        if k.is_zero() {
+            continue;
+        }
 
-    let sigma: BigInt = modulo(&((&d * &r + bigint(&m)) * 
-                            modular_multiplicative_inverse(&secp256k1.n, k.clone(), None, None)), 
-                            &secp256k1.n);
+        let p: Point = secp256k1.g.multiply_secret_scalar(&k);
 
-    if sigma == zero() {
-        return sign(message, d, Some(k));
+        let r: Scalar = Scalar::from_bytes_reduced(&p.x.to_bytes());
+        if r.is_zero() {
+            k.zeroize();
+            continue;
+        }
+
+        let mut k_inverse: Scalar = k.invert();
+        let mut sigma: Scalar = (*d * r + z) * k_inverse;
+        k.zeroize();
+        k_inverse.zeroize();
+
+        if sigma.is_zero() {
+            continue;
+        }
+
+        // (r, s) and (r, n - s) are both valid, only the low one is kept so that signatures can't be malleated
+        if sigma.is_high() {
+            sigma = -sigma;
+        }
+
+        return Signature { r, s: sigma };
     }
 
-    Signature { r, s: sigma }
+    unreachable!("RFC 6979 nonces never run out")
 }
 
 
-/// verifies a signature using "r = (u1 * G + u2 * Q).x"
+/// verifies a signature using "r = (u1 * G + u2 * Q).x", where the message is hashed with SHA-256
 /// 
 /// # Arguments
 /// * `signature` - A reference to a Signature struct that holds the r and sigma values of the signature
@@ -129,22 +306,21 @@ pub fn sign(message: &str, d: BigInt, k: Option<BigInt>) -> Signature {
 /// A boolean that is true if the signature is valid and false otherwise
 /// 
 pub fn verify_signature(signature: &Signature, message: &str, public_key: Point) -> bool {
-    let secp256k1: Secp256k1 = Secp256k1::new(); // gets parameters for secp256k1 curve
-
-    let z: BigInt = bigint(&hash(message.to_owned() + &secp256k1.p.to_string()));
-
-    let w: BigInt = modulo(&modular_multiplicative_inverse(&secp256k1.n, signature.s.clone(), None, None), 
-                            &secp256k1.n);
-
-    let u1: BigInt = modulo(&(z * &w), &secp256k1.n);
-    let u2: BigInt = modulo(&(&signature.r * &w), &secp256k1.n); 
-
-    let p1: Point = secp256k1.g.multiply(u1, W, get_curve_precomputed_points());
-    let public_key_precomp: Vec<Point> = super::point::precompute_points(public_key.clone(), W);
-
-    let p2: Point = public_key.multiply(u2.clone(), W, &public_key_precomp);
-
-    let res: Point = p1.add(&p2);
+    verify_digest(signature, &digest(message.as_bytes()), &public_key)
+}
 
-    res.x.eq(&signature.r)
+/// verifies a signature of a 32 bytes message digest as SEC1 section 4.1.4 describes.
+/// r and s must be in [1, n-1] and s must be low (at most n/2), the other s of a valid signature is rejected
+/// 
+/// # Arguments
+/// * `signature` - A reference to a Signature struct that holds the r and sigma values of the signature
+/// * `digest` - The 32 bytes digest of the message, usually its SHA-256
+/// * `public_key` - A reference to a Point that is the public key
+/// 
+/// # Returns
+/// A boolean that is true if the signature is valid and false otherwise
+/// 
+pub fn verify_digest(signature: &Signature, digest: &[u8; 32], public_key: &Point) -> bool {
+    // a batch of one does the same work as checking the signature on its own
+    verify_batch(&[BatchItem { signature, digest: *digest, public_key }]).is_ok()
 }
@@ -0,0 +1,113 @@
+//! field elements and scalars must compute the same thing as BigInt modulo P and n
+
+use ecdsa::secp256k1::{FieldElement, Scalar, Secp256k1};
+use num_bigint::{BigInt, Sign};
+
+fn modulo(x: &BigInt, m: &BigInt) -> BigInt {
+    ((x % m) + m) % m
+}
+
+/// the edge cases around 0 and the modulo, then values taken from a chain of digests
+fn values(m: &BigInt) -> Vec<BigInt> {
+    let mut values: Vec<BigInt> = vec![
+        BigInt::from(0),
+        BigInt::from(1),
+        BigInt::from(2),
+        BigInt::from(u64::MAX),
+        m >> 1,
+        (m >> 1) + 1,
+        m - 2,
+        m - 1
+    ];
+
+    let mut seed: [u8; 32] = sha256::digest(b"secp256k1");
+    for _ in 0..24 {
+        values.push(modulo(&BigInt::from_bytes_be(Sign::Plus, &seed), m));
+        seed = sha256::digest(&seed);
+    }
+
+    values
+}
+
+#[test]
+fn field_matches_bigint() {
+    let p: BigInt = Secp256k1::new().p;
+    let values: Vec<BigInt> = values(&p);
+
+    for a in &values {
+        let fa: FieldElement = FieldElement::from(a);
+        assert_eq!(fa.to_bigint(), *a);
+        assert_eq!((-fa).to_bigint(), modulo(&-a, &p));
+
+        if a != &BigInt::from(0) {
+            assert_eq!((fa * fa.invert()).to_bigint(), BigInt::from(1), "a: {:x}", a);
+        }
+
+        for b in &values {
+            let fb: FieldElement = FieldElement::from(b);
+
+            assert_eq!((fa + fb).to_bigint(), modulo(&(a + b), &p), "{:x} + {:x}", a, b);
+            assert_eq!((fa - fb).to_bigint(), modulo(&(a - b), &p), "{:x} - {:x}", a, b);
+            assert_eq!((fa * fb).to_bigint(), modulo(&(a * b), &p), "{:x} * {:x}", a, b);
+        }
+    }
+
+    assert!(FieldElement::ZERO.invert().is_zero());
+}
+
+#[test]
+fn scalar_matches_bigint() {
+    let n: BigInt = Secp256k1::new().n;
+    let values: Vec<BigInt> = values(&n);
+
+    for a in &values {
+        let sa: Scalar = Scalar::from(a);
+        assert_eq!(sa.to_bigint(), *a);
+        assert_eq!((-sa).to_bigint(), modulo(&-a, &n));
+        assert_eq!(sa.is_high(), a > &(&n >> 1));
+
+        if a != &BigInt::from(0) {
+            assert_eq!((sa * sa.invert()).to_bigint(), BigInt::from(1), "a: {:x}", a);
+        }
+
+        for b in &values {
+            let sb: Scalar = Scalar::from(b);
+
+            assert_eq!((sa + sb).to_bigint(), modulo(&(a + b), &n), "{:x} + {:x}", a, b);
+            assert_eq!((sa - sb).to_bigint(), modulo(&(a - b), &n), "{:x} - {:x}", a, b);
+            assert_eq!((sa * sb).to_bigint(), modulo(&(a * b), &n), "{:x} * {:x}", a, b);
+        }
+    }
+
+    assert!(Scalar::ZERO.invert().is_zero());
+}
+
+#[test]
+fn conversions() {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+    let p: BigInt = secp256k1.p.clone();
+    let n: BigInt = secp256k1.n.clone();
+
+    // the strict conversions only take canonical values
+    assert!(FieldElement::from_bigint(&(&p - 1)).is_some());
+    assert!(FieldElement::from_bigint(&p).is_none());
+    assert!(FieldElement::from_bigint(&BigInt::from(-1)).is_none());
+    assert!(FieldElement::from_bytes(&[0xff; 32]).is_none());
+
+    assert!(Scalar::from_bigint(&(&n - 1)).is_some());
+    assert!(Scalar::from_bigint(&n).is_none());
+    assert!(Scalar::from_bigint(&BigInt::from(-1)).is_none());
+    assert!(Scalar::from_bytes(&[0xff; 32]).is_none());
+
+    // the other ones reduce
+    assert!(FieldElement::from(&p).is_zero());
+    assert_eq!(FieldElement::from(&BigInt::from(-1)).to_bigint(), &p - 1);
+    assert_eq!(Scalar::from(&(&n + 5)), Scalar::from(5));
+    assert_eq!(Scalar::from_bytes_reduced(&[0xff; 32]).to_bigint(), (BigInt::from(1) << 256) - 1 - &n);
+
+    // bytes and text round trip, the text being the one of the BigInt
+    let g_x: &FieldElement = &secp256k1.g.x;
+    assert_eq!(FieldElement::from_bytes(&g_x.to_bytes()), Some(*g_x));
+    assert_eq!(format!("{}", g_x), format!("{}", g_x.to_bigint()));
+    assert_eq!(format!("{:x}", g_x), "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+}
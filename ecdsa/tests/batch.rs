@@ -0,0 +1,91 @@
+//! batch verification must accept the batches of valid signatures and point at the first invalid one otherwise,
+//! and signatures can only be built from values in [0, n-1]
+
+use ecdsa::secp256k1::{get_curve, sign_digest, verify_batch, verify_batch_parallel, BatchItem, FieldElement, Point, Signature};
+use num_bigint::{BigInt, Sign};
+
+/// signatures of 48 messages by 5 keys, so that most keys sign several times
+fn signatures() -> Vec<(Signature, [u8; 32], Point)> {
+    let keys: Vec<BigInt> = (0..5u8)
+        .map(|i| BigInt::from_bytes_be(Sign::Plus, &sha256::digest(&[i])))
+        .collect();
+
+    (0..48u8).map(|i| {
+        let private_key: &BigInt = &keys[i as usize % keys.len()];
+        let digest: [u8; 32] = sha256::digest(format!("transaction {}", i).as_bytes());
+
+        (sign_digest(&digest, private_key), digest, get_curve().g.multiply_secret(private_key))
+    }).collect()
+}
+
+fn items(signatures: &[(Signature, [u8; 32], Point)]) -> Vec<BatchItem<'_>> {
+    signatures.iter()
+        .map(|(signature, digest, public_key)| BatchItem { signature, digest: *digest, public_key })
+        .collect()
+}
+
+#[test]
+fn valid_batches() {
+    let signatures: Vec<(Signature, [u8; 32], Point)> = signatures();
+    let items: Vec<BatchItem> = items(&signatures);
+
+    assert_eq!(verify_batch(&items), Ok(()));
+    assert_eq!(verify_batch(&items[..1]), Ok(()));
+    assert_eq!(verify_batch(&[]), Ok(()));
+
+    for threads in [1, 2, 3, 8] {
+        assert_eq!(verify_batch_parallel(&items, threads), Ok(()), "{} threads", threads);
+    }
+}
+
+#[test]
+fn invalid_signatures_are_found() {
+    let signatures: Vec<(Signature, [u8; 32], Point)> = signatures();
+    let empty: Signature = Signature::get_empty();
+    let high_s: Signature = Signature::new(signatures[7].0.get_r(), &get_curve().n - signatures[7].0.get_s()).unwrap();
+
+    // another message, another key, an empty signature, the high s of a valid one and a key off the curve
+    let mut wrong_message: Vec<BatchItem> = items(&signatures);
+    wrong_message[30].digest = sha256::digest(b"something else");
+
+    let mut wrong_key: Vec<BatchItem> = items(&signatures);
+    wrong_key[41].public_key = &signatures[0].2;
+
+    let mut empty_signature: Vec<BatchItem> = items(&signatures);
+    empty_signature[3].signature = &empty;
+
+    let mut malleated: Vec<BatchItem> = items(&signatures);
+    malleated[7].signature = &high_s;
+
+    // a key off the curve with the x of a valid one
+    let off_curve: Point = Point { x: signatures[12].2.x, y: signatures[12].2.y + FieldElement::ONE };
+    let mut invalid_key: Vec<BatchItem> = items(&signatures);
+    invalid_key[12].public_key = &off_curve;
+
+    for (items, culprit) in [(wrong_message, 30), (wrong_key, 41), (empty_signature, 3), (malleated, 7), (invalid_key, 12)] {
+        assert_eq!(verify_batch(&items), Err(culprit));
+
+        for threads in [2, 3, 8] {
+            assert_eq!(verify_batch_parallel(&items, threads), Err(culprit), "{} threads", threads);
+        }
+    }
+
+    // the first of several invalid signatures
+    let mut two_invalid: Vec<BatchItem> = items(&signatures);
+    two_invalid[20].digest = [0; 32];
+    two_invalid[45].digest = [0; 32];
+
+    assert_eq!(verify_batch(&two_invalid), Err(20));
+    assert_eq!(verify_batch_parallel(&two_invalid, 3), Err(20));
+}
+
+#[test]
+fn signature_values_out_of_range() {
+    let n: BigInt = get_curve().n.clone();
+    let r: BigInt = BigInt::from(1);
+
+    assert!(Signature::new(r.clone(), &n - 1).is_some());
+    assert!(Signature::new(r.clone(), n.clone()).is_none());
+    assert!(Signature::new(n.clone(), r.clone()).is_none());
+    assert!(Signature::new(r.clone(), BigInt::from(-1)).is_none());
+}
@@ -0,0 +1,117 @@
+//! private and public keys: generation, hex and WIF round trips with bitcoin's known WIF,
+//! rejection of keys out of range and signing with a private key
+
+use ecdsa::secp256k1::{get_curve, schnorr_verify, verify_digest, KeyError, KeyPair, Point, PointError, PrivateKey, PublicKey};
+use sha256::Base58Error;
+
+/// bitcoin's version byte for private keys
+const BITCOIN_WIF_VERSION: u8 = 0x80;
+
+const KEY_HEX: &str = "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d";
+const KEY_WIF: &str = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ";
+const KEY_WIF_COMPRESSED: &str = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+
+const N_HEX: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+#[test]
+fn generation() {
+    let pair: KeyPair = KeyPair::generate().unwrap();
+    assert_eq!(&pair.get_private_key().get_public_key(), pair.get_public_key());
+    assert!(pair.get_public_key().as_point().is_on_curve());
+
+    let other: PrivateKey = PrivateKey::generate().unwrap();
+    assert_ne!(other.to_bytes(), pair.get_private_key().to_bytes());
+
+    // the key itself is never printed
+    assert_eq!(format!("{:?}", other), "PrivateKey(..)");
+}
+
+#[test]
+fn hex_and_wif() {
+    let key: PrivateKey = PrivateKey::from_hex(KEY_HEX).unwrap();
+    assert_eq!(*key.to_hex(), KEY_HEX);
+    assert_eq!(*key.to_wif(BITCOIN_WIF_VERSION), KEY_WIF_COMPRESSED);
+
+    for wif in [KEY_WIF, KEY_WIF_COMPRESSED] {
+        assert_eq!(PrivateKey::from_wif(wif, BITCOIN_WIF_VERSION).unwrap().to_bytes(), key.to_bytes());
+    }
+
+    assert_eq!(PrivateKey::from_hex(&KEY_HEX.to_uppercase()).unwrap().to_bytes(), key.to_bytes());
+
+    let pair: KeyPair = KeyPair::generate().unwrap();
+    let wif = pair.get_private_key().to_wif(0xbf);
+    assert_eq!(PrivateKey::from_wif(&wif, 0xbf).unwrap().to_bytes(), pair.get_private_key().to_bytes());
+}
+
+#[test]
+fn invalid_private_keys() {
+    let mut n_minus_1: Vec<u8> = sha256::from_hex(N_HEX).unwrap();
+    n_minus_1[31] -= 1;
+    assert!(PrivateKey::from_bytes(&n_minus_1.try_into().unwrap()).is_ok());
+    assert!(PrivateKey::from_hex(&format!("{:064x}", 1)).is_ok());
+
+    assert_eq!(PrivateKey::from_hex(N_HEX).unwrap_err(), KeyError::OutOfRange);
+    assert_eq!(PrivateKey::from_hex(&"0".repeat(64)).unwrap_err(), KeyError::OutOfRange);
+    assert_eq!(PrivateKey::from_hex(&"f".repeat(64)).unwrap_err(), KeyError::OutOfRange);
+
+    assert_eq!(PrivateKey::from_hex(&KEY_HEX[2..]).unwrap_err(), KeyError::InvalidHex);
+    assert_eq!(PrivateKey::from_hex(&KEY_HEX[1..]).unwrap_err(), KeyError::InvalidHex);
+    assert_eq!(PrivateKey::from_hex(&format!("{}zz", &KEY_HEX[2..])).unwrap_err(), KeyError::InvalidHex);
+
+    assert_eq!(PrivateKey::from_wif(KEY_WIF, 0xbf).unwrap_err(), KeyError::WrongNetwork { version: BITCOIN_WIF_VERSION });
+    assert_eq!(PrivateKey::from_wif(&KEY_WIF.replace('T', "U"), BITCOIN_WIF_VERSION).unwrap_err(),
+        KeyError::InvalidBase58(Base58Error::InvalidChecksum));
+    assert_eq!(PrivateKey::from_wif("5Hue0", BITCOIN_WIF_VERSION).unwrap_err(),
+        KeyError::InvalidBase58(Base58Error::InvalidCharacter));
+
+    // an address is Base58Check too, but of 21 bytes
+    assert_eq!(PrivateKey::from_wif("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", 0).unwrap_err(), KeyError::InvalidWif);
+
+    // a 34 bytes WIF must end with the compressed flag
+    let mut payload: Vec<u8> = vec![BITCOIN_WIF_VERSION];
+    payload.extend(sha256::from_hex(KEY_HEX).unwrap());
+    payload.push(0x02);
+    assert_eq!(PrivateKey::from_wif(&sha256::base58check_encode(&payload), BITCOIN_WIF_VERSION).unwrap_err(), KeyError::InvalidWif);
+
+    // the key of a WIF is in range too
+    let mut payload: Vec<u8> = vec![BITCOIN_WIF_VERSION];
+    payload.extend(sha256::from_hex(N_HEX).unwrap());
+    assert_eq!(PrivateKey::from_wif(&sha256::base58check_encode(&payload), BITCOIN_WIF_VERSION).unwrap_err(), KeyError::OutOfRange);
+}
+
+#[test]
+fn public_keys() {
+    let key: PrivateKey = PrivateKey::from_hex(&format!("{:064x}", 1)).unwrap();
+    let g: &Point = &get_curve().g;
+
+    let public_key: PublicKey = key.get_public_key();
+    assert_eq!(public_key.as_point(), g);
+    assert_eq!(public_key.to_string(), g.to_string());
+    assert_eq!(public_key.to_string().parse::<PublicKey>(), Ok(public_key.clone()));
+    assert_eq!(PublicKey::from_sec1(&g.to_sec1(false)), Ok(public_key.clone()));
+    assert_eq!(Point::from(public_key), *g);
+
+    assert_eq!(PublicKey::from_sec1(&[0x00]), Err(KeyError::InvalidPublicKey(PointError::NotOnCurve)));
+    assert_eq!(PublicKey::try_from(Point::identity()), Err(KeyError::InvalidPublicKey(PointError::NotOnCurve)));
+    assert_eq!(PublicKey::from_sec1(&[0x02]), Err(KeyError::InvalidPublicKey(PointError::InvalidEncoding)));
+    assert_eq!("02zz".parse::<PublicKey>(), Err(KeyError::InvalidHex));
+}
+
+#[test]
+fn signing() {
+    let pair: KeyPair = KeyPair::generate().unwrap();
+    let digest: [u8; 32] = sha256::digest(b"message");
+
+    let signature = pair.get_private_key().sign_digest(&digest);
+    assert!(verify_digest(&signature, &digest, pair.get_public_key().as_point()));
+    assert!(!verify_digest(&signature, &sha256::digest(b"other message"), pair.get_public_key().as_point()));
+
+    let signature = pair.get_private_key().sign_schnorr(&digest, &[0; 32]);
+    assert!(schnorr_verify(&signature, &digest, &pair.get_private_key().get_x_only_public_key()));
+
+    // the same signatures as with the key as a BigInt
+    let key: PrivateKey = PrivateKey::from_hex(KEY_HEX).unwrap();
+    let d = num_bigint::BigInt::parse_bytes(KEY_HEX.as_bytes(), 16).unwrap();
+    assert_eq!(key.sign_digest(&digest).to_der(), ecdsa::secp256k1::sign_digest(&digest, &d).to_der());
+    assert_eq!(Some(key.sign_schnorr(&digest, &[7; 32])), ecdsa::secp256k1::schnorr_sign(&digest, &d, &[7; 32]));
+}
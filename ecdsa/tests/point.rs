@@ -0,0 +1,46 @@
+//! scalar multiplication: the ladder used for secret scalars must give the same points as the wNAF one
+
+use ecdsa::secp256k1::{get_curve_precomputed_points, Point, Secp256k1, W};
+use num_bigint::BigInt;
+
+fn bigint(hex: &str) -> BigInt {
+    BigInt::parse_bytes(hex.as_bytes(), 16).unwrap()
+}
+
+#[test]
+fn secret_multiplication_matches_wnaf() {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+    let n: BigInt = secp256k1.n.clone();
+
+    let scalars: [BigInt; 8] = [
+        BigInt::from(1),
+        BigInt::from(2),
+        BigInt::from(3),
+        BigInt::from(0xffff_ffffu32),
+        bigint("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181"),
+        bigint("e91671c46231f833a6406ccbea0e3e392c76c167bac1cb013f6f1013980455c2"),
+        &n - 2,
+        &n - 1
+    ];
+
+    for k in scalars {
+        let expected: Point = secp256k1.g.clone().multiply(k.clone(), W, get_curve_precomputed_points());
+        assert_eq!(secp256k1.g.multiply_secret(&k), expected, "k: {:x}", k);
+    }
+
+    // scalars are taken mod n
+    assert_eq!(secp256k1.g.multiply_secret(&(&n + 5)), secp256k1.g.multiply_secret(&BigInt::from(5)));
+    assert!(secp256k1.g.multiply_secret(&n).is_identity());
+    assert!(secp256k1.g.multiply_secret(&BigInt::from(0)).is_identity());
+}
+
+#[test]
+fn secret_multiplication_of_any_point() {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+    let q: Point = secp256k1.g.multiply_secret(&bigint("1234567890abcdef"));
+
+    let k: BigInt = bigint("c0ffee0123456789c0ffee0123456789c0ffee0123456789c0ffee0123456789");
+    let expected: Point = secp256k1.g.clone().multiply(k.clone() * bigint("1234567890abcdef"), W, get_curve_precomputed_points());
+
+    assert_eq!(q.multiply_secret(&k), expected);
+}
@@ -0,0 +1,95 @@
+//! RFC 6979 nonces for secp256k1 with SHA-256, from the vectors published for bitcoin
+//! libraries (python-ecdsa, trezor-crypto), message digests being the SHA-256 of the message.
+//! the published signatures have a low s
+
+use ecdsa::secp256k1::{get_curve_precomputed_points, rfc6979_nonce, sign, sign_digest, verify_signature, NonceGenerator, Point, Scalar, Secp256k1, Signature, W};
+use num_bigint::BigInt;
+
+fn bigint(hex: &str) -> BigInt {
+    BigInt::parse_bytes(hex.as_bytes(), 16).unwrap()
+}
+
+fn scalar(hex: &str) -> Scalar {
+    Scalar::from_bigint(&bigint(hex)).unwrap()
+}
+
+#[test]
+fn secp256k1_vectors() {
+    let n_minus_1: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140";
+
+    let vectors: [(&str, &str, &str); 5] = [
+        ("1", "Satoshi Nakamoto", "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15"),
+        ("1", "All those moments will be lost in time, like tears in rain. Time to die...",
+            "38aa22d72376b4dbc472e06c3ba403ee0a394da63fc58d88686c611aba98d6b3"),
+        (n_minus_1, "Satoshi Nakamoto", "33a19b60e25fb6f4435af53a3d42d493644827367e6453928554f43e49aa6f90"),
+        ("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181", "Alan Turing",
+            "525a82b70e67874398067543fd84c83d30c175fdc45fdeee082fe13b1d7cfdf1"),
+        ("e91671c46231f833a6406ccbea0e3e392c76c167bac1cb013f6f1013980455c2",
+            "There is a computer disease that anybody who works with computers knows about. It's a very serious disease and it interferes completely with the work. The trouble with computers is that you 'play' with them!",
+            "1f4b84c23a86a221d233f2521be018d9318639d5b8bbd6374a8a59232d16ad3d")
+    ];
+
+    for (private_key, message, expected) in vectors {
+        let k: Scalar = rfc6979_nonce(&scalar(private_key), &sha256::digest(message.as_bytes()));
+        assert_eq!(k, scalar(expected), "message: {:?}", message);
+    }
+}
+
+#[test]
+fn secp256k1_signatures() {
+    // r then s, s being the low one
+    let vectors: [(&str, &str, &str); 4] = [
+        ("1", "Satoshi Nakamoto",
+            "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d82442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"),
+        ("1", "All those moments will be lost in time, like tears in rain. Time to die...",
+            "8600dbd41e348fe5c9465ab92d23e3db8b98b873beecd930736488696438cb6b547fe64427496db33bf66019dacbf0039c04199abb0122918601db38a72cfc21"),
+        ("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181", "Alan Turing",
+            "7063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c58dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea"),
+        ("e91671c46231f833a6406ccbea0e3e392c76c167bac1cb013f6f1013980455c2",
+            "There is a computer disease that anybody who works with computers knows about. It's a very serious disease and it interferes completely with the work. The trouble with computers is that you 'play' with them!",
+            "b552edd27580141f3b2a5463048cb7cd3e047b97c9f98076c32dbdf85a68718b279fa72dd19bfae05577e06c7c0c1900c371fcd5893f7e1d56a37d30174671f6")
+    ];
+
+    for (private_key, message, expected) in vectors {
+        let signature: Signature = sign_digest(&sha256::digest(message.as_bytes()), &bigint(private_key));
+        assert_eq!(format!("{:064x}{:064x}", signature.get_r(), signature.get_s()), expected, "message: {:?}", message);
+
+        // sign hashes the message with SHA-256 the same way
+        assert_eq!(sign(message, bigint(private_key), None).to_der(), signature.to_der());
+    }
+}
+
+#[test]
+fn candidates_are_distinct_and_in_range() {
+    let candidates: Vec<Scalar> = NonceGenerator::new(&Scalar::ONE, &sha256::digest(b"Satoshi Nakamoto")).take(5).collect();
+
+    assert_eq!(candidates[0], scalar("8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15"));
+    for (i, k) in candidates.iter().enumerate() {
+        // a scalar is always below n, it only has to be non zero
+        assert!(!k.is_zero());
+        assert!(!candidates[..i].contains(k));
+    }
+}
+
+#[test]
+fn signatures_are_deterministic() {
+    let secp256k1: Secp256k1 = Secp256k1::new();
+    let private_key: BigInt = bigint("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181");
+    let public_key: Point = secp256k1.g.multiply(private_key.clone(), W, get_curve_precomputed_points());
+
+    let first: String = sign("Alan Turing", private_key.clone(), None).to_string();
+    assert_eq!(sign("Alan Turing", private_key.clone(), None).to_string(), first);
+    assert_ne!(sign("Alan Turing.", private_key.clone(), None).to_string(), first);
+
+    assert!(verify_signature(&sign("Alan Turing", private_key, None), "Alan Turing", public_key));
+}
+
+#[test]
+fn unusable_nonce_falls_back_to_rfc6979() {
+    // n * G is the identity, whose x gives a zero r
+    let n: BigInt = Secp256k1::new().n;
+    let private_key: BigInt = BigInt::from(1);
+
+    assert_eq!(sign("Satoshi Nakamoto", private_key.clone(), Some(n)).to_string(),
+               sign("Satoshi Nakamoto", private_key, None).to_string());
+}
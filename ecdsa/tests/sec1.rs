@@ -0,0 +1,94 @@
+//! SEC1 encoding of points: known encodings of the generator, round trips and rejection of invalid encodings
+//! and of points that aren't on the curve
+
+use ecdsa::secp256k1::{get_curve, FieldElement, Point, PointError};
+use num_bigint::BigInt;
+use sha256::from_hex;
+
+const G_COMPRESSED: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const G_UNCOMPRESSED: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+#[test]
+fn generator_encodings() {
+    let g: &Point = &get_curve().g;
+
+    assert_eq!(sha256::to_hex(&g.to_sec1(true)), G_COMPRESSED);
+    assert_eq!(sha256::to_hex(&g.to_sec1(false)), G_UNCOMPRESSED);
+    assert_eq!(g.to_string(), G_COMPRESSED);
+
+    assert_eq!(Point::from_sec1(&from_hex(G_COMPRESSED).unwrap()).as_ref(), Ok(g));
+    assert_eq!(Point::from_sec1(&from_hex(G_UNCOMPRESSED).unwrap()).as_ref(), Ok(g));
+}
+
+#[test]
+fn round_trips() {
+    // points with even and odd y
+    for k in 1..40u32 {
+        let point: Point = get_curve().g.multiply_secret(&(BigInt::from(k) * 0x9e3779b9u32));
+
+        assert_eq!(point.to_sec1(true)[0], 0x02 | point.y.is_odd() as u8);
+        assert!(point.is_on_curve());
+        assert_eq!(Point::from_sec1(&point.to_sec1(true)), Ok(point.clone()));
+        assert_eq!(Point::from_sec1(&point.to_sec1(false)), Ok(point.clone()));
+
+        let json: String = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, format!("\"{}\"", point));
+        assert_eq!(serde_json::from_str::<Point>(&json).unwrap(), point);
+    }
+
+    assert_eq!(Point::identity().to_sec1(true), vec![0x00]);
+    assert_eq!(Point::from_sec1(&[0x00]), Ok(Point::identity()));
+}
+
+#[test]
+fn invalid_encodings() {
+    let g: &Point = &get_curve().g;
+    let mut off_curve: Vec<u8> = g.to_sec1(false);
+    off_curve[64] ^= 1;
+
+    let invalid: Vec<(Vec<u8>, PointError)> = vec![
+        (vec![], PointError::InvalidEncoding),
+        (vec![0x00, 0x00], PointError::InvalidEncoding),
+        (from_hex(&G_COMPRESSED[..64]).unwrap(), PointError::InvalidEncoding),
+        (from_hex(&G_UNCOMPRESSED[2..]).unwrap(), PointError::InvalidEncoding),
+        // hybrid encodings and unknown prefixes
+        ([&[0x06], &g.to_sec1(false)[1..]].concat(), PointError::InvalidEncoding),
+        ([&[0x05], &g.to_sec1(true)[1..]].concat(), PointError::InvalidEncoding),
+        ([&[0x04], &g.to_sec1(true)[1..]].concat(), PointError::InvalidEncoding),
+        ([&[0x02], &g.to_sec1(false)[1..]].concat(), PointError::InvalidEncoding),
+        // no point has this x
+        (from_hex("02eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34").unwrap(), PointError::NotOnCurve),
+        // x is P
+        (from_hex("03fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f").unwrap(), PointError::InvalidCoordinate),
+        (off_curve, PointError::NotOnCurve)
+    ];
+
+    for (bytes, error) in invalid {
+        assert_eq!(Point::from_sec1(&bytes), Err(error), "{}", sha256::to_hex(&bytes));
+
+        let json: Result<Point, serde_json::Error> = serde_json::from_str(&format!("\"{}\"", sha256::to_hex(&bytes)));
+        assert!(json.unwrap_err().to_string().contains(&error.to_string()));
+    }
+
+    assert!(serde_json::from_str::<Point>("\"02zz\"").is_err());
+    assert!(serde_json::from_str::<Point>("{\"x\": \"1\", \"y\": \"2\"}").is_err());
+}
+
+#[test]
+fn points_off_the_curve() {
+    let g: &Point = &get_curve().g;
+
+    assert!(g.is_on_curve());
+    assert_eq!(Point::new(g.x, g.y).as_ref(), Ok(g));
+    assert_eq!(Point::new(g.x, -g.y), Ok(Point { x: g.x, y: -g.y }));
+
+    // the identity isn't an affine point of the curve
+    assert!(!Point::identity().is_on_curve());
+    assert_eq!(Point::new(FieldElement::ZERO, FieldElement::ZERO), Err(PointError::NotOnCurve));
+    assert_eq!(Point::new(g.x, g.y + FieldElement::ONE), Err(PointError::NotOnCurve));
+    assert_eq!(Point::new(g.y, g.x), Err(PointError::NotOnCurve));
+
+    // the points built from the fields aren't checked, they are when they are used
+    let invalid: Point = Point { x: FieldElement::from(1), y: FieldElement::from(1) };
+    assert!(!invalid.is_on_curve());
+}
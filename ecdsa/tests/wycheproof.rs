@@ -0,0 +1,40 @@
+//! Wycheproof ECDSA vectors for secp256k1 with SHA-256, bitcoin variant: signatures with a high s are invalid.
+//! see data/WYCHEPROOF_COPYING for where the vectors come from
+
+use ecdsa::secp256k1::{verify_digest, FieldElement, Point, Signature};
+use num_bigint::BigInt;
+use serde_json::Value;
+use sha256::from_hex;
+
+#[test]
+fn ecdsa_secp256k1_sha256_bitcoin() {
+    let vectors: Value = serde_json::from_str(include_str!("data/ecdsa_secp256k1_sha256_bitcoin_test.json")).unwrap();
+    let mut tested: u64 = 0;
+
+    for group in vectors["testGroups"].as_array().unwrap() {
+        let public_key: Point = Point {
+            x: FieldElement::from(&BigInt::parse_bytes(group["publicKey"]["wx"].as_str().unwrap().as_bytes(), 16).unwrap()),
+            y: FieldElement::from(&BigInt::parse_bytes(group["publicKey"]["wy"].as_str().unwrap().as_bytes(), 16).unwrap())
+        };
+
+        for test in group["tests"].as_array().unwrap() {
+            let id: u64 = test["tcId"].as_u64().unwrap();
+            let digest: [u8; 32] = sha256::digest(&from_hex(test["msg"].as_str().unwrap()).unwrap());
+            let der: Vec<u8> = from_hex(test["sig"].as_str().unwrap()).unwrap();
+
+            let valid: bool = match Signature::from_der(&der) {
+                Some(signature) => {
+                    // anything that decodes must encode back to the same bytes
+                    assert_eq!(signature.to_der(), der, "test {}", id);
+                    verify_digest(&signature, &digest, &public_key)
+                },
+                None => false
+            };
+
+            assert_eq!(valid, test["result"] == "valid", "test {}: {}", id, test["comment"]);
+            tested += 1;
+        }
+    }
+
+    assert_eq!(tested, vectors["numberOfTests"].as_u64().unwrap());
+}
@@ -0,0 +1,171 @@
+//! addresses that coins are paid to, written in Base58Check like bitcoin's.
+//!
+//! an address is the first 20 bytes of the SHA-256 of the compressed public key. it is written as
+//! the network's version byte, the 20 bytes and a checksum of 4 bytes, the start of their SHA256d,
+//! in base 58: digits and letters without 0, O, I and l, which are easy to mix up.
+//! a typo is caught by the checksum, and an address of another network by its version byte
+
+use core::fmt;
+use std::str::FromStr;
+use ecdsa::secp256k1::{Point, PublicKey};
+use serde::{de::{self, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+use sha256::Base58Error;
+
+/// The version byte of the addresses of this network, it makes them start with an S
+pub const ADDRESS_VERSION: u8 = 0x3f;
+
+/// The version byte of the WIF private keys of this network, the address version with its high bit set as in bitcoin
+pub const WIF_VERSION: u8 = ADDRESS_VERSION | 0x80;
+
+/// The number of bytes of the hash of the public key in an address
+const ADDRESS_HASH_LEN: usize = 20;
+
+/// The address of a public key, the hash of its compressed SEC1 encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address([u8; ADDRESS_HASH_LEN]);
+
+/// Reasons for which a string can't be parsed into an Address
+#[derive(Debug, PartialEq, Eq)]
+pub enum AddressError {
+    /// The string has a character that isn't a base 58 digit
+    InvalidCharacter,
+
+    /// The string doesn't decode to a version byte and a hash
+    InvalidLength,
+
+    /// The checksum doesn't match, the address has a typo
+    InvalidChecksum,
+
+    /// The address is from another network
+    WrongNetwork { version: u8 }
+}
+
+/// implement display for AddressError for easy printing of parsing errors
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::InvalidCharacter => write!(f, "address has an invalid character"),
+            AddressError::InvalidLength => write!(f, "address has an invalid length"),
+            AddressError::InvalidChecksum => write!(f, "address has an invalid checksum"),
+            AddressError::WrongNetwork { version } =>
+                write!(f, "address is for another network: version {:02x}, expected {:02x}", version, ADDRESS_VERSION)
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl From<Base58Error> for AddressError {
+    fn from(error: Base58Error) -> Self {
+        match error {
+            Base58Error::InvalidCharacter => AddressError::InvalidCharacter,
+            Base58Error::TooShort => AddressError::InvalidLength,
+            Base58Error::InvalidChecksum => AddressError::InvalidChecksum
+        }
+    }
+}
+
+impl Address {
+    /// returns the address of a public key
+    ///
+    /// # Arguments
+    /// * `public_key` - A reference to the public key
+    ///
+    /// # Returns
+    /// * The first 20 bytes of the SHA-256 of the compressed public key
+    ///
+    pub fn from_public_key(public_key: &Point) -> Self {
+        let digest: [u8; 32] = sha256::digest(&public_key.to_sec1(true));
+        Address(digest[..ADDRESS_HASH_LEN].try_into().unwrap())
+    }
+
+    /// returns the hash of the public key that the address holds
+    pub fn get_hash(&self) -> [u8; ADDRESS_HASH_LEN] {
+        self.0
+    }
+}
+
+/// the address of a public key, as from_public_key
+impl From<&Point> for Address {
+    fn from(public_key: &Point) -> Self {
+        Address::from_public_key(public_key)
+    }
+}
+
+/// the address of a public key, as from_public_key
+impl From<&PublicKey> for Address {
+    fn from(public_key: &PublicKey) -> Self {
+        Address::from_public_key(public_key.as_point())
+    }
+}
+
+/// displays the address in Base58Check
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", sha256::base58check_encode(&[&[ADDRESS_VERSION], self.0.as_slice()].concat()))
+    }
+}
+
+/// parses an address written in Base58Check, checking its checksum and its network
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let payload: Vec<u8> = sha256::base58check_decode(s)?;
+
+        if payload.len() != 1 + ADDRESS_HASH_LEN {
+            return Err(AddressError::InvalidLength);
+        }
+
+        if payload[0] != ADDRESS_VERSION {
+            return Err(AddressError::WrongNetwork { version: payload[0] });
+        }
+
+        Ok(Address(payload[1..].try_into().unwrap()))
+    }
+}
+
+/// addresses are written in Base58Check in human readable formats such as JSON,
+/// binary formats only get the 20 bytes of the hash since the version and checksum can be recomputed
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+/// visitor reading an address from Base58Check or from the 20 bytes of its hash
+struct AddressVisitor;
+
+impl<'de> Visitor<'de> for AddressVisitor {
+    type Value = Address;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an address")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Address, E> {
+        s.parse().map_err(|e| E::custom(format!("invalid address {}: {}", s, e)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Address, E> {
+        let hash: [u8; ADDRESS_HASH_LEN] = bytes.try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &"the 20 bytes of an address"))?;
+
+        Ok(Address(hash))
+    }
+}
+
+/// reads an address from Base58Check in human readable formats or from the 20 bytes of the hash otherwise
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(AddressVisitor)
+        } else {
+            deserializer.deserialize_bytes(AddressVisitor)
+        }
+    }
+}
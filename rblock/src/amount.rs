@@ -0,0 +1,155 @@
+use core::fmt;
+use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Number of decimals of a coin
+pub const COIN_DECIMALS: usize = 8;
+
+/// Number of snails, the smallest unit, in one coin
+pub const SNAILS_PER_COIN: u64 = 100_000_000;
+
+/// An amount of coins, stored as an integer number of snails so that no precision is lost.
+///
+/// Arithmetic is checked, every operation that would overflow or go below zero returns None.
+/// Amounts are written as decimal coin strings such as "1.5" or "0.00000001".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+/// Reasons for which a decimal coin string can't be parsed into an Amount
+#[derive(Debug, PartialEq, Eq)]
+pub enum AmountError {
+    /// The string has no digits before or after the decimal point
+    Empty,
+
+    /// The string has a character that isn't a digit or a single decimal point
+    InvalidCharacter,
+
+    /// The string has more decimals than a snail can represent
+    TooManyDecimals,
+
+    /// The amount doesn't fit in a u64 number of snails
+    Overflow
+}
+
+/// implement display for AmountError for easy printing of parsing errors
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Empty => write!(f, "amount is empty"),
+            AmountError::InvalidCharacter => write!(f, "amount has an invalid character"),
+            AmountError::TooManyDecimals => write!(f, "amount has more than {} decimals", COIN_DECIMALS),
+            AmountError::Overflow => write!(f, "amount is too big")
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(u64::MAX);
+
+    /// returns an amount of the given number of snails
+    pub const fn from_snails(snails: u64) -> Self {
+        Amount(snails)
+    }
+
+    /// returns an amount of the given number of whole coins, None if it doesn't fit
+    pub fn from_coins(coins: u64) -> Option<Self> {
+        coins.checked_mul(SNAILS_PER_COIN).map(Amount)
+    }
+
+    /// returns the number of snails in the amount
+    pub const fn to_snails(self) -> u64 {
+        self.0
+    }
+
+    /// returns true if the amount is zero
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// returns the sum of the amounts, None if it overflows
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    /// returns the sum of the amounts, Amount::MAX if it overflows
+    pub fn saturating_add(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_add(other.0))
+    }
+
+    /// returns the difference of the amounts, None if it would be negative
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+/// implement display for Amount, written in coins without trailing zeros
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let coins: u64 = self.0 / SNAILS_PER_COIN;
+        let snails: u64 = self.0 % SNAILS_PER_COIN;
+
+        if snails == 0 {
+            return write!(f, "{}", coins);
+        }
+
+        let decimals: String = format!("{:0width$}", snails, width = COIN_DECIMALS);
+        write!(f, "{}.{}", coins, decimals.trim_end_matches('0'))
+    }
+}
+
+/// parses a decimal coin string such as "12", "1.5" or "0.00000001"
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (coins, decimals): (&str, &str) = match s.split_once('.') {
+            Some((coins, decimals)) => (coins, decimals),
+            None => (s, "")
+        };
+
+        if coins.is_empty() || (s.contains('.') && decimals.is_empty()) {
+            return Err(AmountError::Empty);
+        }
+
+        // u64::from_str accepts a leading +, only digits are allowed here
+        if !coins.bytes().chain(decimals.bytes()).all(|byte| byte.is_ascii_digit()) {
+            return Err(AmountError::InvalidCharacter);
+        }
+
+        if decimals.len() > COIN_DECIMALS {
+            return Err(AmountError::TooManyDecimals);
+        }
+
+        let coins: u64 = coins.parse().map_err(|_| AmountError::Overflow)?;
+        let snails: u64 = format!("{:0<width$}", decimals, width = COIN_DECIMALS).parse().map_err(|_| AmountError::Overflow)?;
+
+        Amount::from_coins(coins)
+            .and_then(|amount| amount.checked_add(Amount(snails)))
+            .ok_or(AmountError::Overflow)
+    }
+}
+
+/// amounts are written as coin strings in human readable formats and as snails otherwise (bincode)
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_u64(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s: String = String::deserialize(deserializer)?;
+            s.parse().map_err(de::Error::custom)
+        } else {
+            u64::deserialize(deserializer).map(Amount)
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use core::fmt;
-use ecdsa::secp256k1::Point;
-use sha256::hash;
-use super::{functions, Transaction, TRANSACTION_LIMIT_PER_BLOCK};
+use std::thread;
+use ecdsa::secp256k1::{verify_batch_parallel, BatchItem, Point};
+use sha256::{digest, double_hash, to_hex, Sha256};
+use super::{functions, get_block_subsidy, Address, Amount, BlockHeader, Transaction, TRANSACTION_LIMIT_PER_BLOCK};
 use serde::{Serialize, Deserialize};
 
 /// A block in the blockchain
@@ -48,11 +49,12 @@ impl fmt::Display for Block {
 
 impl Block {
     /// creates and returns new genesis block
+    /// the genesis block is the same on every node so that they all share the same chain
     pub fn new_genesis() -> Self {
         let mut genesis: Block = Block {
             height: 0,
             hash: "".to_owned(),
-            timestamp: functions::get_unix_time(),
+            timestamp: super::GENESIS_TIMESTAMP,
             nonce: 0, 
             difficulty: 0xffffffff, 
             prev_hash: "".to_owned(),
@@ -92,17 +94,18 @@ impl Block {
         new_block
     }
 
-    /// rewards miner only if another reward doesn't already exist
-    /// pretty much obselete since you could just add it yourself when using 
-    /// block::new in the transactions you pass
+    /// rewards miner only if another reward doesn't already exist.
+    /// the reward is the block's subsidy plus the fees of the block's transactions,
+    /// so it must be added after every other transaction. it is put first in the block
+    /// since that's the only position where a reward is valid
     /// 
     /// # Modifications
     /// * Adds a reward transaction to the block's transactions, hence the mut self
     /// 
     /// # Arguments
-    /// * `miner_address` - A reference to the miner's public key
+    /// * `miner_address` - A reference to the miner's address
     /// 
-    pub fn reward_miner(&mut self, miner_address: &Point) {
+    pub fn reward_miner(&mut self, miner_address: &Address) {
         // check if there is already a reward in the block
         for transaction in &self.transactions {
             if transaction.get_sender() == Point::identity() {
@@ -111,9 +114,15 @@ impl Block {
             }
         }
         
-        let reward_transaction: Transaction = Transaction::reward_transaction(miner_address);
+        let reward_transaction: Transaction = match Transaction::reward_transaction(miner_address, self.height, self.get_fees()) {
+            Some(transaction) => transaction,
+            None => {
+                eprintln!("The fees of this block are too high to be rewarded.");
+                return;
+            }
+        };
         
-        self.transactions.push(reward_transaction);
+        self.transactions.insert(0, reward_transaction);
         self.merkel_root = functions::get_merkel_root(&self.transactions);
         self.set_hash();
     }
@@ -137,15 +146,75 @@ impl Block {
     /// # Modifications
     /// * Changes the block's nonce and hash, hence the mut self
     /// 
-    pub fn increment_and_hash(&mut self) {
-        // if nonce is at max, then the block is invalid
+    /// # Returns
+    /// * False if the nonce is already at max u32 and nothing was changed, the timestamp
+    ///   or transactions need to change for the block to get new hashes
+    /// 
+    pub fn increment_and_hash(&mut self) -> bool {
         if self.nonce == u32::MAX {
-            eprintln!("Nonce is at max u32, consider changing transactions.");
-            return;
+            return false;
         }
 
         self.nonce += 1;
         self.set_hash();
+
+        true
+    }
+
+    /// sets the nonce and generates hash
+    /// used by miners that split the nonce range between threads
+    /// 
+    /// # Arguments
+    /// * `nonce` - The new nonce
+    /// 
+    /// # Modifications
+    /// * Changes the block's nonce and hash, hence the mut self
+    /// 
+    pub fn set_nonce(&mut self, nonce: u32) {
+        self.nonce = nonce;
+        self.set_hash();
+    }
+
+    /// returns the hasher state after the part of the block's message that comes before the nonce.
+    /// a miner computes it once and gives it to set_nonce_from_midstate so that only the rest of the
+    /// message is hashed for every nonce. it must be computed again when the height, timestamp or previous hash change
+    pub fn get_midstate(&self) -> Sha256 {
+        let mut midstate: Sha256 = Sha256::new();
+        midstate.update(functions::get_block_message_prefix(self.height, self.timestamp, &self.prev_hash).as_bytes());
+
+        midstate
+    }
+
+    /// sets the nonce and generates hash starting from the block's midstate, gives the same hash as set_nonce
+    /// 
+    /// # Arguments
+    /// * `nonce` - The new nonce
+    /// * `midstate` - The hasher state returned by get_midstate
+    /// 
+    /// # Modifications
+    /// * Changes the block's nonce and hash, hence the mut self
+    /// 
+    pub fn set_nonce_from_midstate(&mut self, nonce: u32, midstate: &Sha256) {
+        self.nonce = nonce;
+
+        let mut hasher: Sha256 = midstate.clone();
+        hasher.update(functions::get_block_message_suffix(self.nonce, self.difficulty, &self.merkel_root).as_bytes());
+        self.hash = to_hex(&digest(&hasher.finalize()));
+    }
+
+    /// sets the timestamp and generates hash
+    /// used by miners to get new hashes once every nonce was tried.
+    /// the difficulty might need to be updated after since it depends on the timestamp
+    /// 
+    /// # Arguments
+    /// * `timestamp` - The new unix timestamp
+    /// 
+    /// # Modifications
+    /// * Changes the block's timestamp and hash, hence the mut self
+    /// 
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+        self.set_hash();
     }
 
     /// returns the current block's hash
@@ -168,6 +237,13 @@ impl Block {
         self.transactions.clone()
     }
 
+    /// returns the sum of the fees of the block's transactions, Amount::MAX if it overflows
+    pub fn get_fees(&self) -> Amount {
+        self.transactions.iter()
+            .filter(|transaction| transaction.get_sender() != Point::identity())
+            .fold(Amount::ZERO, |fees, transaction| fees.saturating_add(transaction.get_fee()))
+    }
+
     /// returns the current block's difficulty
     pub fn get_difficulty(&self) -> u32 {
         self.difficulty.clone()
@@ -180,27 +256,54 @@ impl Block {
 
     /// returns the current block's height
     pub fn get_height(&self) -> u64 {
-        self.height.clone()
+        self.height
+    }
+
+    /// returns the work that went into the block, ie. the expected number of hashes
+    /// needed to satisfy its difficulty, scaled by 2^32 to keep precision.
+    ///
+    /// a 4-bit chunk d of the difficulty lets (d + 1) out of 16 values through,
+    /// so the expected number of hashes is the product of 16 / (d + 1) over every chunk.
+    ///
+    /// # Returns
+    /// * A u128 representing the work of the block, used to compare chains
+    ///
+    pub fn get_work(&self) -> u128 {
+        let mut allowed: u128 = 1;
+
+        for i in (0..=28).step_by(4) {
+            allowed *= ((self.difficulty >> i) & 0xf) as u128 + 1;
+        }
+
+        // 16^8 * 2^32 = 2^64
+        (1 << 64) / allowed
     }
  
-    /// Hashes with the data in the block and sets the hash 
+    /// Hashes with the data in the block and sets the hash, the id of a block is the SHA256d of its message
     /// 
     /// # Modifications
     /// * Changes the block's hash, hence the mut self
     /// 
     fn set_hash(&mut self) {
-        self.hash = hash(self.get_message());
+        self.hash = double_hash(self.get_message());
     }
 
     /// gets the message that was used to hash the block
     pub fn get_message(&self) -> String {
-        format!("{}{}{}{}{}{}", 
-                self.height, 
-                self.timestamp,
-                self.prev_hash,
-                self.nonce,
-                self.difficulty,
-                self.merkel_root)
+        functions::get_block_message(self.height, self.timestamp, &self.prev_hash, self.nonce, self.difficulty, &self.merkel_root)
+    }
+
+    /// returns the header of the block, ie. the block without its transactions
+    pub fn get_header(&self) -> BlockHeader {
+        BlockHeader {
+            height: self.height,
+            hash: self.hash.clone(),
+            timestamp: self.timestamp,
+            prev_hash: self.prev_hash.clone(),
+            nonce: self.nonce,
+            difficulty: self.difficulty,
+            merkel_root: self.merkel_root.clone()
+        }
     }
 
     /// verifies that the 4-bit sized chunks of the hash are within the correct value range
@@ -230,7 +333,47 @@ impl Block {
         true
     }
 
-    /// checks every transaction to make sure  that its good
+    /// checks the block's reward, every block but genesis has exactly one reward and it is the first transaction.
+    /// the reward can't be more than the block's subsidy plus the fees of the block's transactions
+    /// 
+    /// # Returns
+    /// * True if the reward is valid, false otherwise
+    /// 
+    pub fn confirm_coinbase(&self) -> bool {
+        // genesis doesn't reward anyone
+        if self.height == 0 {
+            return self.transactions.is_empty();
+        }
+
+        let coinbase: &Transaction = match self.transactions.first() {
+            Some(transaction) if transaction.get_sender() == Point::identity() => transaction,
+            _ => {
+                eprintln!("The first transaction of a block must be its reward");
+                return false;
+            }
+        };
+
+        if !coinbase.get_fee().is_zero() || coinbase.get_nonce() != 0 {
+            eprintln!("A reward can't have a fee or a nonce");
+            return false;
+        }
+
+        let max_reward: Amount = get_block_subsidy(self.height).saturating_add(self.get_fees());
+        if coinbase.get_amount() > max_reward {
+            eprintln!("{} is more than the maximum reward of {}", coinbase.get_amount(), max_reward);
+            return false;
+        }
+
+        if self.transactions.iter().skip(1).any(|transaction| transaction.get_sender() == Point::identity()) {
+            eprintln!("A block can only have one reward");
+            return false;
+        }
+
+        true
+    }
+
+    /// checks every transaction to make sure  that its good.
+    /// the ECDSA signatures are verified together in a batch, spread over the available cores
     /// 
     /// # Returns
     /// * True if all transactions are valid, false otherwise
@@ -242,16 +385,44 @@ impl Block {
             return false;
         }
 
-        for transaction in &self.transactions {
-            // Point::identity is miner reward sender
-            if transaction.get_sender() != Point::identity() && !transaction.verify() {
-                eprintln!("A transaction is invalid");
-                eprintln!("{}", transaction);
+        let mut batched: Vec<&Transaction> = Vec::with_capacity(self.transactions.len());
+        let mut items: Vec<BatchItem> = Vec::with_capacity(self.transactions.len());
+
+        for (i, transaction) in self.transactions.iter().enumerate() {
+            // Point::identity is miner reward sender, which is only valid as the first transaction
+            if transaction.get_sender() == Point::identity() {
+                if i == 0 {
+                    continue;
+                }
+
+                eprintln!("Only the first transaction can be a reward");
                 return false;
             }
+
+            // Schnorr signatures are verified on their own
+            match transaction.get_batch_item() {
+                Some(item) => {
+                    batched.push(transaction);
+                    items.push(item);
+                },
+                None if !transaction.verify() => {
+                    eprintln!("A transaction is invalid");
+                    eprintln!("{}", transaction);
+                    return false;
+                },
+                None => {}
+            }
+        }
+
+        let threads: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+        if let Err(i) = verify_batch_parallel(&items, threads) {
+            eprintln!("A transaction is invalid");
+            eprintln!("{}", batched[i]);
+            return false;
         }
 
-        return true;
+        true
     }
 
     /// verifies if the hash of the block fits with current data
@@ -259,7 +430,15 @@ impl Block {
     /// # Returns
     /// * True if the hash is correct, false otherwise
     pub fn confirm_hash(&self) -> bool {
-        self.get_hash() == hash(self.get_message())
+        self.get_hash() == double_hash(self.get_message())
+    }
+
+    /// verifies that the merkel root of the block matches its transactions
+    ///
+    /// # Returns
+    /// * True if the merkel root is correct, false otherwise
+    pub fn confirm_merkel_root(&self) -> bool {
+        self.merkel_root == functions::get_merkel_root(&self.transactions)
     }
 
     /// verifies on the block if the difficulty and hash match
@@ -286,10 +465,23 @@ impl Block {
     /// A u32 which is the supposed difficulty of comp_block as a u32.
     /// 
     pub fn get_supposed_difficulty(base_block: &Block, comp_block: &Block) -> u32 {
-        let latest_difficulty: u32 = base_block.get_difficulty();
-        // get time difference between blocks
-        let time_diff: u64 = comp_block.get_timestamp() - base_block.get_timestamp();
+        // get time difference between blocks, a block older than its parent counts as a fast one
+        let time_diff: u64 = comp_block.get_timestamp().saturating_sub(base_block.get_timestamp());
+
+        Block::adjust_difficulty(base_block.get_difficulty(), time_diff)
+    }
 
+    /// returns the difficulty of a block that comes time_diff seconds after a block of the given difficulty,
+    /// see get_supposed_difficulty. shared with BlockHeader so that headers are checked the same way
+    ///
+    /// # Arguments
+    /// * `latest_difficulty` - A u32 which specifies the difficulty of the previous block
+    /// * `time_diff` - A u64 which specifies the seconds between the previous block and the block
+    ///
+    /// # Returns
+    /// A u32 which is the supposed difficulty of the block
+    ///
+    pub(crate) fn adjust_difficulty(latest_difficulty: u32, time_diff: u64) -> u32 {
         let mut difficulty: u32 = latest_difficulty;
 
         if time_diff > super::BLOCK_SPEED {
@@ -11,6 +11,25 @@ pub fn get_unix_time() -> u64 {
     duration_since_epoch.as_secs()
 }
 
+/// returns the message that is hashed to get a block's hash, made from every field of the block's header
+/// 
+/// # Returns
+/// * A string made of the fields one after the other
+/// 
+pub fn get_block_message(height: u64, timestamp: u64, prev_hash: &str, nonce: u32, difficulty: u32, merkel_root: &str) -> String {
+    get_block_message_prefix(height, timestamp, prev_hash) + &get_block_message_suffix(nonce, difficulty, merkel_root)
+}
+
+/// returns the start of a block's message, the fields that come before the nonce
+pub fn get_block_message_prefix(height: u64, timestamp: u64, prev_hash: &str) -> String {
+    format!("{}{}{}", height, timestamp, prev_hash)
+}
+
+/// returns the end of a block's message, from the nonce on
+pub fn get_block_message_suffix(nonce: u32, difficulty: u32, merkel_root: &str) -> String {
+    format!("{}{}{}", nonce, difficulty, merkel_root)
+}
+
 /// returns the merkel root of all the transactions
 /// https://en.wikipedia.org/wiki/Merkle_tree
 /// 
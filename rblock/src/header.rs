@@ -0,0 +1,51 @@
+use sha256::double_hash;
+use serde::{Serialize, Deserialize};
+use super::{functions, Block};
+
+/// The header of a block, ie. every field of the block except its transactions.
+/// The block's hash only covers these fields so a header can be checked without the transactions,
+/// which lets nodes sync the chain's headers before downloading the full blocks.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub height: u64,
+    pub hash: String,
+    pub timestamp: u64,
+    pub prev_hash: String,
+    pub nonce: u32,
+    pub difficulty: u32,
+    pub merkel_root: String
+}
+
+impl BlockHeader {
+    /// gets the message that was used to hash the block
+    pub fn get_message(&self) -> String {
+        functions::get_block_message(self.height, self.timestamp, &self.prev_hash, self.nonce, self.difficulty, &self.merkel_root)
+    }
+
+    /// verifies if the hash of the header fits with current data
+    ///
+    /// # Returns
+    /// * True if the hash is correct, false otherwise
+    pub fn confirm_hash(&self) -> bool {
+        self.hash == double_hash(self.get_message())
+    }
+
+    /// verifies on the header if the difficulty and hash match
+    pub fn confirm_difficulty(&self) -> bool {
+        Block::verify_difficulty(self.hash.clone(), self.difficulty)
+    }
+
+    /// returns the difficulty that a header should have, the same as Block::get_supposed_difficulty
+    ///
+    /// # Arguments
+    /// * `base_header` - A &BlockHeader which specifies the header of the previous block
+    /// * `comp_header` - A &BlockHeader which specifies the header for which you want to know the correct difficulty
+    ///
+    /// # Returns
+    /// A u32 which is the supposed difficulty of comp_header
+    ///
+    pub fn get_supposed_difficulty(base_header: &BlockHeader, comp_header: &BlockHeader) -> u32 {
+        let time_diff: u64 = comp_header.timestamp.saturating_sub(base_header.timestamp);
+        Block::adjust_difficulty(base_header.difficulty, time_diff)
+    }
+}
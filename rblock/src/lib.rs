@@ -1,12 +1,24 @@
 const BLOCK_SPEED: u64 = 1200; // 20 min between blocks
-const TRANSACTION_LIMIT_PER_BLOCK: usize = 5000;
-const REWARD: f32 = 1.5;
+pub const TRANSACTION_LIMIT_PER_BLOCK: usize = 5000;
+const GENESIS_TIMESTAMP: u64 = 1712016000; // fixed so every node builds the same genesis block
+
+mod address;
+pub use address::{Address, AddressError, ADDRESS_VERSION, WIF_VERSION};
+
+mod amount;
+pub use amount::{Amount, AmountError, COIN_DECIMALS, SNAILS_PER_COIN};
+
+mod subsidy;
+pub use subsidy::{get_block_subsidy, get_circulating_supply, HALVING_INTERVAL, INITIAL_SUBSIDY, MAX_SUPPLY};
 
 mod block;
 pub use block::Block;
 
+mod header;
+pub use header::BlockHeader;
+
 mod functions;
 
 mod transaction;
-pub use transaction::Transaction;
-pub use functions::get_merkel_root;
+pub use transaction::{Transaction, TransactionSignature};
+pub use functions::{get_merkel_root, get_unix_time};
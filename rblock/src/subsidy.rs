@@ -0,0 +1,69 @@
+use super::Amount;
+
+/// Subsidy of the blocks before the first halving, 1.5 coins
+pub const INITIAL_SUBSIDY: Amount = Amount::from_snails(150_000_000);
+
+/// Number of blocks between halvings, about 4 years at 20 min per block
+pub const HALVING_INTERVAL: u64 = 105_000;
+
+/// Total amount of coins that will ever be created by block subsidies, genesis has no subsidy
+pub const MAX_SUPPLY: Amount = Amount::from_snails(get_supply_until_era(u64::BITS as u64) - INITIAL_SUBSIDY.to_snails());
+
+/// returns the subsidy that the miner of a block at the given height can claim, fees excluded.
+/// the subsidy is halved every HALVING_INTERVAL blocks until it reaches zero, genesis has none
+///
+/// # Arguments
+/// * `height` - the height of the block
+///
+/// # Returns
+/// * the block's subsidy
+///
+pub fn get_block_subsidy(height: u64) -> Amount {
+    if height == 0 {
+        return Amount::ZERO;
+    }
+
+    let halvings: u64 = height / HALVING_INTERVAL;
+    if halvings >= u64::BITS as u64 {
+        return Amount::ZERO;
+    }
+
+    Amount::from_snails(INITIAL_SUBSIDY.to_snails() >> halvings)
+}
+
+/// returns the amount of coins created by the subsidies of every block up to the given height.
+/// it is an upper bound on the coins in circulation since miners can claim less than the subsidy
+///
+/// # Arguments
+/// * `height` - the height of the last block counted
+///
+/// # Returns
+/// * the coins created up to that height, never more than MAX_SUPPLY
+///
+pub fn get_circulating_supply(height: u64) -> Amount {
+    let era: u64 = height / HALVING_INTERVAL;
+    if era >= u64::BITS as u64 {
+        return MAX_SUPPLY;
+    }
+
+    // every full era before the block's, then the blocks of its era up to it
+    let blocks_in_era: u64 = height % HALVING_INTERVAL + 1;
+    let supply: u64 = get_supply_until_era(era) + blocks_in_era * (INITIAL_SUBSIDY.to_snails() >> era);
+
+    // genesis is counted as part of the first era but has no subsidy
+    Amount::from_snails(supply - INITIAL_SUBSIDY.to_snails())
+}
+
+/// returns the coins created by every block of the eras before the given one, genesis included as a full block.
+/// can't overflow since every era creates half as much as the previous one
+const fn get_supply_until_era(era: u64) -> u64 {
+    let mut supply: u64 = 0;
+    let mut i: u64 = 0;
+
+    while i < era {
+        supply += HALVING_INTERVAL * (INITIAL_SUBSIDY.to_snails() >> i);
+        i += 1;
+    }
+
+    supply
+}
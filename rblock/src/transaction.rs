@@ -1,8 +1,7 @@
 use core::fmt;
-use ecdsa::secp256k1::{sign, verify_signature, Point, Signature};
-use num_bigint::BigInt;
+use ecdsa::secp256k1::{schnorr_verify, verify_signature, BatchItem, KeyError, Point, PrivateKey, SchnorrSignature, Signature, XOnlyPublicKey};
 use serde::{Deserialize, Serialize};
-use super::REWARD;
+use super::{get_block_subsidy, Address, Amount};
 
 /// A transaction in the blockchain
 #[derive(Clone, Deserialize, Serialize)]
@@ -10,23 +9,51 @@ pub struct Transaction {
     /// The public key of the sender
     sender: Point,
 
-    /// The public key of the recipient
-    recipient: Point,
+    /// The address of the recipient
+    recipient: Address,
 
     /// The amount of the transaction
-    amount: f32,
+    amount: Amount,
+
+    /// The fee paid by the sender to the miner of the block, on top of the amount
+    fee: Amount,
+
+    /// The number of transactions the sender sent before this one, so that a transaction can't be replayed
+    nonce: u64,
 
     /// The digital signature of the transaction, signed by the sender
-    signature: Signature
+    signature: TransactionSignature
+}
+
+/// The signature of a transaction, both kinds are made with the sender's private key
+#[derive(Clone, Deserialize, Serialize)]
+pub enum TransactionSignature {
+    /// An ECDSA signature of the SHA-256 of the message, checked against the sender's point
+    Ecdsa(Signature),
+
+    /// A BIP-340 Schnorr signature of the SHA-256 of the message, checked against the sender's x-only key
+    Schnorr(SchnorrSignature)
+}
+
+/// implement display for transaction signature, an ECDSA one is displayed as it always was
+impl fmt::Display for TransactionSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionSignature::Ecdsa(signature) => write!(f, "{}", signature),
+            TransactionSignature::Schnorr(signature) => write!(f, "schnorr{}", signature)
+        }
+    }
 }
 
 /// implement display for transaction struct for easy printing
 impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\tsender: {}\n\trecipient: {}\n\tamount: {}\n\tsignature: {}", 
+        write!(f, "\tsender: {}\n\trecipient: {}\n\tamount: {}\n\tfee: {}\n\tnonce: {}\n\tsignature: {}", 
             self.sender, 
             self.recipient,
             self.amount,
+            self.fee,
+            self.nonce,
             self.signature)
     }
 }
@@ -35,72 +62,157 @@ impl Transaction {
     /// generates a reward transaction for the miner that doesn't need to be signed
     /// 
     /// # Arguments
-    /// * `recipient` - the public key of the miner
+    /// * `recipient` - the address of the miner
+    /// * `height` - the height of the block, which gives its subsidy
+    /// * `fees` - the sum of the fees of the block's transactions
     /// 
     /// # Returns
-    /// * a new transaction with the reward amount
+    /// * a new transaction with the block's subsidy plus the fees, None if it overflows
     /// 
-    pub fn reward_transaction(recipient: &Point) -> Self {
-        Transaction {
+    pub fn reward_transaction(recipient: &Address, height: u64, fees: Amount) -> Option<Self> {
+        Some(Transaction {
             sender: Point::identity(),
-            recipient: recipient.clone(),
-            amount: REWARD,
-            signature: Signature::get_empty()
-        }
+            recipient: *recipient,
+            amount: get_block_subsidy(height).checked_add(fees)?,
+            fee: Amount::ZERO,
+            nonce: 0,
+            signature: TransactionSignature::Ecdsa(Signature::get_empty())
+        })
     }
 
-    /// returns a new transaction that has already been signed using the private key
+    /// returns a new transaction that has already been signed using the sender's private key
     /// 
     /// # Arguments
-    /// * `sender` - the public key of the sender
-    /// * `recipient` - the public key of the recipient
+    /// * `sender` - the private key of the sender, it signs the transaction and its public key is the sender
+    /// * `recipient` - the address of the recipient
     /// * `amount` - the amount of the transaction
-    /// * `private_key` - the private key of the sender, used to sign the transaction
+    /// * `fee` - the fee paid to the miner, on top of the amount
+    /// * `nonce` - the number of transactions the sender already sent
     /// 
     /// # Returns
-    /// * a new transaction with the sender, recipient, amount, and signature
+    /// * a new transaction with the sender, recipient, amount, fee, nonce, and signature
     /// 
-    pub fn new(sender: &Point, recipient: &Point, amount: f32, private_key: &BigInt) -> Self {
-        let message: String = sender.to_string() + &recipient.to_string() + &amount.to_string();
-        let signature: Signature = sign(&message, private_key.clone(), None);
+    pub fn new(sender: &PrivateKey, recipient: &Address, amount: Amount, fee: Amount, nonce: u64) -> Self {
+        let public_key: Point = sender.get_public_key().as_point().clone();
+        let message: String = Transaction::build_message(&public_key, recipient, amount, fee, nonce);
+        let signature: Signature = sender.sign_digest(&sha256::digest(message.as_bytes()));
 
         Transaction {
-            sender: sender.clone(),
-            recipient: recipient.clone(),
+            sender: public_key,
+            recipient: *recipient,
             amount,
-            signature
+            fee,
+            nonce,
+            signature: TransactionSignature::Ecdsa(signature)
         }
     }
 
+    /// returns a new transaction signed with a BIP-340 Schnorr signature using the sender's private key
+    /// 
+    /// # Arguments
+    /// * `sender` - the private key of the sender, it signs the transaction and its public key is the sender
+    /// * `recipient` - the address of the recipient
+    /// * `amount` - the amount of the transaction
+    /// * `fee` - the fee paid to the miner, on top of the amount
+    /// * `nonce` - the number of transactions the sender already sent
+    /// 
+    /// # Returns
+    /// * a new transaction with the sender, recipient, amount, fee, nonce, and signature,
+    ///   an error if the OS random number generator failed
+    /// 
+    pub fn new_schnorr(sender: &PrivateKey, recipient: &Address, amount: Amount, fee: Amount, nonce: u64) -> Result<Self, KeyError> {
+        let public_key: Point = sender.get_public_key().as_point().clone();
+        let message: String = Transaction::build_message(&public_key, recipient, amount, fee, nonce);
+
+        // fresh auxiliary bytes as BIP-340 recommends, they protect the nonce against side channels
+        let mut aux_rand: [u8; 32] = [0; 32];
+        getrandom::getrandom(&mut aux_rand).map_err(KeyError::Rng)?;
+
+        let signature: SchnorrSignature = sender.sign_schnorr(&sha256::digest(message.as_bytes()), &aux_rand);
+
+        Ok(Transaction {
+            sender: public_key,
+            recipient: *recipient,
+            amount,
+            fee,
+            nonce,
+            signature: TransactionSignature::Schnorr(signature)
+        })
+    }
+
     /// returns the sender's public key
     pub fn get_sender(&self) -> Point { self.sender.clone() }
 
-    /// returns the recipient's public key
-    pub fn get_recipient(&self) -> Point { self.recipient.clone() }
+    /// returns the sender's address, the account the amount and the fee are taken from
+    pub fn get_sender_address(&self) -> Address { Address::from(&self.sender) }
+
+    /// returns the recipient's address
+    pub fn get_recipient(&self) -> Address { self.recipient }
 
     /// returns the amount of the transaction
-    pub fn get_amount(&self) -> f32 { self.amount.clone() }
+    pub fn get_amount(&self) -> Amount { self.amount }
+
+    /// returns the fee of the transaction
+    pub fn get_fee(&self) -> Amount { self.fee }
+
+    /// returns what the sender pays, the amount plus the fee, None if it overflows
+    pub fn get_cost(&self) -> Option<Amount> { self.amount.checked_add(self.fee) }
+
+    /// returns the nonce of the transaction
+    pub fn get_nonce(&self) -> u64 { self.nonce }
 
     /// returns the signature of the transaction
-    pub fn get_signature(&self) -> Signature { self.signature.clone() }
+    pub fn get_signature(&self) -> TransactionSignature { self.signature.clone() }
 
-    /// verifies the signature of the transaction
+    /// verifies the signature of the transaction, and that the sender is on the curve
     /// 
     /// # Returns
     /// * true if the signature is valid, false otherwise
     /// 
     pub fn verify(&self) -> bool {
-        verify_signature(&self.signature, &self.get_message(), self.sender.clone())
+        // no arithmetic is done with a key that isn't on the curve
+        if !self.sender.is_on_curve() {
+            return false;
+        }
+
+        match &self.signature {
+            TransactionSignature::Ecdsa(signature) => verify_signature(signature, &self.get_message(), self.sender.clone()),
+            TransactionSignature::Schnorr(signature) => {
+                schnorr_verify(signature, &sha256::digest(self.get_message().as_bytes()), &XOnlyPublicKey::from(&self.sender))
+            }
+        }
+    }
+
+    /// returns the ECDSA signature with what it has to sign, to verify it in a batch with others
+    /// 
+    /// # Returns
+    /// * the batch item, None if the transaction has a Schnorr signature
+    /// 
+    pub fn get_batch_item(&self) -> Option<BatchItem<'_>> {
+        match &self.signature {
+            TransactionSignature::Ecdsa(signature) => Some(BatchItem {
+                signature,
+                digest: sha256::digest(self.get_message().as_bytes()),
+                public_key: &self.sender
+            }),
+            TransactionSignature::Schnorr(_) => None
+        }
     }
 
     /// returns the message that was signed
     fn get_message(&self) -> String {
-        self.sender.to_string() + &self.recipient.to_string() + &self.amount.to_string()
+        Transaction::build_message(&self.sender, &self.recipient, self.amount, self.fee, self.nonce)
+    }
+
+    /// returns the message that is signed for the given fields.
+    /// the amount, fee and nonce are separated since they are numbers and could otherwise be read in several ways
+    fn build_message(sender: &Point, recipient: &Address, amount: Amount, fee: Amount, nonce: u64) -> String {
+        format!("{}{}{}_{}_{}", sender, recipient, amount, fee, nonce)
     }
 
-    /// returns the hash for the transaction, used in the block's merkel root exclusively
+    /// returns the hash for the transaction, the SHA256d of its fields. used in the block's merkel root exclusively
     pub fn get_hash(&self) -> String {
-        sha256::hash(format!("{}{}{}{}", self.sender, self.recipient, self.amount, self.signature))
+        sha256::double_hash(format!("{}{}{}_{}_{}{}", self.sender, self.recipient, self.amount, self.fee, self.nonce, self.signature))
     }
 }
 
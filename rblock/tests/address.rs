@@ -0,0 +1,82 @@
+//! addresses: Base58Check round trips and the rejection of typos and of addresses of other networks,
+//! and the keys and transactions of a key pair
+
+use ecdsa::secp256k1::{get_curve, KeyPair, Point, PrivateKey};
+use num_bigint::BigInt;
+use rblock::{Address, AddressError, Amount, Transaction, WIF_VERSION};
+
+fn public_keys() -> Vec<Point> {
+    (1..20u32).map(|k| get_curve().g.multiply_secret(&(BigInt::from(k) * 0x9e3779b9u32))).collect()
+}
+
+#[test]
+fn round_trips() {
+    for public_key in public_keys() {
+        let address: Address = Address::from(&public_key);
+        let written: String = address.to_string();
+
+        assert!(written.starts_with('S'), "{}", written);
+        assert_eq!(written.parse::<Address>(), Ok(address));
+
+        let json: String = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", written));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+    }
+
+    // a point and its opposite have different addresses
+    let g: &Point = &get_curve().g;
+    let minus_g: Point = Point { x: g.x, y: -g.y };
+    assert_ne!(Address::from(g), Address::from(&minus_g));
+}
+
+#[test]
+fn invalid_addresses() {
+    let written: String = Address::from(&get_curve().g).to_string();
+
+    // every single character change is caught
+    for i in 0..written.len() {
+        let mut typo: Vec<u8> = written.clone().into_bytes();
+        typo[i] = if typo[i] == b'z' { b'y' } else { b'z' };
+
+        let result: Result<Address, AddressError> = String::from_utf8(typo).unwrap().parse();
+        assert!(matches!(result, Err(AddressError::InvalidChecksum) | Err(AddressError::InvalidLength)), "{:?}", result);
+    }
+
+    assert_eq!(format!("{}0", &written[1..]).parse::<Address>(), Err(AddressError::InvalidCharacter));
+    assert_eq!(written[..20].parse::<Address>(), Err(AddressError::InvalidChecksum));
+    assert_eq!("".parse::<Address>(), Err(AddressError::InvalidLength));
+
+    // a valid bitcoin private key, 33 bytes
+    assert_eq!("5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ".parse::<Address>(), Err(AddressError::InvalidLength));
+
+    // a valid bitcoin address, version 0
+    assert_eq!("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".parse::<Address>(), Err(AddressError::WrongNetwork { version: 0 }));
+
+    assert!(serde_json::from_str::<Address>("\"1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2\"").is_err());
+}
+
+#[test]
+fn key_pairs() {
+    let pair: KeyPair = KeyPair::generate().unwrap();
+    let address: Address = Address::from(pair.get_public_key());
+    assert_eq!(address, Address::from(pair.get_public_key().as_point()));
+
+    let wif = pair.get_private_key().to_wif(WIF_VERSION);
+    let private_key: PrivateKey = PrivateKey::from_wif(&wif, WIF_VERSION).unwrap();
+    assert_eq!(private_key.to_bytes(), pair.get_private_key().to_bytes());
+
+    let recipient: Address = Address::from(&get_curve().g);
+    let amount: Amount = Amount::from_coins(1).unwrap();
+
+    let pair: KeyPair = KeyPair::from(private_key);
+    let transaction: Transaction = Transaction::new(pair.get_private_key(), &recipient, amount, amount, 0);
+    assert!(transaction.verify());
+    assert_eq!(transaction.get_sender(), *pair.get_public_key().as_point());
+    assert_eq!(transaction.get_sender_address(), address);
+
+    // the auxiliary bytes are fresh, so two signatures of the same transaction differ
+    let schnorr: Transaction = Transaction::new_schnorr(pair.get_private_key(), &recipient, amount, amount, 0).unwrap();
+    assert!(schnorr.verify());
+    assert_eq!(schnorr.get_sender_address(), address);
+    assert_ne!(schnorr.to_string(), Transaction::new_schnorr(pair.get_private_key(), &recipient, amount, amount, 0).unwrap().to_string());
+}
@@ -0,0 +1,84 @@
+//! amounts: decimal coin strings to snails and back, the strings that are rejected,
+//! and the checked arithmetic at the limits of a u64
+
+use rblock::{Amount, AmountError, SNAILS_PER_COIN};
+
+#[test]
+fn round_trips() {
+    let amounts: [(&str, u64); 7] = [
+        ("0", 0),
+        ("0.00000001", 1),
+        ("1", SNAILS_PER_COIN),
+        ("1.5", 150_000_000),
+        ("12.3456789", 1_234_567_890),
+        ("21000000", 21_000_000 * SNAILS_PER_COIN),
+        ("184467440737.09551615", u64::MAX)
+    ];
+
+    for (written, snails) in amounts {
+        let amount: Amount = written.parse().unwrap();
+        assert_eq!(amount, Amount::from_snails(snails), "{}", written);
+        assert_eq!(amount.to_string(), written);
+    }
+
+    assert_eq!(Amount::MAX.to_string(), "184467440737.09551615");
+
+    // trailing zeros are accepted but not written
+    assert_eq!("1.50000000".parse::<Amount>().unwrap().to_string(), "1.5");
+    assert_eq!("007".parse::<Amount>().unwrap().to_string(), "7");
+}
+
+#[test]
+fn invalid_strings() {
+    let invalid: [(&str, AmountError); 14] = [
+        ("", AmountError::Empty),
+        ("1.", AmountError::Empty),
+        (".5", AmountError::Empty),
+        (".", AmountError::Empty),
+        ("-1", AmountError::InvalidCharacter),
+        ("+1", AmountError::InvalidCharacter),
+        ("1e3", AmountError::InvalidCharacter),
+        ("1.2.3", AmountError::InvalidCharacter),
+        (" 1", AmountError::InvalidCharacter),
+        ("1,5", AmountError::InvalidCharacter),
+        ("0.000000001", AmountError::TooManyDecimals),
+        ("1.123456789", AmountError::TooManyDecimals),
+        ("184467440737.09551616", AmountError::Overflow),
+        ("184467440738", AmountError::Overflow)
+    ];
+
+    for (written, error) in invalid {
+        assert_eq!(written.parse::<Amount>(), Err(error), "{:?}", written);
+    }
+
+    // more digits than a u64 holds
+    assert_eq!("99999999999999999999999".parse::<Amount>(), Err(AmountError::Overflow));
+}
+
+#[test]
+fn checked_arithmetic() {
+    let one: Amount = Amount::from_snails(1);
+
+    assert_eq!(Amount::MAX.checked_add(one), None);
+    assert_eq!(Amount::MAX.checked_add(Amount::ZERO), Some(Amount::MAX));
+    assert_eq!(Amount::MAX.saturating_add(one), Amount::MAX);
+
+    assert_eq!(Amount::ZERO.checked_sub(one), None);
+    assert_eq!(one.checked_sub(one), Some(Amount::ZERO));
+    assert!(one.checked_sub(one).unwrap().is_zero());
+
+    assert_eq!(Amount::from_coins(u64::MAX / SNAILS_PER_COIN), Some(Amount::from_snails(u64::MAX / SNAILS_PER_COIN * SNAILS_PER_COIN)));
+    assert_eq!(Amount::from_coins(u64::MAX / SNAILS_PER_COIN + 1), None);
+}
+
+#[test]
+fn serialization() {
+    let amount: Amount = "1.5".parse().unwrap();
+
+    // coin strings in JSON
+    let json: String = serde_json::to_string(&amount).unwrap();
+    assert_eq!(json, "\"1.5\"");
+    assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), amount);
+
+    assert!(serde_json::from_str::<Amount>("\"1e3\"").is_err());
+}
@@ -0,0 +1,89 @@
+//! block rewards: the coinbase can claim the block's subsidy plus the fees of its transactions, never more,
+//! and every block but genesis has exactly one, as its first transaction
+
+use ecdsa::secp256k1::{get_curve, PrivateKey};
+use rblock::{get_block_subsidy, Address, Amount, Block, Transaction};
+
+fn miner() -> Address {
+    Address::from(&get_curve().g)
+}
+
+/// a block on top of genesis with the given transactions, in that order
+fn block(transactions: Vec<Transaction>) -> Block {
+    Block::new(&Block::new_genesis(), &transactions)
+}
+
+fn payment(sender: &PrivateKey, fee: Amount, nonce: u64) -> Transaction {
+    Transaction::new(sender, &miner(), Amount::from_coins(1).unwrap(), fee, nonce)
+}
+
+#[test]
+fn reward_is_subsidy_plus_fees() {
+    let sender: PrivateKey = PrivateKey::generate().unwrap();
+    let fees: [Amount; 2] = [Amount::from_snails(1_000), Amount::from_snails(2_500)];
+
+    let mut rewarded: Block = block(vec![payment(&sender, fees[0], 0), payment(&sender, fees[1], 1)]);
+    assert_eq!(rewarded.get_fees(), Amount::from_snails(3_500));
+
+    rewarded.reward_miner(&miner());
+    let coinbase: Transaction = rewarded.get_transactions()[0].clone();
+    assert_eq!(coinbase.get_amount(), get_block_subsidy(1).checked_add(Amount::from_snails(3_500)).unwrap());
+    assert!(rewarded.confirm_coinbase());
+}
+
+#[test]
+fn reward_over_the_cap() {
+    let sender: PrivateKey = PrivateKey::generate().unwrap();
+    let fee: Amount = Amount::from_snails(1_000);
+
+    // claiming exactly the cap, or less, is fine
+    let exact: Transaction = Transaction::reward_transaction(&miner(), 1, fee).unwrap();
+    assert!(block(vec![exact, payment(&sender, fee, 0)]).confirm_coinbase());
+
+    let less: Transaction = Transaction::reward_transaction(&miner(), 1, Amount::ZERO).unwrap();
+    assert!(block(vec![less, payment(&sender, fee, 0)]).confirm_coinbase());
+
+    // one snail more than the fees of the block
+    let greedy: Transaction = Transaction::reward_transaction(&miner(), 1, Amount::from_snails(1_001)).unwrap();
+    assert!(!block(vec![greedy, payment(&sender, fee, 0)]).confirm_coinbase());
+
+    // fees with no transaction to pay them
+    let no_fees: Transaction = Transaction::reward_transaction(&miner(), 1, fee).unwrap();
+    assert!(!block(vec![no_fees]).confirm_coinbase());
+}
+
+#[test]
+fn one_coinbase_first() {
+    let sender: PrivateKey = PrivateKey::generate().unwrap();
+    let reward = || Transaction::reward_transaction(&miner(), 1, Amount::ZERO).unwrap();
+
+    let valid: Block = block(vec![reward(), payment(&sender, Amount::ZERO, 0)]);
+    assert!(valid.confirm_coinbase());
+    assert!(valid.confirm_transactions());
+
+    // no coinbase
+    let missing: Block = block(vec![payment(&sender, Amount::ZERO, 0)]);
+    assert!(!missing.confirm_coinbase());
+    assert!(!block(Vec::new()).confirm_coinbase());
+
+    // a second coinbase, even one that claims nothing
+    let second: Block = block(vec![reward(), Transaction::reward_transaction(&miner(), 0, Amount::ZERO).unwrap()]);
+    assert!(!second.confirm_coinbase());
+    assert!(!second.confirm_transactions());
+
+    // a coinbase after another transaction
+    let not_first: Block = block(vec![payment(&sender, Amount::ZERO, 0), reward()]);
+    assert!(!not_first.confirm_coinbase());
+    assert!(!not_first.confirm_transactions());
+
+    // reward_miner only adds a coinbase if there is none, and puts it first
+    let mut rewarded: Block = block(vec![payment(&sender, Amount::ZERO, 0)]);
+    rewarded.reward_miner(&miner());
+    rewarded.reward_miner(&miner());
+    assert_eq!(rewarded.get_transactions().len(), 2);
+    assert!(rewarded.confirm_coinbase());
+    assert!(rewarded.confirm_transactions());
+
+    // genesis has none
+    assert!(Block::new_genesis().confirm_coinbase());
+}
@@ -0,0 +1,36 @@
+//! difficulty adjustment between a block and its parent: fast blocks make the next one harder,
+//! slow ones make it easier, and a block older than its parent counts as a fast one
+
+use rblock::Block;
+
+/// The 20 minutes between blocks the difficulty aims for
+const BLOCK_SPEED: u64 = 1200;
+
+fn child(parent: &Block, timestamp: u64) -> Block {
+    let mut block: Block = Block::new(parent, &Vec::new());
+    block.set_timestamp(timestamp);
+    block
+}
+
+#[test]
+fn fast_and_slow_blocks() {
+    let genesis: Block = Block::new_genesis();
+    assert_eq!(genesis.get_difficulty(), 0xffffffff);
+
+    let fast: Block = child(&genesis, genesis.get_timestamp() + 1);
+    assert_eq!(Block::get_supposed_difficulty(&genesis, &fast), 0xfffffffe);
+
+    let mut parent: Block = fast.clone();
+    parent.set_difficulty(0xfffffffe);
+
+    let slow: Block = child(&parent, parent.get_timestamp() + BLOCK_SPEED + 1);
+    assert_eq!(Block::get_supposed_difficulty(&parent, &slow), 0xffffffff);
+}
+
+#[test]
+fn block_older_than_its_parent() {
+    let genesis: Block = Block::new_genesis();
+    let older: Block = child(&genesis, genesis.get_timestamp() - 10);
+
+    assert_eq!(Block::get_supposed_difficulty(&genesis, &older), 0xfffffffe);
+}
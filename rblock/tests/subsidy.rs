@@ -0,0 +1,57 @@
+//! the subsidy schedule: halvings at every HALVING_INTERVAL blocks and a circulating supply that never exceeds MAX_SUPPLY
+
+use rblock::{get_block_subsidy, get_circulating_supply, Amount, HALVING_INTERVAL, INITIAL_SUBSIDY, MAX_SUPPLY};
+
+/// heights around the first halvings, the last ones and the end of the u64 range
+fn boundaries() -> Vec<u64> {
+    let mut heights: Vec<u64> = vec![1, 2];
+
+    for era in [1, 2, 3, 27, 28, 29, 63, 64] {
+        let height: u64 = era * HALVING_INTERVAL;
+        heights.extend([height - 1, height, height + 1]);
+    }
+
+    heights.extend([u64::MAX - 1, u64::MAX]);
+    heights
+}
+
+#[test]
+fn halvings() {
+    let initial: u64 = INITIAL_SUBSIDY.to_snails();
+
+    assert_eq!(get_block_subsidy(0), Amount::ZERO);
+    assert_eq!(get_block_subsidy(1), INITIAL_SUBSIDY);
+
+    for era in 1..64 {
+        let height: u64 = era * HALVING_INTERVAL;
+        assert_eq!(get_block_subsidy(height - 1), Amount::from_snails(initial >> (era - 1)), "height {}", height - 1);
+        assert_eq!(get_block_subsidy(height), Amount::from_snails(initial >> era), "height {}", height);
+    }
+
+    // 1.5 coins are less than 2^28 snails, so the subsidy is gone after 28 halvings
+    assert!(!get_block_subsidy(28 * HALVING_INTERVAL - 1).is_zero());
+    assert_eq!(get_block_subsidy(28 * HALVING_INTERVAL), Amount::ZERO);
+
+    assert_eq!(get_block_subsidy(64 * HALVING_INTERVAL), Amount::ZERO);
+    assert_eq!(get_block_subsidy(u64::MAX), Amount::ZERO);
+}
+
+#[test]
+fn circulating_supply() {
+    assert_eq!(MAX_SUPPLY.to_string(), "314998.4895");
+
+    assert_eq!(get_circulating_supply(0), Amount::ZERO);
+    assert_eq!(get_circulating_supply(1), INITIAL_SUBSIDY);
+    assert_eq!(get_circulating_supply(HALVING_INTERVAL - 1), Amount::from_snails((HALVING_INTERVAL - 1) * INITIAL_SUBSIDY.to_snails()));
+
+    // every block adds its subsidy
+    for height in boundaries() {
+        let supply: Amount = get_circulating_supply(height);
+        assert!(supply <= MAX_SUPPLY, "height {}", height);
+        assert_eq!(get_circulating_supply(height - 1).checked_add(get_block_subsidy(height)), Some(supply), "height {}", height);
+    }
+
+    // once the subsidy is gone, the supply is all there will ever be
+    assert_eq!(get_circulating_supply(28 * HALVING_INTERVAL), MAX_SUPPLY);
+    assert_eq!(get_circulating_supply(u64::MAX), MAX_SUPPLY);
+}
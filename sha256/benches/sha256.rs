@@ -0,0 +1,232 @@
+//! Compares the bitvec based implementation that sha256 used to have with the current one,
+//! and hashing a block header from scratch with hashing it from a midstate.
+//!
+//! cargo bench -p sha256
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use sha256::{digest, Sha256};
+
+/// The implementation before compression worked on bytes, kept here as a baseline
+mod legacy {
+    #![allow(clippy::all)]
+
+    use bitvec::prelude::*;
+    use sha256::hash::{get_initial_hash_values, get_round_constants};
+
+    /// Function to hash a string using the SHA-256 algorithm
+    /// 
+    /// # Arguments
+    /// * `data` - A string slice that holds the data to be hashed
+    /// 
+    /// # Returns
+    /// * A string that holds the hashed data as a hexadecimal string
+    /// 
+    pub fn hash(data: String) -> String {
+        let bit_vec = get_processed_data(data);
+        let closest_512_multiple: usize = ((bit_vec.len() + 512 - 1) / 512) * 512;
+
+        let k: [u32; 64] = get_round_constants();
+        let mut hash_values: [u32; 8] = get_initial_hash_values();
+
+        // 512bits chunk loop
+        for i in (512..=closest_512_multiple).step_by(512) {
+            let mut w: [u32; 64] = get_big_endian_words_from_512bits(&bit_vec[(i - 512)..i]);
+            
+            // extended first 16 words into next zero-ed indexes
+            for j in 16..64 {
+                let s0: u32 = right_rotate(w[j-15], 7) ^ right_rotate(w[j-15], 18) ^ (w[j-15] >> 3);
+                let s1: u32 = right_rotate(w[j-2], 17) ^ right_rotate(w[j-2], 19) ^ (w[j-2] >> 10);
+
+                w[j] = w[j-16].wrapping_add(s0).wrapping_add(w[j-7]).wrapping_add(s1);
+            }
+            
+            let mut a: u32 = hash_values[0];
+            let mut b: u32 = hash_values[1];
+            let mut c: u32 = hash_values[2];
+            let mut d: u32 = hash_values[3];
+            let mut e: u32 = hash_values[4];
+            let mut f: u32 = hash_values[5];
+            let mut g: u32 = hash_values[6];
+            let mut h: u32 = hash_values[7];
+
+            // compress chunk into hash values
+            for j in 0..64 {
+                #[allow(non_snake_case)]
+                let S1: u32 = right_rotate(e, 6) ^ right_rotate(e, 11) ^ right_rotate(e, 25);
+                let ch: u32 = (e & f) ^ (!e & g);
+                let temp1: u32 = h.wrapping_add(S1).wrapping_add(ch).wrapping_add(k[j]).wrapping_add(w[j]);
+                
+                #[allow(non_snake_case)]
+                let S0: u32 = right_rotate(a, 2) ^ right_rotate(a, 13) ^ right_rotate(a, 22);
+                let maj: u32 = (a & b) ^ (a & c) ^ (b & c);
+                let temp2: u32 = S0.wrapping_add(maj);
+
+                h = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(temp1);
+                d = c;
+                c = b;
+                b = a;
+                a = temp1.wrapping_add(temp2);
+
+            }
+
+            // update hash values
+            hash_values[0] = hash_values[0].wrapping_add(a);
+            hash_values[1] = hash_values[1].wrapping_add(b);
+            hash_values[2] = hash_values[2].wrapping_add(c);
+            hash_values[3] = hash_values[3].wrapping_add(d);
+            hash_values[4] = hash_values[4].wrapping_add(e);
+            hash_values[5] = hash_values[5].wrapping_add(f);
+            hash_values[6] = hash_values[6].wrapping_add(g);
+            hash_values[7] = hash_values[7].wrapping_add(h);
+        }
+        
+        return hash_values.iter().map(|&val| format!("{:08x}", val)).collect();
+    }
+
+    /// Function to get the 512 bits chunk as an array of 64 32-bit words.
+    /// Most words are 0s only 16 first are filled with the 32 bits of the slice.
+    /// 
+    /// # Arguments
+    /// * `slice` - A BitSlice that holds the 512 bits chunk
+    /// 
+    /// # Returns
+    /// * An array of 64 u32 values that represent the 512 bits chunk
+    /// 
+    fn get_big_endian_words_from_512bits(slice: &BitSlice) -> [u32; 64] {
+
+        // init schedule array with 0s
+        let mut w: [u32; 64] = [0; 64];
+        let mut j = 0;
+
+        // iterate over every 32 bits of the slice and add it to the w array (big-endian)
+        for i in (32..=slice.len()).step_by(32) {
+
+            // load_le and load_be methods not working here, had to do it by hand
+            for (k, bit) in slice[(i-32)..i].iter().enumerate() {
+                w[j] |= if *bit { 1 << 31 - k } else { 0 };
+            }
+
+            j += 1;
+        }
+
+        w
+    }
+
+    /// Function to right rotate a 32-bit number by n bits
+    /// 
+    /// # Arguments
+    /// * `x` - A u32 number to rotate
+    /// * `n` - A u32 number that represents the number of bits to rotate
+    /// 
+    fn right_rotate(x: u32, n: u32) -> u32 {
+        (x >> n) | (x << (32 - n))
+    }
+     
+    /// Function to get the processed data for the SHA-256 algorithm.
+    /// The data is processed as follows:
+    /// 1. Add 1 to the end of the data
+    /// 2. Add 0s until the length of the data is a multiple of 512
+    /// 3. Add the number of bits from the original data in big-endian
+    /// 
+    /// # Arguments
+    /// * `data` - A string that holds the data to be processed
+    /// 
+    /// # Returns
+    /// * A BitVec that holds the processed data
+    ///
+    fn get_processed_data(data: String) -> BitVec {
+        let mut bit_vec: BitVec = bitvec![];
+
+        // iterate over every bit of the data and add it to the bitvec
+        for c in data.chars() {
+            let mut c_as_32: u32 = c as u32;
+
+            for _ in 0..8 { // 8 bits for 0-led chars
+                bit_vec.push((1 << 7) & c_as_32 != 0);
+                c_as_32 <<= 1;
+            }
+        }
+
+        bit_vec.push(true); // add one to the end of the bitvec
+
+        let closest_512_multiple: usize = ((bit_vec.len() + 64 + 512 - 1) / 512) * 512;
+        
+        // 0 padding
+        for _ in 0..closest_512_multiple - bit_vec.len() - 64 {
+            bit_vec.push(false);
+        }
+
+        let mut data_n_bits: u64 = (data.len() * 8) as u64;
+        // add number of bits from original data in big-endian
+        for _ in 0..64 {
+            bit_vec.push(data_n_bits & (1 << 63) != 0);
+            data_n_bits <<= 1;
+        }
+        
+        return bit_vec;
+    }
+}
+
+/// the part of a block header message that comes before the nonce: height, timestamp, previous hash
+fn header_prefix() -> String {
+    format!("{}{}{}", 1234, 1712016000u64, "00000a3c".repeat(8))
+}
+
+/// the part of a block header message that comes after the previous hash: nonce, difficulty, merkel root
+fn header_suffix(nonce: u32) -> String {
+    format!("{}{}{}", nonce, 0x0fffffffu32, "b1e2".repeat(16))
+}
+
+fn compare_implementations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sha256");
+
+    for len in [64usize, 160, 1024] {
+        let data: String = "a".repeat(len);
+
+        group.bench_with_input(BenchmarkId::new("legacy", len), &data, |b, data| {
+            b.iter(|| legacy::hash(black_box(data.clone())))
+        });
+
+        group.bench_with_input(BenchmarkId::new("current", len), &data, |b, data| {
+            b.iter(|| digest(black_box(data.as_bytes())))
+        });
+    }
+
+    group.finish();
+}
+
+fn midstate(c: &mut Criterion) {
+    let prefix: String = header_prefix();
+
+    // the prefix doesn't change while mining so its full chunks only need to be compressed once
+    let mut midstate: Sha256 = Sha256::new();
+    midstate.update(prefix.as_bytes());
+
+    let mut group = c.benchmark_group("header");
+    let mut nonce: u32 = 0;
+
+    group.bench_function("from scratch", |b| b.iter(|| {
+        nonce = nonce.wrapping_add(1);
+
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(prefix.as_bytes());
+        hasher.update(header_suffix(black_box(nonce)).as_bytes());
+        hasher.finalize()
+    }));
+
+    group.bench_function("from midstate", |b| b.iter(|| {
+        nonce = nonce.wrapping_add(1);
+
+        let mut hasher: Sha256 = midstate.clone();
+        hasher.update(header_suffix(black_box(nonce)).as_bytes());
+        hasher.finalize()
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, compare_implementations, midstate);
+criterion_main!(benches);
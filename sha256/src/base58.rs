@@ -0,0 +1,136 @@
+use core::fmt;
+use crate::double_digest;
+
+/// The digits of base 58, in order. 0, O, I and l are left out since they are easy to mix up
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The number of bytes of the checksum at the end of Base58Check data
+const CHECKSUM_LEN: usize = 4;
+
+/// Reasons for which a string can't be decoded from Base58Check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base58Error {
+    /// The string has a character that isn't a base 58 digit
+    InvalidCharacter,
+
+    /// The string decodes to fewer bytes than a checksum
+    TooShort,
+
+    /// The checksum doesn't match the data, the string has a typo
+    InvalidChecksum
+}
+
+/// implement display for Base58Error for easy printing of decoding errors
+impl fmt::Display for Base58Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Base58Error::InvalidCharacter => write!(f, "invalid base 58 character"),
+            Base58Error::TooShort => write!(f, "too short for a checksum"),
+            Base58Error::InvalidChecksum => write!(f, "invalid checksum")
+        }
+    }
+}
+
+impl std::error::Error for Base58Error {}
+
+/// Function to write bytes in base 58, every leading zero byte is written as a 1
+///
+/// # Arguments
+/// * `bytes` - A byte slice that holds the data
+///
+/// # Returns
+/// * The base 58 string of the data
+///
+pub fn base58_encode(bytes: &[u8]) -> String {
+    // the digits of the number in base 58, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+
+    for &byte in bytes {
+        let mut carry: u32 = byte as u32;
+
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros: usize = bytes.iter().take_while(|&&byte| byte == 0).count();
+
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&digit| BASE58_ALPHABET[digit as usize] as char))
+        .collect()
+}
+
+/// Function to read bytes written in base 58, the reverse of base58_encode
+///
+/// # Arguments
+/// * `s` - A string slice of base 58 digits
+///
+/// # Returns
+/// * The bytes, None if a character isn't a base 58 digit
+///
+pub fn base58_decode(s: &str) -> Option<Vec<u8>> {
+    // the bytes of the number, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+
+    for c in s.bytes() {
+        let mut carry: u32 = BASE58_ALPHABET.iter().position(|&digit| digit == c)? as u32;
+
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros: usize = s.bytes().take_while(|&c| c == b'1').count();
+
+    Some(std::iter::repeat_n(0, zeros).chain(bytes.into_iter().rev()).collect())
+}
+
+/// Function to write data in Base58Check, the data followed by the first 4 bytes of its SHA256d in base 58
+///
+/// # Arguments
+/// * `payload` - A byte slice that holds the data, usually a version byte followed by a hash or a key
+///
+/// # Returns
+/// * The Base58Check string of the data
+///
+pub fn base58check_encode(payload: &[u8]) -> String {
+    let checksum: [u8; 32] = double_digest(payload);
+    base58_encode(&[payload, &checksum[..CHECKSUM_LEN]].concat())
+}
+
+/// Function to read data written in Base58Check, checking its checksum
+///
+/// # Arguments
+/// * `s` - A string slice that holds the Base58Check string
+///
+/// # Returns
+/// * The data without its checksum, or why the string isn't valid Base58Check
+///
+pub fn base58check_decode(s: &str) -> Result<Vec<u8>, Base58Error> {
+    let mut bytes: Vec<u8> = base58_decode(s).ok_or(Base58Error::InvalidCharacter)?;
+
+    if bytes.len() < CHECKSUM_LEN {
+        return Err(Base58Error::TooShort);
+    }
+
+    let checksum: Vec<u8> = bytes.split_off(bytes.len() - CHECKSUM_LEN);
+    if double_digest(&bytes)[..CHECKSUM_LEN] != checksum[..] {
+        return Err(Base58Error::InvalidChecksum);
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,129 @@
+use zeroize::Zeroize;
+use crate::{hash::compress, HX};
+
+/// Incremental SHA-256 hasher, for data that comes in several pieces or is too big to hold at once.
+///
+/// Data is compressed as soon as a full 512 bits chunk is available, only the last partial
+/// chunk is kept until more data comes or the hash is finalized.
+///
+/// A clone of a hasher is its midstate: when many messages start with the same prefix, the
+/// prefix can be hashed once and the clone given the rest of each message. Miners use this
+/// for the part of a block header that comes before the nonce.
+///
+/// # Example
+/// ```
+/// let mut hasher = sha256::Sha256::new();
+/// hasher.update(b"ab");
+/// hasher.update(b"c");
+///
+/// assert_eq!(hasher.finalize(), sha256::digest(b"abc"));
+/// ```
+#[derive(Clone)]
+pub struct Sha256 {
+    /// The hash values of the chunks compressed so far
+    hash_values: [u32; 8],
+
+    /// The bytes that don't fill a whole chunk yet
+    buffer: [u8; 64],
+    buffer_len: usize,
+
+    /// Number of bytes given so far
+    length: u64
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256::new()
+    }
+}
+
+impl Sha256 {
+    /// returns a hasher that hasn't been given any data
+    pub fn new() -> Self {
+        Sha256 {
+            hash_values: HX,
+            buffer: [0; 64],
+            buffer_len: 0,
+            length: 0
+        }
+    }
+
+    /// Adds data to hash
+    ///
+    /// # Arguments
+    /// * `data` - A byte slice that holds the next bytes of the data
+    ///
+    /// # Modifications
+    /// * Compresses every chunk that gets completed and keeps the rest, hence the mut self
+    ///
+    pub fn update(&mut self, data: &[u8]) {
+        let mut data: &[u8] = data;
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        // complete the buffered chunk first
+        if self.buffer_len > 0 {
+            let taken: usize = data.len().min(64 - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + taken].copy_from_slice(&data[..taken]);
+            self.buffer_len += taken;
+            data = &data[taken..];
+
+            if self.buffer_len < 64 {
+                return;
+            }
+
+            compress(&mut self.hash_values, &self.buffer);
+            self.buffer_len = 0;
+        }
+
+        let mut chunks = data.chunks_exact(64);
+        for chunk in &mut chunks {
+            compress(&mut self.hash_values, chunk.try_into().unwrap());
+        }
+
+        let rest: &[u8] = chunks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Pads the data and returns its hash.
+    /// The padding is a 1 bit, 0s until the length is 64 bits short of a multiple of 512,
+    /// then the number of bits of the data in big-endian
+    ///
+    /// # Returns
+    /// * The 32 bytes digest of all the data given
+    ///
+    pub fn finalize(mut self) -> [u8; 32] {
+        self.finalize_in_place()
+    }
+
+    /// Pads the data and returns its hash as finalize does, without moving the hasher,
+    /// so that a hasher of secret data can be zeroed afterwards
+    pub(crate) fn finalize_in_place(&mut self) -> [u8; 32] {
+        // lengths over 2^64 bits wrap around as the standard says
+        let n_bits: u64 = self.length.wrapping_mul(8);
+
+        let mut padding: [u8; 72] = [0; 72];
+        padding[0] = 0x80;
+        let padding_len: usize = if self.buffer_len < 56 { 56 - self.buffer_len } else { 120 - self.buffer_len };
+
+        self.update(&padding[..padding_len]);
+        self.update(&n_bits.to_be_bytes());
+
+        let mut digest: [u8; 32] = [0; 32];
+        for (i, value) in self.hash_values.iter().enumerate() {
+            digest[i * 4..(i + 1) * 4].copy_from_slice(&value.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+/// the state and the buffered bytes are enough to compute the hash of secret data, such as an HMAC key
+impl Zeroize for Sha256 {
+    fn zeroize(&mut self) {
+        self.hash_values.zeroize();
+        self.buffer.zeroize();
+        self.buffer_len.zeroize();
+        self.length.zeroize();
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{hmac, HmacSha256};
+
+/// Longest output HKDF-SHA256 can expand to, 255 blocks of 32 bytes
+pub const HKDF_MAX_LEN: usize = 255 * 32;
+
+/// Function to extract a pseudorandom key from input keying material (RFC 5869, step 1)
+///
+/// # Arguments
+/// * `salt` - A byte slice that holds an optional non-secret salt, empty means a block of zeros
+/// * `ikm` - A byte slice that holds the input keying material
+///
+/// # Returns
+/// * The 32 bytes pseudorandom key
+///
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    // an empty key is padded with zeros just like a key of 32 zeros would be
+    hmac(salt, ikm)
+}
+
+/// Function to expand a pseudorandom key into as many bytes as needed (RFC 5869, step 2)
+///
+/// # Arguments
+/// * `prk` - A byte slice that holds a pseudorandom key, usually from hkdf_extract
+/// * `info` - A byte slice that holds context specific information, can be empty
+/// * `len` - The number of bytes to output
+///
+/// # Returns
+/// * The output keying material, None if len is more than HKDF_MAX_LEN
+///
+pub fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Option<Vec<u8>> {
+    if len > HKDF_MAX_LEN {
+        return None;
+    }
+
+    let mut okm: Vec<u8> = Vec::with_capacity(len);
+    let mut block: [u8; 32] = [0; 32];
+    let mut counter: u8 = 1;
+
+    // T(i) = HMAC(prk, T(i-1) || info || i), with T(0) empty
+    while okm.len() < len {
+        let mut mac: HmacSha256 = HmacSha256::new(prk);
+        if counter > 1 {
+            mac.update(&block);
+        }
+        mac.update(info);
+        mac.update(&[counter]);
+        block = mac.finalize();
+
+        let taken: usize = (len - okm.len()).min(block.len());
+        okm.extend_from_slice(&block[..taken]);
+        counter = counter.wrapping_add(1);
+    }
+
+    Some(okm)
+}
+
+/// Function to derive keys with HKDF-SHA256, extract then expand
+///
+/// # Arguments
+/// * `salt` - A byte slice that holds an optional non-secret salt
+/// * `ikm` - A byte slice that holds the input keying material
+/// * `info` - A byte slice that holds context specific information
+/// * `len` - The number of bytes to output
+///
+/// # Returns
+/// * The output keying material, None if len is more than HKDF_MAX_LEN
+///
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Option<Vec<u8>> {
+    hkdf_expand(&hkdf_extract(salt, ikm), info, len)
+}
@@ -0,0 +1,102 @@
+use zeroize::Zeroize;
+use crate::{digest, Sha256};
+
+/// Size in bytes of the blocks SHA-256 compresses, keys are padded (or hashed) to this size
+const BLOCK_SIZE: usize = 64;
+
+/// Incremental HMAC-SHA256 (RFC 2104), a message authentication code keyed with a secret.
+///
+/// The key is padded to a block, xored with the inner and outer pads, and the message is
+/// hashed as `H((key ^ opad) || H((key ^ ipad) || message))`.
+///
+/// # Example
+/// ```
+/// let mut mac = sha256::HmacSha256::new(b"key");
+/// mac.update(b"some ");
+/// mac.update(b"data");
+///
+/// assert_eq!(mac.finalize(), sha256::hmac(b"key", b"some data"));
+/// ```
+#[derive(Clone)]
+pub struct HmacSha256 {
+    /// Hasher of the inner hash, already given the key xored with ipad
+    inner: Sha256,
+
+    /// Hasher of the outer hash, already given the key xored with opad
+    outer: Sha256
+}
+
+impl HmacSha256 {
+    /// returns an HMAC keyed with the given key, keys longer than a block are hashed first
+    ///
+    /// # Arguments
+    /// * `key` - A byte slice that holds the secret key, of any length
+    ///
+    pub fn new(key: &[u8]) -> Self {
+        let mut block: [u8; BLOCK_SIZE] = [0; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            block[..32].copy_from_slice(&digest(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner_pad: [u8; BLOCK_SIZE] = block.map(|byte| byte ^ 0x36);
+        let mut outer_pad: [u8; BLOCK_SIZE] = block.map(|byte| byte ^ 0x5c);
+
+        let mut inner: Sha256 = Sha256::new();
+        let mut outer: Sha256 = Sha256::new();
+        inner.update(&inner_pad);
+        outer.update(&outer_pad);
+
+        block.zeroize();
+        inner_pad.zeroize();
+        outer_pad.zeroize();
+
+        HmacSha256 { inner, outer }
+    }
+
+    /// Adds data to authenticate
+    ///
+    /// # Arguments
+    /// * `data` - A byte slice that holds the next bytes of the message
+    ///
+    /// # Modifications
+    /// * Gives the data to the inner hasher, hence the mut self
+    ///
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /// returns the 32 bytes authentication code of all the data given
+    pub fn finalize(mut self) -> [u8; 32] {
+        let mut inner_hash: [u8; 32] = self.inner.finalize_in_place();
+        self.outer.update(&inner_hash);
+        inner_hash.zeroize();
+
+        self.outer.finalize_in_place()
+    }
+}
+
+/// the hashers were given the key, anyone that has their state can authenticate messages
+impl Drop for HmacSha256 {
+    fn drop(&mut self) {
+        self.inner.zeroize();
+        self.outer.zeroize();
+    }
+}
+
+/// Function to compute the HMAC-SHA256 of a message in one go
+///
+/// # Arguments
+/// * `key` - A byte slice that holds the secret key
+/// * `data` - A byte slice that holds the message
+///
+/// # Returns
+/// * The 32 bytes authentication code
+///
+pub fn hmac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac: HmacSha256 = HmacSha256::new(key);
+    mac.update(data);
+
+    mac.finalize()
+}
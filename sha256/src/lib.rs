@@ -1,5 +1,8 @@
 //! # SHA-256
 //! https://en.wikipedia.org/wiki/SHA-2
+//!
+//! Also SHA256d (double SHA-256), HMAC-SHA256 and HKDF-SHA256 built on top of it,
+//! and Base58Check whose checksum is a SHA256d.
 
 
 // initial hash values
@@ -17,4 +20,16 @@ const ROUND_CONSTANTS: [u32; 64]= [1116352408, 1899447441, 3049323471, 392100957
 // pub hash module because I want initial hash values and round constants methods available               
 pub mod hash;
 
-pub use hash::hash;
\ No newline at end of file
+pub use hash::{digest, double_digest, double_hash, from_hex, hash, to_hex};
+
+mod hasher;
+pub use hasher::Sha256;
+
+mod hmac;
+pub use hmac::{hmac, HmacSha256};
+
+mod hkdf;
+pub use hkdf::{hkdf, hkdf_expand, hkdf_extract, HKDF_MAX_LEN};
+
+mod base58;
+pub use base58::{base58_decode, base58_encode, base58check_decode, base58check_encode, Base58Error};
@@ -0,0 +1,81 @@
+//! HMAC-SHA256 test vectors from RFC 4231 and HKDF-SHA256 test vectors from RFC 5869
+
+use sha256::{from_hex, hkdf, hkdf_expand, hkdf_extract, hmac, to_hex, HmacSha256, HKDF_MAX_LEN};
+
+#[test]
+fn rfc_4231() {
+    let vectors: [(Vec<u8>, &[u8], &str); 6] = [
+        (vec![0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+        (b"Jefe".to_vec(), b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+        (vec![0xaa; 20], &[0xdd; 50], "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe"),
+        ((1..=25).collect(), &[0xcd; 50], "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b"),
+        (vec![0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        (vec![0xaa; 131], b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.",
+            "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2")
+    ];
+
+    for (key, data, expected) in vectors {
+        assert_eq!(to_hex(&hmac(&key, data)), expected, "key: {}", to_hex(&key));
+    }
+
+    // test case 5 only gives the first 128 bits
+    assert_eq!(to_hex(&hmac(&[0x0c; 20], b"Test With Truncation")[..16]), "a3b6167473100ee06e0c796c2955552b");
+}
+
+#[test]
+fn streaming_hmac_matches_one_shot() {
+    let data: Vec<u8> = (0..=255).cycle().take(200).collect();
+
+    for key_len in [0, 32, 64, 65, 131] {
+        let key: Vec<u8> = vec![0x42; key_len];
+        let expected: [u8; 32] = hmac(&key, &data);
+
+        for split in [0, 1, 63, 64, 65, 200] {
+            let mut mac: HmacSha256 = HmacSha256::new(&key);
+            mac.update(&data[..split]);
+            mac.update(&data[split..]);
+
+            assert_eq!(mac.finalize(), expected, "key length: {}, split: {}", key_len, split);
+        }
+    }
+}
+
+/// input keying material, salt, info, pseudorandom key and output keying material
+type HkdfVector = (Vec<u8>, Vec<u8>, Vec<u8>, &'static str, &'static str);
+
+#[test]
+fn rfc_5869() {
+    let vectors: [HkdfVector; 3] = [
+        (vec![0x0b; 22], (0x00..=0x0c).collect(), (0xf0..=0xf9).collect(),
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"),
+        ((0x00..=0x4f).collect(), (0x60..=0xaf).collect(), (0xb0..=0xff).collect(),
+            "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+            "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87"),
+        (vec![0x0b; 22], vec![], vec![],
+            "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8")
+    ];
+
+    for (ikm, salt, info, prk, okm) in vectors {
+        let len: usize = okm.len() / 2;
+
+        assert_eq!(to_hex(&hkdf_extract(&salt, &ikm)), prk);
+        assert_eq!(to_hex(&hkdf_expand(&from_hex(prk).unwrap(), &info, len).unwrap()), okm);
+        assert_eq!(to_hex(&hkdf(&salt, &ikm, &info, len).unwrap()), okm);
+    }
+}
+
+#[test]
+fn hkdf_output_length() {
+    let prk: [u8; 32] = hkdf_extract(b"salt", b"secret");
+
+    assert_eq!(hkdf_expand(&prk, b"", 0).unwrap().len(), 0);
+    assert_eq!(hkdf_expand(&prk, b"", HKDF_MAX_LEN).unwrap().len(), HKDF_MAX_LEN);
+    assert!(hkdf_expand(&prk, b"", HKDF_MAX_LEN + 1).is_none());
+
+    // shorter outputs are prefixes of longer ones
+    let long: Vec<u8> = hkdf_expand(&prk, b"info", 100).unwrap();
+    assert_eq!(hkdf_expand(&prk, b"info", 33).unwrap(), long[..33]);
+}
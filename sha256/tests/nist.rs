@@ -0,0 +1,91 @@
+//! SHA-256 test vectors from NIST: the FIPS 180-2 examples and the byte-oriented
+//! messages of SHA256ShortMsg.rsp (CAVS 11.0), and SHA256d
+
+use sha256::{digest, double_digest, double_hash, from_hex, hash, to_hex, Sha256};
+
+#[test]
+fn fips_180_examples() {
+    let vectors: [(&str, &str); 4] = [
+        ("", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        ("abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        ("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"),
+        ("abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+            "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1")
+    ];
+
+    for (message, expected) in vectors {
+        assert_eq!(hash(message), expected, "message: {:?}", message);
+        assert_eq!(to_hex(&digest(message.as_bytes())), expected, "message: {:?}", message);
+    }
+}
+
+#[test]
+fn short_messages() {
+    let vectors: [(&str, &str); 8] = [
+        ("d3", "28969cdfa74a12c82f3bad960b0b000aca2ac329deea5c2328ebc6f2ba9802c1"),
+        ("11af", "5ca7133fa735326081558ac312c620eeca9970d1e70a4b95533d956f072d1f98"),
+        ("b4190e", "dff2e73091f6c05e528896c4c831b9448653dc2ff043528f6769437bc7b975c2"),
+        ("74ba2521", "b16aa56be3880d18cd41e68384cf1ec8c17680c45a02b1575dc1518923ae8b0e"),
+        ("c299209682", "f0887fe961c9cd3beab957e8222494abb969b1ce4c6557976df8b0f6d20e9166"),
+        ("e1dc724d5621", "eca0a060b489636225b4fa64d267dabbe44273067ac679f20820bddc6b6a90ac"),
+        ("06e076f5a442d5", "3fd877e27450e6bbd5d74bb82f9870c64c66e109418baa8e6bbcff355e287926"),
+        ("5738c929c4f4ccb6", "963bb88f27f512777aab6c8b1a02c70ec0ad651d428f870036e1917120fb48bf")
+    ];
+
+    for (message, expected) in vectors {
+        assert_eq!(to_hex(&digest(&from_hex(message).unwrap())), expected, "message: {}", message);
+    }
+}
+
+#[test]
+fn million_a() {
+    let expected: &str = "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0";
+    assert_eq!(to_hex(&digest(&[b'a'; 1_000_000])), expected);
+
+    // same data given in pieces that don't line up with chunks
+    let mut hasher: Sha256 = Sha256::new();
+    for _ in 0..10_000 {
+        hasher.update(&[b'a'; 100]);
+    }
+
+    assert_eq!(to_hex(&hasher.finalize()), expected);
+}
+
+#[test]
+fn streaming_matches_one_shot() {
+    let data: Vec<u8> = (0..=255).cycle().take(300).collect();
+
+    // every length around the padding edges, split at every point
+    for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 300] {
+        let expected: [u8; 32] = digest(&data[..len]);
+
+        for split in 0..=len {
+            let mut hasher: Sha256 = Sha256::new();
+            hasher.update(&data[..split]);
+            hasher.update(&data[split..len]);
+
+            assert_eq!(hasher.finalize(), expected, "len: {}, split: {}", len, split);
+        }
+    }
+}
+
+#[test]
+fn strings_are_hashed_as_utf8() {
+    // "é" is 0xc3 0xa9 in UTF-8
+    assert_eq!(hash("é"), "4a99557e4033c3539de2eb65472017cad5f9557f7a0625a09f1c3f6e2ba69c4c");
+    assert_eq!(hash(String::from("é")), to_hex(&digest(&[0xc3, 0xa9])));
+}
+
+#[test]
+fn double_sha256() {
+    assert_eq!(double_hash(""), "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456");
+    assert_eq!(double_digest(b"abc"), digest(&digest(b"abc")));
+
+    // the bitcoin genesis block header, its id is the double digest written in reverse
+    let header: Vec<u8> = from_hex("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c").unwrap();
+    let mut id: [u8; 32] = double_digest(&header);
+    id.reverse();
+
+    assert_eq!(to_hex(&id), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+}
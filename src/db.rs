@@ -1,408 +1,1066 @@
 use std::{collections::HashMap, io::{self, ErrorKind}, path::PathBuf};
 use dirs::home_dir;
 use ecdsa::secp256k1::Point;
-use rblock::{Block, Transaction};
-use rusty_leveldb::{DBIterator, LdbIterator, Options, Status, DB};
-use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::Cursor;
+use rblock::{get_block_subsidy, Address, Amount, Block, BlockHeader};
+use rusty_leveldb::{DBIterator, LdbIterator, Options, Status, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use crate::validation::{validate_block, BlockError};
 
-pub const DB_FILENAME: &'static str = ".r_blocks";
-const LATEST_BLOCK_KEY: &'static [u8; 6] = b"latest";
-const PUBLIC_KEY_PREFIX: &'static [u8; 7] = b"userPK_";
+pub const DB_FILENAME: &str = ".r_blocks";
+const LATEST_BLOCK_KEY: &[u8; 6] = b"latest";
+const ADDRESS_PREFIX: &[u8; 5] = b"addr_";
+const BLOCK_PREFIX: &[u8; 6] = b"block_";
+const HEIGHT_PREFIX: &[u8; 7] = b"height_";
+const TIP_PREFIX: &[u8; 4] = b"tip_";
+const UNDO_PREFIX: &[u8; 5] = b"undo_";
+const INVALID_PREFIX: &[u8; 8] = b"invalid_";
+
+/// The chainstate of an address
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub balance: Amount,
+
+    /// The nonce that the account's next transaction must have, it is the number of transactions the account sent
+    pub nonce: u64
+}
+
+/// The accounts touched by a block before it was applied, None if the account didn't exist.
+/// Used to roll back the chainstate when the block leaves the main chain.
+type UndoData = Vec<(Address, Option<Account>)>;
+
+/// Pending chainstate changes that haven't been written to the db yet.
+/// A None account means the account is to be deleted.
+type Overlay = HashMap<Address, Option<Account>>;
+
+/// How the main chain changed when a block was added.
+/// Both lists are empty if the block went on a side branch that doesn't have the most work.
+#[derive(Default)]
+pub struct ChainUpdate {
+    /// Blocks that left the main chain, latest first
+    pub disconnected: Vec<Block>,
+
+    /// Blocks that joined the main chain, in increasing height
+    pub connected: Vec<Block>
+}
+
+/// Why the main chain couldn't be moved onto a new tip
+enum ReorgError {
+    /// A block of the new branch is invalid, or was already marked as such
+    Invalid { hash: String, error: BlockError },
+
+    /// The db failed while reading the branches
+    Db(Status)
+}
+
+impl From<Status> for ReorgError {
+    fn from(status: Status) -> Self {
+        ReorgError::Db(status)
+    }
+}
+
+/// A block as it is stored in the db, with the total work of the chain that it ends
+#[derive(Serialize, Deserialize)]
+struct StoredBlock {
+    block: Block,
+    cumulative_work: u128
+}
 
 
 /// A struct that represents a database of blocks.
-/// 
+///
+/// Blocks are indexed by hash so that competing blocks at the same height can be stored.
+/// The main chain is indexed by height and ends at the `latest` block, which is the tip with the most cumulative work.
+/// Every block without children is tracked as a tip so that side branches are known.
+///
 /// # Fields
 /// * `db` - A DB object that represents the database of blocks
-/// 
+///
 pub struct BlocksDB {
     db: DB
 }
 
 impl BlocksDB {
     /// Starts the database and returns a BlocksDB object with the database
-    /// 
+    ///
     /// # Modifications
     /// This method creates a new database file in the home directory of the user.
-    /// 
+    ///
     pub fn start_db() -> Result<Self, Status> {
-        let mut options: Options = Options::default();
-        options.create_if_missing = false; // create DB if missing
-        
         // get home directory
         let path: PathBuf = home_dir().ok_or_else(|| {
             io::Error::new(ErrorKind::NotFound, "Home directory could not be found")
         })?;
 
-        let db: DB = DB::open(path.join(DB_FILENAME), options)?;
-        Ok(BlocksDB { db })
+        BlocksDB::open(path.join(DB_FILENAME))
     }
 
+    /// Opens the database at the given path and returns a BlocksDB object with the database.
+    /// Used to run several nodes on the same machine, each with its own database.
+    ///
+    /// # Arguments
+    /// * `path` - A PathBuf which specifies where the database is stored
+    ///
+    /// # Modifications
+    /// This method creates the database and puts the genesis block in it if this is the first time it is opened.
+    ///
+    pub fn open(path: PathBuf) -> Result<Self, Status> {
+        let options: Options = Options {
+            create_if_missing: true, // create DB if missing
+            ..Default::default()
+        };
 
-    // TODO: don't forget to remove this function
-    pub fn init_db(&mut self, point1: &Point, point2: &Point) {
-        let genesis: Block = Block::new_genesis();
+        let db: DB = DB::open(path, options)?;
+        let mut blocks_db: BlocksDB = BlocksDB { db };
 
-        self.put_block(&genesis).unwrap();
-        self.update_latest_block(&genesis).unwrap();
+        // first time running the node, genesis is the same for everyone so no need to get it from a peer
+        if blocks_db.db.get(LATEST_BLOCK_KEY).is_none() {
+            let mut batch: WriteBatch = WriteBatch::new();
+            BlocksDB::put_genesis(&mut batch);
+            blocks_db.db.write(batch, true)?;
+        }
 
-        self.update_balance(point1, 10.).unwrap();
-        self.update_balance(point2, 10.).unwrap();
+        Ok(blocks_db)
     }
 
-    /// Reads and returns the block with a specific height if it exists
-    /// 
+    /// Puts the genesis block in a batch as the only block of the main chain
+    ///
+    /// # Arguments
+    /// * `batch` - A &mut WriteBatch in which the genesis block is put
+    ///
+    fn put_genesis(batch: &mut WriteBatch) {
+        let genesis: Block = Block::new_genesis();
+        let stored: StoredBlock = StoredBlock { cumulative_work: genesis.get_work(), block: genesis.clone() };
+
+        batch.put(&BlocksDB::get_db_block_key(&genesis.get_hash()), &bincode::serialize(&stored).unwrap());
+        batch.put(&BlocksDB::get_db_height_key(0), genesis.get_hash().as_bytes());
+        batch.put(&BlocksDB::get_db_tip_key(&genesis.get_hash()), &[]);
+        batch.put(LATEST_BLOCK_KEY, genesis.get_hash().as_bytes());
+    }
+
+
+    /// Reads and returns the block of the main chain with a specific height if it exists
+    ///
     /// # Arguments
     /// * `height` - A u64 that specifies the index of the block in the DB
-    /// 
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling get on it.
-    /// 
+    ///
     /// # Returns
-    /// An Option<Block> which is the block at the specified height if it exists in the db, or None if it does not.
-    /// 
+    /// An Result<Block, Status> which is the block at the specified height if it exists in the db, or NotFound if it does not.
+    ///
     pub fn get_block(&mut self, height: u64) -> Result<Block, Status> {
         // convert height to little-endian for standard use throughout project
-        match self.db.get(&height.to_le_bytes()) {
-            Some(bytes) => {
-                let block: Block = bincode::deserialize(&bytes).map_err(|e| 
-                    Status::new(rusty_leveldb::StatusCode::Corruption, &format!("{e}"))
-                )?; 
-
-                Ok(block)
-            },
-            None => { 
-                Err(Status::new(rusty_leveldb::StatusCode::NotFound, &format!("Block not found"))) 
+        match self.db.get(&BlocksDB::get_db_height_key(height)) {
+            Some(hash) => self.get_block_by_hash(&String::from_utf8_lossy(&hash)),
+            None => {
+                Err(Status::new(rusty_leveldb::StatusCode::NotFound, "Block not found"))
             }
         }
     }
 
+    /// Reads and returns the block with a specific hash if it exists, whether it's on the main chain or not
+    ///
+    /// # Arguments
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    /// # Returns
+    /// An Result<Block, Status> which is the block with the specified hash if it exists in the db, or NotFound if it does not.
+    ///
+    pub fn get_block_by_hash(&mut self, hash: &str) -> Result<Block, Status> {
+        Ok(self.get_stored_block(hash)?.block)
+    }
+
     /// Obtain latest block from the on-machine node database
-    /// 
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling get on it.
-    /// 
+    ///
     /// # Returns
-    /// An Option<Block> which is the latest block in the db, or None if it does not exist.
-    /// 
+    /// An Result<Block, Status> which is the latest block in the db, or NotFound if it does not exist.
+    ///
     pub fn get_latest_block(&mut self) -> Result<Block, Status> {
+        Ok(self.get_latest_stored_block()?.block)
+    }
+
+    /// Returns every tip (block without children) in the db with the cumulative work of the chain it ends.
+    /// The latest block is the tip with the most work, the others are the ends of side branches.
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by iterating over it.
+    ///
+    /// # Returns
+    /// An Result<Vec<(String, u128)>, Status> which is the hashes of the tips along with their cumulative work.
+    ///
+    pub fn get_tips(&mut self) -> Result<Vec<(String, u128)>, Status> {
+        let hashes: Vec<String> = self.get_keys_with_prefix(TIP_PREFIX)?
+            .iter()
+            .map(|key| String::from_utf8_lossy(&key[TIP_PREFIX.len()..]).into_owned())
+            .collect();
+
+        let mut tips: Vec<(String, u128)> = Vec::new();
+        for hash in hashes {
+            let work: u128 = self.get_stored_block(&hash)?.cumulative_work;
+            tips.push((hash, work));
+        }
+
+        Ok(tips)
+    }
+
+    /// Checks whether a block is part of the main chain
+    ///
+    /// # Arguments
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    pub fn is_main_chain(&mut self, hash: &str) -> bool {
+        match self.get_block_by_hash(hash) {
+            Ok(block) => self.db.get(&BlocksDB::get_db_height_key(block.get_height())).as_deref() == Some(hash.as_bytes()),
+            Err(_) => false
+        }
+    }
+
+    /// Returns hashes of the main chain that a peer can use to find where our chains split.
+    /// The ten latest blocks are listed, then the gap between hashes doubles down to the genesis block.
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    /// # Returns
+    /// An Result<Vec<String>, Status> which is the hashes from the latest block to the genesis block.
+    ///
+    pub fn get_locator(&mut self) -> Result<Vec<String>, Status> {
+        let mut locator: Vec<String> = Vec::new();
+        let mut height: u64 = self.get_latest_block()?.get_height();
+        let mut step: u64 = 1;
+
+        loop {
+            locator.push(self.get_block(height)?.get_hash());
+
+            if height == 0 {
+                break;
+            }
+
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+
+            height = height.saturating_sub(step);
+        }
+
+        Ok(locator)
+    }
+
+    /// Returns the headers of the main chain that come after the first locator hash found on our main chain.
+    /// Starts after genesis if none of the hashes are on our main chain.
+    ///
+    /// # Arguments
+    /// * `locator` - A &[String] which specifies hashes from a peer's chain, latest first
+    /// * `max` - A usize which specifies the maximum number of headers to return
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    /// # Returns
+    /// An Result<Vec<BlockHeader>, Status> which is the headers in increasing height.
+    ///
+    pub fn get_headers_after(&mut self, locator: &[String], max: usize) -> Result<Vec<BlockHeader>, Status> {
+        let mut start: u64 = 0;
 
+        for hash in locator {
+            if self.is_main_chain(hash) {
+                start = self.get_block_by_hash(hash)?.get_height();
+                break;
+            }
+        }
+
+        let latest_height: u64 = self.get_latest_block()?.get_height();
+        let mut headers: Vec<BlockHeader> = Vec::new();
+        let mut height: u64 = start + 1;
+
+        while height <= latest_height && headers.len() < max {
+            headers.push(self.get_block(height)?.get_header());
+            height += 1;
+        }
+
+        Ok(headers)
+    }
+
+    /// Reads the latest block along with its cumulative work
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    fn get_latest_stored_block(&mut self) -> Result<StoredBlock, Status> {
         match self.db.get(LATEST_BLOCK_KEY) {
-            Some(bytes) => {
+            Some(hash) => self.get_stored_block(&String::from_utf8_lossy(&hash)),
+            None => {
+                Err(Status::new(rusty_leveldb::StatusCode::NotFound, "Block not found"))
+            }
+        }
+    }
 
+    /// Reads a block along with its cumulative work
+    ///
+    /// # Arguments
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    fn get_stored_block(&mut self, hash: &str) -> Result<StoredBlock, Status> {
+        match self.db.get(&BlocksDB::get_db_block_key(hash)) {
+            Some(bytes) => {
                 // attempt to desiralize the block
-                let block: Block = bincode::deserialize(&bytes).map_err(|e| 
+                let stored: StoredBlock = bincode::deserialize(&bytes).map_err(|e|
                     Status::new(rusty_leveldb::StatusCode::Corruption, &format!("{e}"))
                 )?;
 
-                Ok(block)
+                Ok(stored)
             },
-            None => { 
-                Err(Status::new(rusty_leveldb::StatusCode::NotFound, &format!("Block not found"))) 
+            None => {
+                Err(Status::new(rusty_leveldb::StatusCode::NotFound, "Block not found"))
             }
         }
     }
 
-    /// Updates the latest block in the db to the given block.
-    /// 
+    /// Checks whether a block with the given hash is stored in the db
+    ///
     /// # Arguments
-    /// * `block` - A &Block which specifies a reference to the block to update the latest block to
-    /// 
-    /// # Modifications
-    /// This method changes the latest block in the db by calling put on the db object.
-    /// 
-    /// # Returns
-    /// An Result<(), Status> which is Ok(()) if the block was successfully updated, or an error if it was not.
-    /// 
-    fn update_latest_block(&mut self, block: &Block) -> Result<(), Status> {
-        self.db.put(LATEST_BLOCK_KEY, &bincode::serialize(block).unwrap())?;
-        self.db.flush()?;
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    pub fn contains_block(&mut self, hash: &str) -> bool {
+        self.db.get(&BlocksDB::get_db_block_key(hash)).is_some()
+    }
 
-        Ok(())
+    /// Checks whether a block was marked as invalid while trying to move the main chain onto it
+    ///
+    /// # Arguments
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    fn is_invalid(&mut self, hash: &str) -> bool {
+        self.db.get(&BlocksDB::get_db_invalid_key(hash)).is_some()
     }
 
-    /// Puts a block into the db if it doesn't exist already.
-    /// 
+
+    /// Adds a block into the db if it doesn't already exist.
+    /// The block can build on any stored block, not only the latest one. If it makes a chain with more
+    /// cumulative work than the main chain, the chain is reorganized onto it.
+    ///
+    /// The block goes through the whole validation pipeline (see validation::validate_block) against its parent.
+    /// Its transactions are checked against the chainstate when it joins the main chain,
+    /// a block on a side branch is only checked once its branch becomes the heaviest.
+    ///
+    /// Everything is written in a single batch so that the db is never left half updated.
+    /// If a block of the new main chain is invalid, the batch is dropped and only the invalid markers are written.
+    ///
     /// # Arguments
     /// * `block` - A &Block which specifies a reference to the block to put into the db
-    /// 
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling put on it.
-    /// 
+    ///
     /// # Returns
-    /// An Result<bool, Status> which is Ok(true) if the block was successfully put, or Ok(false) if it already existed.
-    /// 
-    fn put_block(&mut self, block: &Block) -> Result<(), Status> {
-        match self.get_block(block.get_height()) {
-            Ok(_) => {
-                Err(Status::new(rusty_leveldb::StatusCode::AlreadyExists, &format!("Block already exists in db")))
-            },
-            Err(e) => {
-                if e.code == rusty_leveldb::StatusCode::NotFound {
-                    // serialize block
-                    let binary: Vec<u8> = bincode::serialize(block).unwrap(); // blocks are always serializable
-                    self.db.put(&block.get_height().to_le_bytes(), &binary)?;
-                    self.db.flush()?;
-                    
-                    // successful put
-                    return Ok(());
-                }
+    /// An Result<ChainUpdate, BlockError> which is how the main chain changed if the block was added, or the reason it was rejected.
+    ///
+    pub fn add_block(&mut self, block: &Block) -> Result<ChainUpdate, BlockError> {
+        if block.get_height() == 0 {
+            return Err(BlockError::Genesis);
+        }
+
+        if self.contains_block(&block.get_hash()) {
+            return Err(BlockError::AlreadyExists);
+        }
+
+        if self.is_invalid(&block.get_hash()) {
+            return Err(BlockError::KnownInvalid);
+        }
+
+        let prev_hash: String = block.get_prev_hash();
+        if self.is_invalid(&prev_hash) {
+            return Err(BlockError::InvalidAncestor { hash: prev_hash });
+        }
+
+        let parent: StoredBlock = self.get_stored_block(&prev_hash).map_err(|e| match e.code {
+            rusty_leveldb::StatusCode::NotFound => BlockError::UnknownParent { prev_hash: prev_hash.clone() },
+            _ => BlockError::Db(e)
+        })?;
+
+        validate_block(&parent.block, block)?;
+
+        let stored: StoredBlock = StoredBlock {
+            cumulative_work: parent.cumulative_work + block.get_work(),
+            block: block.clone()
+        };
+
+        // store block and update tips, the parent is no longer a tip now that it has a child
+        let mut batch: WriteBatch = WriteBatch::new();
+        batch.put(&BlocksDB::get_db_block_key(&block.get_hash()), &bincode::serialize(&stored).unwrap());
+        batch.delete(&BlocksDB::get_db_tip_key(&prev_hash));
+        batch.put(&BlocksDB::get_db_tip_key(&block.get_hash()), &[]);
+
+        let latest: StoredBlock = self.get_latest_stored_block()?;
+
+        // heavier chain, move main chain onto it
+        let update: ChainUpdate = if stored.cumulative_work > latest.cumulative_work {
+            match self.reorganize(&latest.block, block, &mut batch) {
+                Ok(update) => update,
+                Err(ReorgError::Invalid { hash, error }) => {
+                    // the block isn't stored, but it's on the invalid branch too
+                    let mut invalid: WriteBatch = WriteBatch::new();
+                    invalid.put(&BlocksDB::get_db_invalid_key(&block.get_hash()), &[]);
+                    self.mark_invalid(&hash, &mut invalid)?;
+                    self.db.write(invalid, true)?;
+
+                    return Err(error);
+                },
+                Err(ReorgError::Db(status)) => return Err(BlockError::Db(status))
+            }
+        } else {
+            ChainUpdate::default()
+        };
+
+        self.db.write(batch, true)?;
+        Ok(update)
+    }
+
+    /// Moves the main chain from the current latest block onto a new tip.
+    /// The old branch's transactions are rolled back with their undo data down to the fork point,
+    /// then the new branch's transactions are verified and applied on top.
+    /// In the simple case where the new tip builds on the latest block, the old branch is empty.
+    ///
+    /// Nothing is written to the db by this method, all changes are put in the given batch.
+    /// If a block on the new branch is invalid, the batch should be dropped and the returned block marked as invalid.
+    ///
+    /// # Arguments
+    /// * `latest` - A &Block which specifies a reference to the current latest block
+    /// * `new_tip` - A &Block which specifies a reference to the tip to reorganize onto
+    /// * `batch` - A &mut WriteBatch in which the changes are put
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    /// # Returns
+    /// An Result<ChainUpdate, ReorgError> which is the blocks of the old branch and of the new branch,
+    /// or the block to mark as invalid along with the reason.
+    ///
+    fn reorganize(&mut self, latest: &Block, new_tip: &Block, batch: &mut WriteBatch) -> Result<ChainUpdate, ReorgError> {
+        // walk back both branches until the fork point
+        let mut old_branch: Vec<Block> = Vec::new();
+        let mut new_branch: Vec<Block> = Vec::new();
+        let mut old: Block = latest.clone();
+        let mut new: Block = new_tip.clone();
+
+        while old.get_height() > new.get_height() {
+            let parent: Block = self.get_block_by_hash(&old.get_prev_hash())?;
+            old_branch.push(old);
+            old = parent;
+        }
+
+        while new.get_height() > old.get_height() {
+            let parent: Block = self.get_block_by_hash(&new.get_prev_hash())?;
+            self.check_not_invalid(&new)?;
+            new_branch.push(new);
+            new = parent;
+        }
+
+        while old.get_hash() != new.get_hash() {
+            let old_parent: Block = self.get_block_by_hash(&old.get_prev_hash())?;
+            let new_parent: Block = self.get_block_by_hash(&new.get_prev_hash())?;
+            self.check_not_invalid(&new)?;
+            old_branch.push(old);
+            new_branch.push(new);
+            old = old_parent;
+            new = new_parent;
+        }
+
+        // apply new branch from fork point upward
+        new_branch.reverse();
+
+        let mut overlay: Overlay = HashMap::new();
 
-                Err(e)
+        // roll back old branch, latest block first
+        for block in &old_branch {
+            let undo: UndoData = self.get_undo_data(&block.get_hash())?;
+
+            for (addr, account) in undo {
+                overlay.insert(addr, account);
+            }
+
+            batch.delete(&BlocksDB::get_db_undo_key(&block.get_hash()));
+        }
+
+        // heights above the new tip are not part of the main chain anymore
+        for height in (new_tip.get_height() + 1)..=latest.get_height() {
+            batch.delete(&BlocksDB::get_db_height_key(height));
+        }
+
+        for block in &new_branch {
+            let accounts: HashMap<Address, Account> = self.verify_transactions(block, &overlay)
+                .map_err(|error| ReorgError::Invalid { hash: block.get_hash(), error })?;
+
+            self.apply_accounts(block, accounts, &mut overlay, batch);
+        }
+
+        for (addr, account) in overlay.iter() {
+            match account {
+                Some(value) => batch.put(&BlocksDB::get_db_user_key(addr), &bincode::serialize(value).unwrap()),
+                None => batch.delete(&BlocksDB::get_db_user_key(addr))
             }
         }
+
+        batch.put(LATEST_BLOCK_KEY, new_tip.get_hash().as_bytes());
+
+        Ok(ChainUpdate { disconnected: old_branch, connected: new_branch })
     }
 
+    /// Checks that a block of the new branch wasn't marked as invalid, before any work is done on the branch
+    ///
+    /// # Arguments
+    /// * `block` - A &Block which specifies a reference to the block of the new branch to check
+    ///
+    fn check_not_invalid(&mut self, block: &Block) -> Result<(), ReorgError> {
+        if self.is_invalid(&block.get_hash()) {
+            return Err(ReorgError::Invalid {
+                hash: block.get_hash(),
+                error: BlockError::InvalidAncestor { hash: block.get_hash() }
+            });
+        }
 
-    /// Adds a block into the db if it doesn't already exist.
-    /// Method should only be used to add a new highest block. It doesn't allow blocks other than the next one over to be added
-    /// 
+        Ok(())
+    }
+
+    /// Marks a block and every stored block that descends from it as invalid, so that nothing is built on them again.
+    /// Their branches are no longer tips, and the parent of the block is a tip again if no other branch goes through it.
+    ///
     /// # Arguments
-    /// * `block` - A &Block which specifies a reference to the block to put into the db
-    /// 
+    /// * `hash` - A &str that specifies the hash of the block
+    /// * `batch` - A &mut WriteBatch in which the changes are put
+    ///
     /// # Modifications
-    /// This method changes the internal state of the DB object by calling put on it.
-    /// 
-    pub fn add_block(&mut self, block: &Block) -> Result<(), Status> {
-        let latest_block: Block = self.get_latest_block()?;
-        let latest_block_height: u64 = latest_block.get_height();
-        let added_block_height: u64 = block.get_height();
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    fn mark_invalid(&mut self, hash: &str, batch: &mut WriteBatch) -> Result<(), Status> {
+        batch.put(&BlocksDB::get_db_invalid_key(hash), &[]);
 
-        // check if genesis
-        if added_block_height == 0 {
-            return Err(Status::new(rusty_leveldb::StatusCode::NotSupported, &format!("Cannot add another genesis block")));
+        // a block that was never stored has no descendants and isn't a tip
+        if !self.contains_block(hash) {
+            return Ok(());
         }
 
-        // if the latest block is smaller than added block
-        if latest_block_height == added_block_height - 1 {
+        let block: Block = self.get_block_by_hash(hash)?;
+        let mut parent_has_child: bool = false;
+
+        for (tip, _) in self.get_tips()? {
+            // walk back down to the height of the block
+            let mut descendants: Vec<String> = Vec::new();
+            let mut current: Block = self.get_block_by_hash(&tip)?;
 
-            // update db with new latest block info
-            self.update_latest_block(block)?;
-            self.update_chainstate(block.get_transactions())?;
+            while current.get_height() > block.get_height() {
+                descendants.push(current.get_hash());
+                current = self.get_block_by_hash(&current.get_prev_hash())?;
+            }
+
+            if current.get_hash() == hash {
+                for descendant in descendants {
+                    batch.put(&BlocksDB::get_db_invalid_key(&descendant), &[]);
+                }
+
+                batch.delete(&BlocksDB::get_db_tip_key(&tip));
+            } else if current.get_height() == block.get_height() && current.get_prev_hash() == block.get_prev_hash() {
+                parent_has_child = true;
+            }
+        }
 
-        // if latest block is much smaller than added block
-        } else if latest_block_height < added_block_height - 1 {
-            return Err(Status::new(rusty_leveldb::StatusCode::NotSupported, &format!("Block height is greater next latest block.")));
-        } else if latest_block_height >= added_block_height {
-            return Err(Status::new(rusty_leveldb::StatusCode::NotSupported, &format!("Block height is much smaller than latest block's")));
+        if !parent_has_child {
+            batch.put(&BlocksDB::get_db_tip_key(&block.get_prev_hash()), &[]);
         }
-        
-        // put block after all checks otherwise there could be some issues
-        self.put_block(block)?;
+
         Ok(())
     }
 
-    /// Reads and returns the balance of a given adress.
-    /// 
+    /// Puts a block's verified accounts in the overlay and records the block in the main chain.
+    /// The accounts as they were before the block are saved as undo data.
+    ///
+    /// # Arguments
+    /// * `block` - A &Block which specifies a reference to the block that the accounts come from
+    /// * `accounts` - A HashMap<Address, Account> which specifies the new accounts given by verify_transactions
+    /// * `overlay` - A &mut Overlay which specifies the pending chainstate to update
+    /// * `batch` - A &mut WriteBatch in which the undo data and height index are put
+    ///
+    fn apply_accounts(&mut self, block: &Block, accounts: HashMap<Address, Account>, overlay: &mut Overlay, batch: &mut WriteBatch) {
+        let mut undo: UndoData = Vec::new();
+
+        for (addr, account) in accounts {
+            undo.push((addr, self.get_overlay_account(&addr, overlay)));
+            overlay.insert(addr, Some(account));
+        }
+
+        batch.put(&BlocksDB::get_db_undo_key(&block.get_hash()), &bincode::serialize(&undo).unwrap());
+        batch.put(&BlocksDB::get_db_height_key(block.get_height()), block.get_hash().as_bytes());
+    }
+
+    /// Reads the undo data of a block on the main chain
+    ///
+    /// # Arguments
+    /// * `hash` - A &str that specifies the hash of the block
+    ///
+    /// # Modifications
+    /// This method changes the internal state of the DB object by calling get on it.
+    ///
+    fn get_undo_data(&mut self, hash: &str) -> Result<UndoData, Status> {
+        match self.db.get(&BlocksDB::get_db_undo_key(hash)) {
+            Some(bytes) => {
+                bincode::deserialize(&bytes).map_err(|e|
+                    Status::new(rusty_leveldb::StatusCode::Corruption, &format!("{e}"))
+                )
+            },
+            None => {
+                Err(Status::new(rusty_leveldb::StatusCode::NotFound, "Undo data not found"))
+            }
+        }
+    }
+
+    /// Reads and returns the account of a given address.
+    ///
     /// # Arguments
-    /// * `public_key` - A &Point which specifies a reference to the public key to lookup
-    /// 
+    /// * `address` - A &Address which specifies a reference to the address to lookup
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling get on it.
-    /// 
+    ///
     /// # Returns
-    /// An Option<f32> which is the balance of the address if it exists in the db, or None if it does not.
-    /// 
-    pub fn get_balance(&mut self, public_key: &Point) -> Result<f32, Status> {
-
-        // serialize the public_key to get the database key. Unwrap because Point never fails to serialize
-        match self.db.get(&BlocksDB::get_db_user_key(public_key)) {
+    /// An Result<Account, Status> which is the account of the address if it exists in the db, or NotFound if it does not.
+    ///
+    pub fn get_account(&mut self, address: &Address) -> Result<Account, Status> {
+        match self.db.get(&BlocksDB::get_db_user_key(address)) {
             Some(bytes) => {
-
-                // wrap bytes buffer with a cursor for easy little-endian conversion to f32
-                let mut reader: Cursor<Vec<u8>> = Cursor::new(bytes);
-
-                Ok(reader.read_f32::<LittleEndian>()?) // Error if data is corrupted
+                bincode::deserialize(&bytes).map_err(|e|
+                    Status::new(rusty_leveldb::StatusCode::Corruption, &format!("{e}"))
+                )
             },
             None => {
                 // address was not found
-                Err(Status::new(rusty_leveldb::StatusCode::NotFound, &format!("Public key was not found.")))
-            } 
+                Err(Status::new(rusty_leveldb::StatusCode::NotFound, "Address was not found."))
+            }
         }
     }
 
-    /// Method used to update balance for an existing user or create a new user with a specified balance.
-    /// 
+    /// Reads the account of a given address, looking at pending changes before the db
+    ///
     /// # Arguments
-    /// * `public_key` - A &Point which specifies a reference to the public key to update the balance of
-    /// * `value` - A f32 which specifies the value to update the balance to
-    /// 
-    /// # Modifications
-    /// This method changes the internal state of the DB object by calling put on it.
-    /// 
+    /// * `address` - A &Address which specifies a reference to the address to lookup
+    /// * `overlay` - A &Overlay which specifies the pending chainstate changes
+    ///
     /// # Returns
-    /// An Result<(), Status> which is Ok(()) if the balance was successfully updated, or an error if it was not.
-    /// 
-    fn update_balance(&mut self, public_key: &Point, value: f32) -> Result<(), Status> {
-        // account balances are stored in little-endian
-        self.db.put(&BlocksDB::get_db_user_key(public_key), &value.to_le_bytes())?;
-        self.db.flush()?;
-        Ok(())
+    /// An Option<Account> which is the account of the address, or None if the account doesn't exist.
+    ///
+    fn get_overlay_account(&mut self, address: &Address, overlay: &Overlay) -> Option<Account> {
+        match overlay.get(address) {
+            Some(account) => *account,
+            None => self.get_account(address).ok()
+        }
     }
 
 
-    /// Method to add prefix to the user public keys to get the key in the db
-    /// 
+    /// Method to add prefix to the addresses to get the key in the db
+    ///
     /// # Arguments
-    /// * `public_key` - A &Point which specifies a reference to the public key to get the db key for
-    /// 
+    /// * `address` - A &Address which specifies a reference to the address to get the db key for
+    ///
     /// # Returns
-    /// A Vec<u8> which is the key in the db for the public key
-    /// 
-    fn get_db_user_key(public_key: &Point) -> Vec<u8> {
-        // add prefix
-        let mut key: Vec<u8> = Vec::new();
-        key.extend_from_slice(PUBLIC_KEY_PREFIX);
-        key.extend_from_slice(&bincode::serialize(public_key).unwrap());
+    /// A Vec<u8> which is the key in the db for the address, the prefix followed by the 20 bytes of the address
+    ///
+    fn get_db_user_key(address: &Address) -> Vec<u8> {
+        [ADDRESS_PREFIX.as_slice(), &address.get_hash()].concat()
+    }
+
+    /// Method to get the key of a block in the db from its hash
+    fn get_db_block_key(hash: &str) -> Vec<u8> {
+        [BLOCK_PREFIX.as_slice(), hash.as_bytes()].concat()
+    }
+
+    /// Method to get the key of the main chain's block hash at a height, height is in little-endian
+    fn get_db_height_key(height: u64) -> Vec<u8> {
+        [HEIGHT_PREFIX.as_slice(), &height.to_le_bytes()].concat()
+    }
 
-        key
+    /// Method to get the key marking a block as a tip from its hash
+    fn get_db_tip_key(hash: &str) -> Vec<u8> {
+        [TIP_PREFIX.as_slice(), hash.as_bytes()].concat()
     }
 
-    
+    /// Method to get the key of a block's undo data from its hash
+    fn get_db_undo_key(hash: &str) -> Vec<u8> {
+        [UNDO_PREFIX.as_slice(), hash.as_bytes()].concat()
+    }
+
+    /// Method to get the key marking a block as invalid from its hash
+    fn get_db_invalid_key(hash: &str) -> Vec<u8> {
+        [INVALID_PREFIX.as_slice(), hash.as_bytes()].concat()
+    }
+
+
     /// verifies that the transactions are valid and can be added to the chainstate.
-    /// makes a hashmap of all the new balances so that the new balances can be updated easily
-    /// 
+    /// makes a hashmap of all the new accounts so that the chainstate can be updated easily.
+    /// transactions are applied in order, a sender can't spend coins it only receives later in the block
+    /// and its transactions must use its nonces one after the other.
+    /// only the first transaction can be a reward and it can't be more than the block's subsidy plus the fees
+    ///
     /// # Arguments
-    /// * `transactions` - A Vec<Transaction> which specifies the transactions to verify
-    /// 
+    /// * `block` - A &Block which specifies the block whose transactions are verified
+    /// * `overlay` - A &Overlay which specifies pending chainstate changes to take into account
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling get on it.
-    /// 
+    ///
     /// # Returns
-    /// An Result<HashMap<&Point, f32>, Status> which is returns a map of the new balances of the verified transactions
-    /// 
-    fn verify_transactions(&mut self, transactions: &Vec<Transaction>) -> Result<HashMap<Point, f32>, Status> {
-        // hashmap to remember good balances
-        let mut balances: HashMap<Point, f32> = HashMap::new();
+    /// An Result<HashMap<Address, Account>, BlockError> which is returns a map of the new accounts of the verified transactions
+    ///
+    fn verify_transactions(&mut self, block: &Block, overlay: &Overlay) -> Result<HashMap<Address, Account>, BlockError> {
+        // hashmap to remember good accounts
+        let mut accounts: HashMap<Address, Account> = HashMap::new();
+
+        let mut fees: Amount = Amount::ZERO;
+        let mut rewards: Amount = Amount::ZERO;
 
-        for transaction in transactions {
+        for (i, transaction) in block.get_transactions().iter().enumerate() {
             let sender: Point = transaction.get_sender();
-            let recipient: Point = transaction.get_recipient();
+            let recipient: Address = transaction.get_recipient();
 
             // Point::identity is miner reward
+            if sender == Point::identity() && i != 0 {
+                return Err(BlockError::InvalidCoinbase);
+            }
+
             if sender != Point::identity() {
+                if !sender.is_on_curve() {
+                    return Err(BlockError::InvalidPublicKey(sender));
+                }
+
+                // get original account
+                // check hashmap first for accounts
+                let sender_address: Address = Address::from(&sender);
+                let mut sender_account: Account = match accounts.get(&sender_address) {
+                    Some(account) => *account,
+                    None => self.get_overlay_account(&sender_address, overlay).unwrap_or_default()
+                };
 
-                // get original balances
-                // check hashmap first for balances
-                let sender_balance: f32 = *balances.get(&sender).unwrap_or(
-                    &self.get_balance(&sender).unwrap_or(0.0)
-                );
+                // a transaction that was already applied or that skips a nonce is refused
+                if transaction.get_nonce() != sender_account.nonce {
+                    return Err(BlockError::InvalidNonce {
+                        address: sender_address,
+                        expected: sender_account.nonce,
+                        found: transaction.get_nonce()
+                    });
+                }
 
-                // calculate new balances
-                let new_sender_balance: f32 = sender_balance - transaction.get_amount();
+                // calculate new account, the sender pays the amount and the fee
+                sender_account.balance = transaction.get_cost()
+                    .and_then(|cost| sender_account.balance.checked_sub(cost))
+                    .ok_or(BlockError::InsufficientBalance(sender_address))?;
+                sender_account.nonce += 1;
 
-                balances.insert(sender, new_sender_balance);
+                accounts.insert(sender_address, sender_account);
+                fees = fees.saturating_add(transaction.get_fee());
+            } else {
+                rewards = rewards.saturating_add(transaction.get_amount());
             }
 
             // do same for recipient
-            let recipient_balance: f32 = *balances.get(&recipient).unwrap_or(
-                &self.get_balance(&recipient).unwrap_or(0.0)
-            );
-            
-            let new_recipient_balance: f32 = recipient_balance + transaction.get_amount();
-            
-            balances.insert(recipient, new_recipient_balance);
-        }
-
-        for (addr, balance) in balances.iter() {
-            if *balance < 0.0 {
-                return Err(Status::new(rusty_leveldb::StatusCode::InvalidData, &format!("public key: {} has negative balance after all transactions.", addr)));
-            }
+            let mut recipient_account: Account = match accounts.get(&recipient) {
+                Some(account) => *account,
+                None => self.get_overlay_account(&recipient, overlay).unwrap_or_default()
+            };
+
+            recipient_account.balance = recipient_account.balance.checked_add(transaction.get_amount())
+                .ok_or(BlockError::BalanceOverflow(recipient))?;
+
+            accounts.insert(recipient, recipient_account);
+        }
+
+        let max_reward: Amount = get_block_subsidy(block.get_height()).saturating_add(fees);
+        if rewards > max_reward {
+            return Err(BlockError::InvalidReward { max: max_reward, found: rewards });
         }
 
-        Ok(balances)
+        Ok(accounts)
     }
 
-    
-    /// Updates the chainstate with the transactions of a given block.
-    /// Multiple checks should be made before using this method. Method is private so as to not invalidate the data in the db
-    /// If error on update balance. Chainstate should be rebuilt from beginning
-    /// 
+    /// Returns every key in the db that starts with the given prefix
+    ///
     /// # Arguments
-    /// * `transactions` - A Vec<Transaction> which specifies the transactions to update the chainstate with
-    /// 
+    /// * `prefix` - A &[u8] which specifies the prefix of the keys
+    ///
     /// # Modifications
-    /// This method changes often multiple addresses' balances using put on the db object.
-    /// 
-    /// # Returns
-    /// An Result<(), Status> which is Ok(()) if the chainstate was successfully updated, or an error if it was not.
+    /// This method changes the internal state of the DB object by iterating over it.
     ///
-    fn update_chainstate(&mut self, transactions: Vec<Transaction>) -> Result<(), Status> {
-        // verify that the transactions are valid according to the chainstate
-        let verified_balances: HashMap<Point, f32> = self.verify_transactions(&transactions)?; 
+    fn get_keys_with_prefix(&mut self, prefix: &[u8]) -> Result<Vec<Vec<u8>>, Status> {
+        // make new db iterator
+        let mut iter: DBIterator = self.db.new_iter()?;
 
-        // update all balances
-        for (addr, balance) in verified_balances.iter() {
-            self.update_balance(addr, *balance)?;
+        // init values for key and val
+        let mut key: Vec<u8> = vec![];
+        let mut val: Vec<u8> = vec![];
+        let mut keys: Vec<Vec<u8>> = vec![];
+
+        iter.seek(prefix);
+        while iter.valid() {
+            // get current key and val
+            iter.current(&mut key, &mut val);
+
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            keys.push(key.clone());
+            iter.advance();
         }
 
-        Ok(())
+        Ok(keys)
     }
 
-    /// Method used to wipe the chainstate of every user and transaction.
-    /// 
+    /// Method used to wipe the chainstate of every user and the undo data of every block.
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling delete on it.
-    /// 
+    ///
     /// # Returns
     /// An Result<(), Status> which is Ok(()) if the chainstate was successfully cleared, or an error if it was not.
-    /// 
+    ///
     fn clear_chainstate(&mut self) -> Result<(), Status> {
-        // make new db iterator
-        let mut iter: DBIterator = self.db.new_iter()?;
-
-        // init values for key and val
-        let mut key: Vec<u8> = vec![];
-        let mut val: Vec<u8> = vec![];
+        let mut batch: WriteBatch = WriteBatch::new();
 
-        while iter.advance() {
-            // get current key and val
-            iter.current(&mut key, &mut val);
-            
-            // verify prefix
-            if key.len() >= 7 && key[0..7] == *PUBLIC_KEY_PREFIX {
+        for prefix in [ADDRESS_PREFIX.as_slice(), UNDO_PREFIX.as_slice()] {
+            for key in self.get_keys_with_prefix(prefix)? {
                 // wipe key
-                self.db.delete(&key)?;
+                batch.delete(&key);
             }
         }
 
-        Ok(())
+        self.db.write(batch, true)
     }
 
-    /// Method used to rebuild chainstate from all the blocks in the db.
+    /// Method used to rebuild chainstate from all the blocks of the main chain.
     /// Used in case when chainstate gets updated, there are some internal errors and the data gets corrupted
-    /// 
+    ///
     /// # Modifications
     /// This method changes the internal state of the DB object by calling get and put on it.
-    /// 
+    ///
     /// # Returns
-    /// An Result<(), Status> which is Ok(()) if the chainstate was successfully rebuilt, or an error if it was not.
-    /// 
-    pub fn rebuild_chainstate(&mut self) -> Result<(), Status> {
+    /// An Result<(), BlockError> which is Ok(()) if the chainstate was successfully rebuilt, or an error if it was not.
+    ///
+    pub fn rebuild_chainstate(&mut self) -> Result<(), BlockError> {
 
         // clear chainstate
         self.clear_chainstate()?;
 
-        let mut curr_block: Block; 
+        let mut curr_block: Block;
         let mut curr_height: u64 = 0; // start at genesis block
 
         let latest_block: Block = self.get_latest_block()?;
         let latest_block_height: u64 = latest_block.get_height();
-    
+
+        // whole chainstate is built in memory and written at once
+        let mut overlay: Overlay = HashMap::new();
+        let mut batch: WriteBatch = WriteBatch::new();
 
         while curr_height <= latest_block_height {
             // get block
             curr_block = self.get_block(curr_height)?;
 
             // update chainstate
-            let transactions: Vec<Transaction> = curr_block.get_transactions();
-            self.update_chainstate(transactions)?;
+            let accounts: HashMap<Address, Account> = self.verify_transactions(&curr_block, &overlay)?;
+            self.apply_accounts(&curr_block, accounts, &mut overlay, &mut batch);
 
-        
             curr_height += 1;
         }
 
+        for (addr, account) in overlay.iter() {
+            if let Some(value) = account {
+                batch.put(&BlocksDB::get_db_user_key(addr), &bincode::serialize(value).unwrap());
+            }
+        }
+
+        self.db.write(batch, true)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecdsa::secp256k1::PrivateKey;
+    use rblock::Transaction;
+    use crate::test_utils::{mine_block, miner, miner_key, temp_db};
+
+    /// returns the accounts of the given addresses, None for the ones that don't exist
+    fn accounts(db: &mut BlocksDB, addresses: &[Address]) -> Vec<Option<Account>> {
+        addresses.iter().map(|address| db.get_account(address).ok()).collect()
+    }
+
+    fn payment(sender: &PrivateKey, recipient: &Address, nonce: u64) -> Transaction {
+        Transaction::new(sender, recipient, Amount::from_coins(1).unwrap(), Amount::from_snails(1_000), nonce)
+    }
+
+    #[test]
+    fn reorganization() {
+        let (_dir, mut db) = temp_db();
+        let alice: Address = Address::from(&PrivateKey::generate().unwrap().get_public_key());
+        let bob: Address = Address::from(&PrivateKey::generate().unwrap().get_public_key());
+        let addresses: [Address; 3] = [miner(), alice, bob];
+
+        let genesis: Block = db.get_latest_block().unwrap();
+        let a1: Block = mine_block(&genesis, &Vec::new());
+        let a2: Block = mine_block(&a1, &vec![payment(&miner_key(), &alice, 0)]);
+        let a3: Block = mine_block(&a2, &Vec::new());
+
+        for block in [&a1, &a2, &a3] {
+            db.add_block(block).unwrap();
+        }
+
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), a3.get_hash());
+        assert_eq!(db.get_account(&alice).unwrap().balance, Amount::from_coins(1).unwrap());
+        assert_eq!(db.get_account(&miner()).unwrap().nonce, 1);
+
+        // a fork from a1 that pays bob instead, it only becomes the main chain once it has more work
+        let b2: Block = mine_block(&a1, &vec![payment(&miner_key(), &bob, 0)]);
+        let b3: Block = mine_block(&b2, &vec![payment(&miner_key(), &bob, 1)]);
+        let b4: Block = mine_block(&b3, &Vec::new());
+
+        db.add_block(&b2).unwrap();
+        db.add_block(&b3).unwrap();
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), a3.get_hash());
+        assert!(!db.is_main_chain(&b3.get_hash()));
+        assert!(db.get_account(&bob).is_err());
+
+        db.add_block(&b4).unwrap();
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), b4.get_hash());
+        assert!(db.is_main_chain(&b2.get_hash()));
+        assert!(!db.is_main_chain(&a2.get_hash()));
+        assert_eq!(db.get_block(4).unwrap().get_hash(), b4.get_hash());
+
+        let mut tips: Vec<String> = db.get_tips().unwrap().into_iter().map(|(hash, _)| hash).collect();
+        tips.sort();
+        let mut expected: Vec<String> = vec![a3.get_hash(), b4.get_hash()];
+        expected.sort();
+        assert_eq!(tips, expected);
+
+        // alice's payment is rolled back, bob got both of his
+        assert!(db.get_account(&alice).is_err());
+        assert_eq!(db.get_account(&bob).unwrap().balance, Amount::from_coins(2).unwrap());
+        assert_eq!(db.get_account(&miner()).unwrap().nonce, 2);
+
+        // the chainstate is the one of a db that only ever saw the new main chain
+        let reorganized: Vec<Option<Account>> = accounts(&mut db, &addresses);
+
+        let (_fresh_dir, mut fresh) = temp_db();
+        for block in [&a1, &b2, &b3, &b4] {
+            fresh.add_block(block).unwrap();
+        }
+        assert_eq!(accounts(&mut fresh, &addresses), reorganized);
+
+        // and the one rebuilt from the main chain's blocks
+        db.rebuild_chainstate().unwrap();
+        assert_eq!(accounts(&mut db, &addresses), reorganized);
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), b4.get_hash());
+
+        // the old branch can take the lead back
+        let a4: Block = mine_block(&a3, &Vec::new());
+        let a5: Block = mine_block(&a4, &Vec::new());
+        db.add_block(&a4).unwrap();
+        db.add_block(&a5).unwrap();
+
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), a5.get_hash());
+        assert_eq!(db.get_account(&alice).unwrap().balance, Amount::from_coins(1).unwrap());
+        assert!(db.get_account(&bob).is_err());
+        assert_eq!(db.get_account(&miner()).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn invalid_branch() {
+        let (_dir, mut db) = temp_db();
+        let alice: Address = Address::from(&PrivateKey::generate().unwrap().get_public_key());
+
+        let genesis: Block = db.get_latest_block().unwrap();
+        let a1: Block = mine_block(&genesis, &Vec::new());
+        db.add_block(&a1).unwrap();
+
+        // the sender has no coins, the block is refused and the main chain doesn't move
+        let broke: PrivateKey = PrivateKey::generate().unwrap();
+        let a2: Block = mine_block(&a1, &vec![payment(&broke, &alice, 0)]);
+
+        assert!(matches!(db.add_block(&a2), Err(BlockError::InsufficientBalance(_))));
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), a1.get_hash());
+        assert!(!db.contains_block(&a2.get_hash()));
+
+        // the refused tip is remembered, and so is everything built on it
+        assert!(matches!(db.add_block(&a2), Err(BlockError::KnownInvalid)));
+        assert!(matches!(db.add_block(&mine_block(&a2, &Vec::new())), Err(BlockError::InvalidAncestor { .. })));
+
+        // an invalid side branch is stored until it gets the most work
+        let b2: Block = mine_block(&a1, &vec![payment(&broke, &miner(), 0)]);
+        let b3: Block = mine_block(&b2, &Vec::new());
+        let a2: Block = mine_block(&a1, &Vec::new());
+        let a3: Block = mine_block(&a2, &Vec::new());
+        for block in [&a2, &a3, &b2] {
+            db.add_block(block).unwrap();
+        }
+
+        let b4: Block = mine_block(&b3, &Vec::new());
+        db.add_block(&b3).unwrap();
+        assert!(matches!(db.add_block(&b4), Err(BlockError::InsufficientBalance(_))));
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), a3.get_hash());
+
+        // the branch and its tip are dead, only the main chain is left
+        for block in [&b2, &b3, &b4] {
+            assert!(db.is_invalid(&block.get_hash()));
+        }
+        let tips: Vec<String> = db.get_tips().unwrap().into_iter().map(|(hash, _)| hash).collect();
+        assert_eq!(tips, vec![a3.get_hash()]);
+        assert!(matches!(db.add_block(&mine_block(&b3, &vec![payment(&miner_key(), &alice, 0)])),
+            Err(BlockError::InvalidAncestor { .. })));
+    }
+
+    #[test]
+    fn nonces() {
+        let (_dir, mut db) = temp_db();
+        let alice: Address = Address::from(&PrivateKey::generate().unwrap().get_public_key());
+
+        let genesis: Block = db.get_latest_block().unwrap();
+        let funded: Block = mine_block(&genesis, &Vec::new());
+        db.add_block(&funded).unwrap();
+
+        // two transactions of the same sender in one block, in nonce order
+        let first: Transaction = Transaction::new(&miner_key(), &alice, Amount::from_snails(1_000), Amount::ZERO, 0);
+        let second: Transaction = Transaction::new(&miner_key(), &alice, Amount::from_snails(1_000), Amount::ZERO, 1);
+
+        let swapped: Block = mine_block(&funded, &vec![second.clone(), first.clone()]);
+        assert!(matches!(db.add_block(&swapped), Err(BlockError::InvalidNonce { expected: 0, found: 1, .. })));
+
+        let both: Block = mine_block(&funded, &vec![first.clone(), second.clone()]);
+        db.add_block(&both).unwrap();
+        assert_eq!(db.get_account(&miner()).unwrap().nonce, 2);
+        assert_eq!(db.get_account(&alice).unwrap().balance, Amount::from_snails(2_000));
+
+        // a transaction that was already applied can't be replayed
+        let replayed: Block = mine_block(&both, &vec![first.clone()]);
+        assert!(matches!(db.add_block(&replayed), Err(BlockError::InvalidNonce { expected: 2, found: 0, .. })));
+
+        // not even inside the block that applies it
+        let doubled: Block = mine_block(&both, &vec![payment(&miner_key(), &alice, 2), payment(&miner_key(), &alice, 2)]);
+        assert!(matches!(db.add_block(&doubled), Err(BlockError::InvalidNonce { expected: 3, found: 2, .. })));
+
+        // nor can a nonce be skipped
+        let skipped: Block = mine_block(&both, &vec![payment(&miner_key(), &alice, 3)]);
+        assert!(matches!(db.add_block(&skipped), Err(BlockError::InvalidNonce { expected: 2, found: 3, .. })));
+
+        assert_eq!(db.get_latest_block().unwrap().get_hash(), both.get_hash());
+        assert_eq!(db.get_account(&miner()).unwrap().nonce, 2);
+    }
+}
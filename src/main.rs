@@ -1,49 +1,129 @@
 mod db;
+mod mempool;
+mod miner;
+mod net;
+mod validation;
 
-use ecdsa::secp256k1::{get_curve_precomputed_points, Secp256k1, W};
-use num_bigint::BigInt;
-use rblock::{Block, Transaction};
+#[cfg(test)]
+mod test_utils;
 
-use crate::db::BlocksDB;
-
-fn bigint(num: &str) -> BigInt {
-    BigInt::parse_bytes(num.as_bytes(), 16).unwrap()
-}
-
-// TODO: figure out to a way to know when its the first time running the app on user computer 
-    // to init genesis block and latest block or to get it from server
-    // TODO: should genesis block have transactions with addresses to kickstart the currency?
-    // TODO: should chainstate be rebuilt whenever you restart your node? to make sure that everything is alright?
-
-fn main() {
-    let mut db: BlocksDB = BlocksDB::start_db().unwrap();
+use std::{net::{SocketAddr, ToSocketAddrs}, path::PathBuf, process, thread};
 
+use rblock::{Address, Block};
 
+use crate::{db::BlocksDB, net::{Node, DEFAULT_PORT}};
 
-    // let p_1 = bigint("78c86580d3b2c9f8392e01f6635356439f706ca200db266ab734504a8bb9553b");
-    // let p_2 = bigint("552d2967fac5c16573049a4b03b015801688496186873f5a60a7e3bfeeb12570");
+// TODO: should genesis block have transactions with addresses to kickstart the currency?
 
-    // let curve_1 = Secp256k1::new();
-    // let point_1 = curve_1.g.multiply(p_1.clone(), W, get_curve_precomputed_points());
+/// Options given on the command line
+struct Args {
+    /// The port to listen on for peers
+    port: u16,
 
-    // let curve_2 = Secp256k1::new();
-    // let point_2 = curve_2.g.multiply(p_2.clone(), W, get_curve_precomputed_points());
+    /// Where to store the db, the home directory is used if it isn't given
+    datadir: Option<PathBuf>,
 
-    // let t_1 = Transaction::new(&point_1, &point_2, 1000., &p_1);
-    // let t_2 = Transaction::new(&point_2, &point_1, 1000., &p_2);    
+    /// Peers to connect to at startup
+    peers: Vec<SocketAddr>,
 
-    // let transactions = &vec![t_1, t_2];
+    /// The address to pay block rewards to, blocks are only mined if it is given
+    mine: Option<Address>,
 
+    /// The number of threads to mine with
+    threads: usize,
 
-    //db.init_db(&point_1, &point_2);
+    /// Whether to rebuild the chainstate from the blocks of the main chain before starting
+    reindex: bool
+}
 
-    // let latest = db.get_latest_block().unwrap();
-    // let mut next = Block::new(&latest, transactions);
-    // next.reward_miner(&point_1);
+/// Parses the command line, usage:
+/// rust_blockchain [--port <port>] [--datadir <path>] [--connect <host:port>]... [--mine <address>] [--threads <n>] [--reindex]
+fn parse_args() -> Result<Args, String> {
+    let threads: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut args: Args = Args { port: DEFAULT_PORT, datadir: None, peers: Vec::new(), mine: None, threads, reindex: false };
+    let mut iter = std::env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        // the only flag without a value
+        if arg == "--reindex" {
+            args.reindex = true;
+            continue;
+        }
+
+        let value: String = iter.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+
+        match arg.as_str() {
+            "--port" => args.port = value.parse().map_err(|_| format!("Invalid port: {}", value))?,
+            "--datadir" => args.datadir = Some(PathBuf::from(value)),
+            "--connect" => {
+                let addr: SocketAddr = value.to_socket_addrs().ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .ok_or_else(|| format!("Invalid peer address: {}", value))?;
+                args.peers.push(addr);
+            },
+            "--mine" => args.mine = Some(value.parse().map_err(|e| format!("Invalid address {}: {}", value, e))?),
+            "--threads" => args.threads = value.parse().ok()
+                .filter(|threads: &usize| *threads > 0)
+                .ok_or_else(|| format!("Invalid number of threads: {}", value))?,
+            _ => return Err(format!("Unknown argument: {}", arg))
+        }
+    }
+
+    Ok(args)
+}
 
-    //db.add_block(&next).unwrap();
-    //db.rebuild_chainstate().unwrap();
+/// Prints the latest block and the number of side branches the db knows about
+///
+/// # Arguments
+/// * `db` - A &mut BlocksDB which specifies the db to read the chain from
+///
+fn print_chain_status(db: &mut BlocksDB) {
+    let latest: Block = match db.get_latest_block() {
+        Ok(block) => block,
+        Err(e) => {
+            eprintln!("Could not read the latest block: {}", e);
+            return;
+        }
+    };
+
+    // every tip but the latest one ends a side branch
+    let side_branches: usize = db.get_tips().map(|tips| tips.len().saturating_sub(1)).unwrap_or(0);
+    println!("Latest block {} at height {}, {} side branches", latest.get_hash(), latest.get_height(), side_branches);
+}
 
-    // println!("{}", db.get_balance(&point_1).unwrap());
-    // println!("{}", db.get_balance(&point_2).unwrap());
+fn main() {
+    let args: Args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("usage: rust_blockchain [--port <port>] [--datadir <path>] [--connect <host:port>]... [--mine <address>] [--threads <n>] [--reindex]");
+        process::exit(1);
+    });
+
+    let mut db: BlocksDB = match &args.datadir {
+        Some(path) => BlocksDB::open(path.clone()),
+        None => BlocksDB::start_db()
+    }.unwrap_or_else(|e| {
+        eprintln!("Could not open db: {}", e);
+        process::exit(1);
+    });
+
+    if args.reindex {
+        println!("Rebuilding the chainstate from the main chain");
+
+        if let Err(e) = db.rebuild_chainstate() {
+            eprintln!("Could not rebuild the chainstate: {}", e);
+            process::exit(1);
+        }
+    }
+
+    print_chain_status(&mut db);
+
+    let mut node: Node = Node::new(db, args.port);
+    if let Some(address) = args.mine {
+        node.start_miner(address, args.threads);
+    }
+
+    if let Err(e) = node.run(args.peers) {
+        eprintln!("Node stopped: {}", e);
+        process::exit(1);
+    }
 }
\ No newline at end of file
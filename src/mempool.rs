@@ -0,0 +1,640 @@
+use core::fmt;
+use std::{cmp::Ordering, collections::{btree_map, BTreeMap, BinaryHeap, HashMap, HashSet}};
+use ecdsa::secp256k1::Point;
+use rblock::{Address, Amount, Transaction};
+use crate::db::{Account, BlocksDB, ChainUpdate};
+
+/// Default maximum number of transactions kept in the mempool
+pub const MAX_MEMPOOL_SIZE: usize = 50000;
+
+/// Reasons for which a transaction can be refused by the mempool
+#[derive(Debug)]
+pub enum MempoolError {
+    /// A transaction with the same hash is already in the mempool
+    Duplicate,
+
+    /// Rewards are only valid inside blocks
+    Reward,
+
+    /// The amount is zero or the amount plus the fee overflows
+    InvalidAmount,
+
+    /// The signature doesn't match the sender
+    InvalidSignature,
+
+    /// The nonce isn't the one after the sender's last transaction
+    InvalidNonce { expected: u64, found: u64 },
+
+    /// The sender can't pay for this transaction on top of its other pending transactions
+    InsufficientBalance { balance: Amount, pending: Amount },
+
+    /// The mempool is full and the transaction has a lower priority than every pending transaction
+    Full
+}
+
+/// implement display for MempoolError for easy printing of rejection reasons
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::Duplicate => write!(f, "transaction is already in the mempool"),
+            MempoolError::Reward => write!(f, "reward transactions can't be relayed"),
+            MempoolError::InvalidAmount => write!(f, "invalid transaction amount"),
+            MempoolError::InvalidSignature => write!(f, "invalid transaction signature"),
+            MempoolError::InvalidNonce { expected, found } =>
+                write!(f, "invalid nonce: expected {}, found {}", expected, found),
+            MempoolError::InsufficientBalance { balance, pending } =>
+                write!(f, "insufficient balance: {} with {} already pending", balance, pending),
+            MempoolError::Full => write!(f, "mempool is full")
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+/// A pending transaction along with the order in which it arrived
+struct MempoolEntry {
+    transaction: Transaction,
+
+    /// Size of the serialized transaction in bytes, used to get its fee rate
+    size: u64,
+
+    sequence: u64
+}
+
+impl MempoolEntry {
+    /// Compares the priority of two entries, Less means that this entry should be mined (and kept) first.
+    /// Higher fee rates (fee per byte) go first, ties go to the transaction that arrived first.
+    fn priority_cmp(&self, other: &MempoolEntry) -> Ordering {
+        // compare fee / size without dividing, u128 can't overflow
+        let rate: u128 = self.transaction.get_fee().to_snails() as u128 * other.size as u128;
+        let other_rate: u128 = other.transaction.get_fee().to_snails() as u128 * self.size as u128;
+
+        other_rate.cmp(&rate)
+            .then(self.sequence.cmp(&other.sequence))
+    }
+}
+
+/// The first pending transaction of a sender that can be put in a block, ordered by priority
+struct SelectCandidate<'a> {
+    entry: &'a MempoolEntry,
+
+    /// The sender's remaining pending transactions, in nonce order
+    next: btree_map::Values<'a, u64, String>
+}
+
+impl PartialEq for SelectCandidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SelectCandidate<'_> {}
+
+impl PartialOrd for SelectCandidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// the highest priority candidate is the greatest so that it's on top of a BinaryHeap
+impl Ord for SelectCandidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.entry.priority_cmp(self.entry)
+    }
+}
+
+/// The pending transactions of a sender
+#[derive(Default)]
+struct SenderPending {
+    /// Total cost (amount plus fee) of the sender's pending transactions
+    total: Amount,
+
+    /// Hashes of the sender's pending transactions by nonce, the nonces always follow each other
+    nonces: BTreeMap<u64, String>
+}
+
+/// Holds the verified transactions that wait to be put in a block.
+///
+/// Every transaction is checked against the chainstate along with the other pending
+/// transactions of the same sender so that a sender can never have more pending than it owns.
+/// The pending transactions of a sender must use the nonces that follow its account's nonce,
+/// so only the last one of a sender can be evicted without making the others unusable.
+pub struct Mempool {
+    transactions: HashMap<String, MempoolEntry>,
+
+    /// Pending transactions of each sender, by address since the address holds the account and its nonce
+    senders: HashMap<Address, SenderPending>,
+
+    max_size: usize,
+
+    /// Incremented for every transaction added, used to order transactions by arrival
+    next_sequence: u64
+}
+
+impl Mempool {
+    /// Creates an empty mempool
+    ///
+    /// # Arguments
+    /// * `max_size` - A usize which specifies the maximum number of transactions kept
+    ///
+    pub fn new(max_size: usize) -> Self {
+        Mempool {
+            transactions: HashMap::new(),
+            senders: HashMap::new(),
+            max_size,
+            next_sequence: 0
+        }
+    }
+
+    /// returns the number of pending transactions
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// returns true if the transaction with the given hash is pending
+    pub fn contains(&self, hash: &str) -> bool {
+        self.transactions.contains_key(hash)
+    }
+
+    /// returns the pending transaction with the given hash
+    pub fn get(&self, hash: &str) -> Option<&Transaction> {
+        self.transactions.get(hash).map(|entry| &entry.transaction)
+    }
+
+    /// Verifies a transaction and adds it to the mempool.
+    /// If the mempool is full, the lowest priority transaction is evicted to make room.
+    ///
+    /// # Arguments
+    /// * `transaction` - A Transaction which specifies the transaction to add
+    /// * `db` - A &mut BlocksDB which specifies the db used to read the sender's balance
+    ///
+    /// # Returns
+    /// A Result<(), MempoolError> which is Ok(()) if the transaction was added, or the reason it was refused.
+    ///
+    pub fn add(&mut self, transaction: Transaction, db: &mut BlocksDB) -> Result<(), MempoolError> {
+        let hash: String = transaction.get_hash();
+
+        if self.transactions.contains_key(&hash) {
+            return Err(MempoolError::Duplicate);
+        }
+
+        if transaction.get_sender() == Point::identity() {
+            return Err(MempoolError::Reward);
+        }
+
+        let cost: Amount = match transaction.get_cost() {
+            Some(cost) if !transaction.get_amount().is_zero() => cost,
+            _ => return Err(MempoolError::InvalidAmount)
+        };
+
+        if !transaction.verify() {
+            return Err(MempoolError::InvalidSignature);
+        }
+
+        let sender: Address = transaction.get_sender_address();
+        let account: Account = db.get_account(&sender).unwrap_or_default();
+        let balance: Amount = account.balance;
+        let pending: Amount = self.senders.get(&sender).map(|pending| pending.total).unwrap_or(Amount::ZERO);
+
+        // the transaction must come right after the sender's last transaction
+        let expected: u64 = match self.senders.get(&sender).and_then(|pending| pending.nonces.keys().next_back()) {
+            Some(last) => last + 1,
+            None => account.nonce
+        };
+
+        if transaction.get_nonce() != expected {
+            return Err(MempoolError::InvalidNonce { expected, found: transaction.get_nonce() });
+        }
+
+        if pending.checked_add(cost).is_none_or(|total| total > balance) {
+            return Err(MempoolError::InsufficientBalance { balance, pending });
+        }
+
+        let nonce: u64 = transaction.get_nonce();
+        let size: u64 = bincode::serialized_size(&transaction).unwrap_or(u64::MAX);
+        let entry: MempoolEntry = MempoolEntry { transaction, size, sequence: self.next_sequence };
+
+        if self.transactions.len() >= self.max_size {
+            let lowest: String = match self.get_lowest_priority(&sender) {
+                Some((lowest_hash, lowest)) if entry.priority_cmp(lowest) == Ordering::Less => lowest_hash.clone(),
+                _ => return Err(MempoolError::Full)
+            };
+
+            self.remove(&lowest);
+        }
+
+        self.next_sequence += 1;
+
+        let pending: &mut SenderPending = self.senders.entry(sender).or_default();
+        // can't overflow since the total was checked against the balance
+        pending.total = pending.total.saturating_add(cost);
+        pending.nonces.insert(nonce, hash.clone());
+
+        self.transactions.insert(hash, entry);
+
+        Ok(())
+    }
+
+    /// Returns the highest priority transactions, to be put in a block.
+    /// A sender's transactions are always returned in nonce order, a transaction is only
+    /// considered once the sender's previous one was selected.
+    ///
+    /// # Arguments
+    /// * `max` - A usize which specifies the maximum number of transactions to return
+    ///
+    /// # Returns
+    /// A Vec<Transaction> which is the transactions from highest to lowest priority
+    ///
+    pub fn select(&self, max: usize) -> Vec<Transaction> {
+        let mut candidates: BinaryHeap<SelectCandidate> = BinaryHeap::new();
+
+        for pending in self.senders.values() {
+            let mut next: btree_map::Values<u64, String> = pending.nonces.values();
+
+            if let Some(hash) = next.next() {
+                candidates.push(SelectCandidate { entry: &self.transactions[hash], next });
+            }
+        }
+
+        let mut selected: Vec<Transaction> = Vec::new();
+
+        while selected.len() < max {
+            let mut candidate: SelectCandidate = match candidates.pop() {
+                Some(candidate) => candidate,
+                None => break
+            };
+
+            selected.push(candidate.entry.transaction.clone());
+
+            if let Some(hash) = candidate.next.next() {
+                candidates.push(SelectCandidate { entry: &self.transactions[hash], next: candidate.next });
+            }
+        }
+
+        selected
+    }
+
+    /// Follows a change of the main chain.
+    /// The transactions of the connected blocks are removed, the ones of the disconnected blocks that
+    /// aren't in the new branch are pending again. Every account that the blocks touched is checked again
+    /// since its balance or its nonce changed.
+    ///
+    /// # Arguments
+    /// * `update` - A &ChainUpdate which specifies the blocks that left and joined the main chain
+    /// * `db` - A &mut BlocksDB which specifies the db used to read the new accounts, already updated
+    ///
+    pub fn on_chain_update(&mut self, update: &ChainUpdate, db: &mut BlocksDB) {
+        let mut touched: HashSet<Address> = HashSet::new();
+        let mut confirmed: HashSet<String> = HashSet::new();
+
+        for block in update.disconnected.iter().chain(update.connected.iter()) {
+            for transaction in block.get_transactions() {
+                if transaction.get_sender() != Point::identity() {
+                    touched.insert(transaction.get_sender_address());
+                }
+
+                touched.insert(transaction.get_recipient());
+            }
+        }
+
+        for block in update.connected.iter() {
+            for transaction in block.get_transactions() {
+                self.remove(&transaction.get_hash());
+                confirmed.insert(transaction.get_hash());
+            }
+        }
+
+        let mut unconfirmed: Vec<Transaction> = update.disconnected.iter()
+            .flat_map(|block| block.get_transactions())
+            .filter(|transaction| transaction.get_sender() != Point::identity() && !confirmed.contains(&transaction.get_hash()))
+            .collect();
+
+        // the senders' nonces went back, their pending transactions now come after the unconfirmed ones
+        let senders: HashSet<Address> = unconfirmed.iter().map(|transaction| transaction.get_sender_address()).collect();
+        for sender in senders {
+            let hashes: Vec<String> = match self.senders.get(&sender) {
+                Some(pending) => pending.nonces.values().cloned().collect(),
+                None => continue
+            };
+
+            for hash in hashes {
+                if let Some(entry) = self.transactions.get(&hash) {
+                    unconfirmed.push(entry.transaction.clone());
+                }
+
+                self.remove(&hash);
+            }
+        }
+
+        // a sender's transactions are added in nonce order, the ones that are no longer valid are refused
+        unconfirmed.sort_by_key(|transaction| transaction.get_nonce());
+        for transaction in unconfirmed {
+            let _ = self.add(transaction, db);
+        }
+
+        for address in touched {
+            self.revalidate_sender(&address, db);
+        }
+    }
+
+    /// Drops the transactions of a sender that can't be put in a block anymore: the ones with a nonce that
+    /// was already used or that don't follow the account's nonce, then the last ones until its pending
+    /// total fits its balance
+    fn revalidate_sender(&mut self, sender: &Address, db: &mut BlocksDB) {
+        let account: Account = db.get_account(sender).unwrap_or_default();
+
+        let pending: &SenderPending = match self.senders.get(sender) {
+            Some(pending) => pending,
+            None => return
+        };
+
+        let mut expected: u64 = account.nonce;
+        let mut invalid: Vec<String> = Vec::new();
+
+        for (nonce, hash) in pending.nonces.iter() {
+            if *nonce == expected {
+                expected += 1;
+            } else {
+                invalid.push(hash.clone());
+            }
+        }
+
+        for hash in invalid {
+            self.remove(&hash);
+        }
+
+        while let Some(pending) = self.senders.get(sender).filter(|pending| pending.total > account.balance) {
+            match pending.nonces.values().next_back().cloned() {
+                Some(hash) => self.remove(&hash),
+                None => break
+            }
+        }
+    }
+
+    /// Returns the pending transaction that would be evicted first, among the last transaction of every sender.
+    /// The sender of the transaction being added is skipped since its new transaction depends on its last one.
+    fn get_lowest_priority(&self, skip: &Address) -> Option<(&String, &MempoolEntry)> {
+        self.senders.iter()
+            .filter(|(sender, _)| *sender != skip)
+            .filter_map(|(_, pending)| pending.nonces.values().next_back())
+            .map(|hash| (hash, &self.transactions[hash]))
+            .max_by(|a, b| a.1.priority_cmp(b.1))
+    }
+
+    /// Removes a transaction and updates its sender's pending transactions
+    fn remove(&mut self, hash: &str) {
+        if let Some(entry) = self.transactions.remove(hash) {
+            let sender: Address = entry.transaction.get_sender_address();
+
+            if let Some(pending) = self.senders.get_mut(&sender) {
+                let cost: Amount = entry.transaction.get_cost().unwrap_or(Amount::MAX);
+                pending.total = pending.total.checked_sub(cost).unwrap_or(Amount::ZERO);
+                pending.nonces.remove(&entry.transaction.get_nonce());
+
+                if pending.nonces.is_empty() {
+                    self.senders.remove(&sender);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ecdsa::secp256k1::PrivateKey;
+    use rblock::Block;
+    use tempfile::TempDir;
+    use crate::test_utils::{mine_block, miner, miner_key, temp_db};
+
+    fn coins(coins: u64) -> Amount {
+        Amount::from_coins(coins).unwrap()
+    }
+
+    fn payment(sender: &PrivateKey, amount: Amount, fee: u64, nonce: u64) -> Transaction {
+        Transaction::new(sender, &miner(), amount, Amount::from_snails(fee), nonce)
+    }
+
+    /// returns a db where the miner has 4.5 coins and three new private keys have 1 coin each
+    fn funded() -> (TempDir, BlocksDB, Vec<PrivateKey>) {
+        let (dir, mut db) = temp_db();
+        let keys: Vec<PrivateKey> = (0..3).map(|_| PrivateKey::generate().unwrap()).collect();
+
+        let mut block: Block = db.get_latest_block().unwrap();
+        for _ in 0..3 {
+            block = mine_block(&block, &Vec::new());
+            db.add_block(&block).unwrap();
+        }
+
+        let funding: Vec<Transaction> = keys.iter().enumerate()
+            .map(|(i, key)| Transaction::new(&miner_key(), &Address::from(&key.get_public_key()), coins(1), Amount::ZERO, i as u64))
+            .collect();
+        db.add_block(&mine_block(&block, &funding)).unwrap();
+
+        (dir, db, keys)
+    }
+
+    #[test]
+    fn duplicates() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let transaction: Transaction = payment(&keys[0], coins(0), 1_000, 0);
+
+        assert!(matches!(mempool.add(transaction.clone(), &mut db), Err(MempoolError::InvalidAmount)));
+
+        let transaction: Transaction = payment(&keys[0], Amount::from_snails(1), 1_000, 0);
+        mempool.add(transaction.clone(), &mut db).unwrap();
+        assert!(matches!(mempool.add(transaction.clone(), &mut db), Err(MempoolError::Duplicate)));
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.contains(&transaction.get_hash()));
+
+        let reward: Transaction = Transaction::reward_transaction(&miner(), 1, Amount::ZERO).unwrap();
+        assert!(matches!(mempool.add(reward, &mut db), Err(MempoolError::Reward)));
+    }
+
+    #[test]
+    fn nonce_gaps() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let amount: Amount = Amount::from_snails(1);
+
+        assert!(matches!(mempool.add(payment(&keys[0], amount, 1_000, 1), &mut db),
+            Err(MempoolError::InvalidNonce { expected: 0, found: 1 })));
+
+        mempool.add(payment(&keys[0], amount, 1_000, 0), &mut db).unwrap();
+        assert!(matches!(mempool.add(payment(&keys[0], amount, 1_000, 2), &mut db),
+            Err(MempoolError::InvalidNonce { expected: 1, found: 2 })));
+        mempool.add(payment(&keys[0], amount, 1_000, 1), &mut db).unwrap();
+
+        // the miner already sent the three funding transactions
+        assert!(matches!(mempool.add(payment(&miner_key(), amount, 1_000, 0), &mut db),
+            Err(MempoolError::InvalidNonce { expected: 3, found: 0 })));
+        mempool.add(payment(&miner_key(), amount, 1_000, 3), &mut db).unwrap();
+    }
+
+    #[test]
+    fn pending_total() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let half: Amount = Amount::from_snails(50_000_000);
+
+        // every transaction fits the balance on its own, not all of them together
+        mempool.add(payment(&keys[0], half, 1_000, 0), &mut db).unwrap();
+        match mempool.add(payment(&keys[0], half, 1_000, 1), &mut db) {
+            Err(MempoolError::InsufficientBalance { balance, pending }) => {
+                assert_eq!(balance, coins(1));
+                assert_eq!(pending, Amount::from_snails(50_001_000));
+            },
+            result => panic!("expected InsufficientBalance, got {:?}", result)
+        }
+
+        // exactly what is left
+        mempool.add(payment(&keys[0], Amount::from_snails(49_998_000), 1_000, 1), &mut db).unwrap();
+        assert_eq!(mempool.len(), 2);
+
+        // an account that doesn't exist has nothing
+        let stranger: PrivateKey = PrivateKey::generate().unwrap();
+        assert!(matches!(mempool.add(payment(&stranger, half, 0, 0), &mut db), Err(MempoolError::InsufficientBalance { .. })));
+    }
+
+    #[test]
+    fn eviction() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(2);
+        let amount: Amount = Amount::from_snails(1);
+
+        let low: Transaction = payment(&keys[0], amount, 1_000, 0);
+        let high: Transaction = payment(&keys[1], amount, 4_000, 0);
+        mempool.add(low.clone(), &mut db).unwrap();
+        mempool.add(high.clone(), &mut db).unwrap();
+
+        // lower fee rate than everything pending
+        assert!(matches!(mempool.add(payment(&keys[2], amount, 500, 0), &mut db), Err(MempoolError::Full)));
+
+        // higher fee rate than the lowest one, which is evicted
+        let middle: Transaction = payment(&keys[2], amount, 2_000, 0);
+        mempool.add(middle.clone(), &mut db).unwrap();
+
+        assert_eq!(mempool.len(), 2);
+        assert!(!mempool.contains(&low.get_hash()));
+        assert!(mempool.contains(&middle.get_hash()));
+        assert!(mempool.contains(&high.get_hash()));
+
+        // a sender's next transaction never evicts its previous one, even when it has the lowest fee rate
+        let next: Transaction = payment(&keys[2], amount, 8_000, 1);
+        mempool.add(next.clone(), &mut db).unwrap();
+
+        assert!(mempool.contains(&middle.get_hash()));
+        assert!(mempool.contains(&next.get_hash()));
+        assert!(!mempool.contains(&high.get_hash()));
+    }
+
+    #[test]
+    fn selection_order() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let amount: Amount = Amount::from_snails(1);
+
+        // the miner's second transaction pays the most but can't go before its first one
+        let transactions: Vec<Transaction> = vec![
+            payment(&miner_key(), amount, 1_000, 3),
+            payment(&miner_key(), amount, 9_000, 4),
+            payment(&keys[0], amount, 4_000, 0),
+            payment(&keys[1], amount, 2_000, 0),
+            payment(&keys[1], amount, 3_000, 1)
+        ];
+
+        for transaction in transactions.iter() {
+            mempool.add(transaction.clone(), &mut db).unwrap();
+        }
+
+        let order: Vec<String> = [2, 3, 4, 0, 1].iter().map(|&i| transactions[i].get_hash()).collect();
+        let selected: Vec<String> = mempool.select(10).iter().map(|transaction| transaction.get_hash()).collect();
+        assert_eq!(selected, order);
+
+        let selected: Vec<String> = mempool.select(2).iter().map(|transaction| transaction.get_hash()).collect();
+        assert_eq!(selected, order[..2]);
+    }
+
+    #[test]
+    fn reorganizations() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let amount: Amount = Amount::from_snails(40_000_000);
+
+        let first: Transaction = payment(&keys[0], amount, 1_000, 0);
+        let second: Transaction = payment(&keys[0], amount, 1_000, 1);
+        let other: Transaction = payment(&keys[1], amount, 1_000, 0);
+        mempool.add(first.clone(), &mut db).unwrap();
+        mempool.add(second.clone(), &mut db).unwrap();
+        mempool.add(other.clone(), &mut db).unwrap();
+
+        // the first transaction is mined
+        let fork: Block = db.get_latest_block().unwrap();
+        let a1: Block = mine_block(&fork, &vec![first.clone()]);
+        let update: ChainUpdate = db.add_block(&a1).unwrap();
+        mempool.on_chain_update(&update, &mut db);
+
+        assert!(!mempool.contains(&first.get_hash()));
+        assert!(mempool.contains(&second.get_hash()));
+        assert_eq!(mempool.len(), 2);
+
+        // a side branch doesn't change the mempool
+        let b1: Block = mine_block(&fork, &vec![other.clone()]);
+        let update: ChainUpdate = db.add_block(&b1).unwrap();
+        assert!(update.connected.is_empty() && update.disconnected.is_empty());
+        mempool.on_chain_update(&update, &mut db);
+        assert_eq!(mempool.len(), 2);
+
+        // until it has more work, the first transaction is pending again and before the second one
+        let b2: Block = mine_block(&b1, &Vec::new());
+        let update: ChainUpdate = db.add_block(&b2).unwrap();
+        assert_eq!(update.disconnected.len(), 1);
+        assert_eq!(update.connected.len(), 2);
+        mempool.on_chain_update(&update, &mut db);
+
+        assert!(mempool.contains(&first.get_hash()));
+        assert!(mempool.contains(&second.get_hash()));
+        assert!(!mempool.contains(&other.get_hash()));
+        assert_eq!(mempool.select(10).iter().map(|transaction| transaction.get_hash()).collect::<Vec<String>>(),
+            [first.get_hash(), second.get_hash()]);
+
+        // a branch that spends the coins differently, the pending transactions that can't be mined anymore are dropped
+        let spend: Transaction = payment(&keys[0], coins(1).checked_sub(Amount::from_snails(1_000)).unwrap(), 1_000, 0);
+        let b3: Block = mine_block(&b2, &vec![spend]);
+        let update: ChainUpdate = db.add_block(&b3).unwrap();
+        mempool.on_chain_update(&update, &mut db);
+
+        assert_eq!(mempool.len(), 0);
+    }
+
+    #[test]
+    fn replays() {
+        let (_dir, mut db, keys) = funded();
+        let mut mempool: Mempool = Mempool::new(MAX_MEMPOOL_SIZE);
+        let amount: Amount = Amount::from_snails(1_000);
+
+        // two pending transactions of the same sender are mined in the same block, in nonce order
+        let first: Transaction = payment(&keys[0], amount, 1_000, 0);
+        let second: Transaction = payment(&keys[0], amount, 1_000, 1);
+        mempool.add(first.clone(), &mut db).unwrap();
+        mempool.add(second.clone(), &mut db).unwrap();
+
+        let block: Block = mine_block(&db.get_latest_block().unwrap(), &mempool.select(10));
+        assert_eq!(block.get_transactions()[1..].iter().map(|transaction| transaction.get_hash()).collect::<Vec<String>>(),
+            [first.get_hash(), second.get_hash()]);
+
+        let update: ChainUpdate = db.add_block(&block).unwrap();
+        mempool.on_chain_update(&update, &mut db);
+        assert_eq!(mempool.len(), 0);
+
+        // the mined transactions can't come back
+        assert!(matches!(mempool.add(first, &mut db), Err(MempoolError::InvalidNonce { expected: 2, found: 0 })));
+        assert!(matches!(mempool.add(second, &mut db), Err(MempoolError::InvalidNonce { expected: 2, found: 1 })));
+
+        // and the next nonce can't be skipped
+        assert!(matches!(mempool.add(payment(&keys[0], amount, 1_000, 3), &mut db),
+            Err(MempoolError::InvalidNonce { expected: 2, found: 3 })));
+        mempool.add(payment(&keys[0], amount, 1_000, 2), &mut db).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, Receiver, Sender}, Arc}, thread};
+use rblock::{Address, Block, Transaction, TRANSACTION_LIMIT_PER_BLOCK};
+use rusty_leveldb::Status;
+use sha256::Sha256;
+use crate::{db::BlocksDB, mempool::Mempool, net::Event, validation::MAX_FUTURE_BLOCK_TIME};
+
+/// A block to find a nonce for, along with the block it builds on
+#[derive(Clone)]
+struct MiningJob {
+    /// Increases with every new job, workers drop their job as soon as a newer one exists
+    id: u64,
+    candidate: Block,
+    prev_block: Block
+}
+
+/// Proof of work miner.
+///
+/// The node builds candidate blocks and hands them to the miner, which searches nonces on several
+/// worker threads. The nonce range is split between the workers. A worker that runs out of nonces
+/// moves its block's timestamp forward and starts over. Solved blocks are sent back to the node
+/// as an Event::MinedBlock so that they go through BlocksDB::add_block like any other block.
+pub struct Miner {
+    /// Where the block rewards are paid
+    address: Address,
+
+    /// Id of the latest job, shared with the workers
+    job_id: Arc<AtomicU64>,
+
+    workers: Vec<Sender<MiningJob>>
+}
+
+impl Miner {
+    /// Starts the worker threads, they wait until a job is given
+    ///
+    /// # Arguments
+    /// * `address` - An Address which specifies the address that gets the block rewards
+    /// * `threads` - A usize which specifies the number of worker threads
+    /// * `node` - A Sender<Event> which specifies where solved blocks are sent
+    ///
+    pub fn start(address: Address, threads: usize, node: Sender<Event>) -> Self {
+        let threads: usize = threads.max(1);
+        let job_id: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+        let mut workers: Vec<Sender<MiningJob>> = Vec::new();
+
+        // every worker gets the same share of the nonce range
+        let range_size: u64 = (u32::MAX as u64 + 1) / threads as u64;
+
+        for i in 0..threads {
+            let (sender, jobs) = mpsc::channel();
+            let start: u32 = (i as u64 * range_size) as u32;
+            let end: u32 = if i == threads - 1 { u32::MAX } else { ((i as u64 + 1) * range_size - 1) as u32 };
+
+            let job_id: Arc<AtomicU64> = job_id.clone();
+            let node: Sender<Event> = node.clone();
+            thread::spawn(move || mine(jobs, job_id, start, end, node));
+
+            workers.push(sender);
+        }
+
+        Miner { address, job_id, workers }
+    }
+
+    /// Builds a new candidate block on top of the latest block and gives it to the workers.
+    /// The block gets the pending transactions with the highest fee rates and a reward that includes their fees.
+    /// Called whenever the latest block changes or new transactions arrive.
+    ///
+    /// # Arguments
+    /// * `db` - A &mut BlocksDB which specifies the db to get the latest block from
+    /// * `mempool` - A &Mempool which specifies where the block's transactions come from
+    ///
+    /// # Returns
+    /// An Result<(), Status> which is an error if the latest block couldn't be read
+    ///
+    pub fn update_job(&mut self, db: &mut BlocksDB, mempool: &Mempool) -> Result<(), Status> {
+        let prev_block: Block = db.get_latest_block()?;
+
+        // keep room for the reward
+        let transactions: Vec<Transaction> = mempool.select(TRANSACTION_LIMIT_PER_BLOCK - 1);
+
+        let mut candidate: Block = Block::new(&prev_block, &transactions);
+        candidate.reward_miner(&self.address);
+
+        // the previous block can be a little in the future, a block older than its parent would be rejected
+        candidate.set_timestamp(candidate.get_timestamp().max(prev_block.get_timestamp()));
+        candidate.set_difficulty(Block::get_supposed_difficulty(&prev_block, &candidate));
+
+        let id: u64 = self.job_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let job: MiningJob = MiningJob { id, candidate, prev_block };
+
+        for worker in self.workers.iter() {
+            let _ = worker.send(job.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Stops the workers from mining the current job, used while the node is syncing
+    pub fn pause(&mut self) {
+        self.job_id.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Loop of a worker thread, searches nonces between start and end for every job it gets
+///
+/// # Arguments
+/// * `jobs` - A Receiver<MiningJob> which specifies where jobs come from
+/// * `job_id` - A Arc<AtomicU64> which specifies the id of the latest job
+/// * `start` - A u32 which specifies the first nonce of the worker's range
+/// * `end` - A u32 which specifies the last nonce of the worker's range
+/// * `node` - A Sender<Event> which specifies where solved blocks are sent
+///
+fn mine(jobs: Receiver<MiningJob>, job_id: Arc<AtomicU64>, start: u32, end: u32, node: Sender<Event>) {
+    while let Ok(mut job) = jobs.recv() {
+        // skip to the newest job
+        while let Ok(newer) = jobs.try_recv() {
+            job = newer;
+        }
+
+        let mut block: Block = job.candidate;
+
+        'job: loop {
+            // only the part of the header from the nonce on is hashed for every nonce
+            let midstate: Sha256 = block.get_midstate();
+
+            for nonce in start..=end {
+                if job_id.load(Ordering::Relaxed) != job.id {
+                    break 'job;
+                }
+
+                block.set_nonce_from_midstate(nonce, &midstate);
+
+                if block.confirm_difficulty() {
+                    let _ = node.send(Event::MinedBlock(block));
+                    break 'job;
+                }
+            }
+
+            // every nonce was tried, move the timestamp forward to get new hashes
+            let timestamp: u64 = rblock::get_unix_time().max(job.prev_block.get_timestamp()).max(block.get_timestamp() + 1);
+            if timestamp > rblock::get_unix_time() + MAX_FUTURE_BLOCK_TIME {
+                // a block this far in the future would be rejected, wait for a new job
+                break 'job;
+            }
+
+            block.set_timestamp(timestamp);
+            block.set_difficulty(Block::get_supposed_difficulty(&job.prev_block, &block));
+        }
+    }
+}
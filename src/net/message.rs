@@ -0,0 +1,134 @@
+use std::{io::{self, ErrorKind, Read, Write}, net::SocketAddr};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rblock::{Block, BlockHeader, Transaction};
+use serde::{Deserialize, Serialize};
+use super::{MAX_MESSAGE_SIZE, NETWORK_MAGIC};
+
+/// An announcement of an object that a node has, identified by its hash
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Inventory {
+    Block(String),
+    Transaction(String)
+}
+
+/// The messages that nodes exchange with each other.
+/// Blocks and transactions are sent as-is since they are already serializable with bincode.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// First message sent on every connection, describes the sender
+    Version {
+        /// The protocol version of the sender
+        version: u32,
+
+        /// The height of the sender's latest block
+        height: u64,
+
+        /// The port the sender listens on, used to share its address with other nodes
+        listen_port: u16,
+
+        /// Random number used to detect connections to self
+        nonce: u64
+    },
+
+    /// Acknowledges a Version message, the handshake is done once both sides got one
+    Verack,
+
+    /// Checks that the peer is still alive, it must answer with a Pong with the same number
+    Ping(u64),
+    Pong(u64),
+
+    /// Asks the peer for the addresses of the nodes it knows
+    GetAddr,
+    Addr(Vec<SocketAddr>),
+
+    /// Announces blocks or transactions that the sender has
+    Inv(Vec<Inventory>),
+
+    /// Asks the peer for the full blocks or transactions of an announcement
+    GetData(Vec<Inventory>),
+
+    /// Answers a GetData with the objects that the sender doesn't have
+    NotFound(Vec<Inventory>),
+
+    /// Asks the peer for the headers of its main chain after the first hash of the locator that it knows
+    GetHeaders(Vec<String>),
+    Headers(Vec<BlockHeader>),
+
+    Block(Block),
+    Transaction(Transaction)
+}
+
+impl Message {
+    /// returns the name of the message, used for logging
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::Version { .. } => "version",
+            Message::Verack => "verack",
+            Message::Ping(_) => "ping",
+            Message::Pong(_) => "pong",
+            Message::GetAddr => "getaddr",
+            Message::Addr(_) => "addr",
+            Message::Inv(_) => "inv",
+            Message::GetData(_) => "getdata",
+            Message::NotFound(_) => "notfound",
+            Message::GetHeaders(_) => "getheaders",
+            Message::Headers(_) => "headers",
+            Message::Block(_) => "block",
+            Message::Transaction(_) => "tx"
+        }
+    }
+}
+
+/// Writes a message to a stream.
+/// Every message is framed as: network magic (4 bytes), payload length (u32 little-endian), bincode payload
+///
+/// # Arguments
+/// * `stream` - A &mut W which specifies where to write the message
+/// * `message` - A &Message which specifies the message to write
+///
+/// # Returns
+/// An io::Result<()> which is Ok(()) if the whole message was written
+///
+pub fn write_message<W: Write>(stream: &mut W, message: &Message) -> io::Result<()> {
+    let payload: Vec<u8> = bincode::serialize(message).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+    if payload.len() > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "Message is too big"));
+    }
+
+    // write the whole frame at once so that messages from different threads don't interleave
+    let mut frame: Vec<u8> = Vec::with_capacity(payload.len() + 8);
+    frame.extend_from_slice(&NETWORK_MAGIC);
+    frame.write_u32::<LittleEndian>(payload.len() as u32)?;
+    frame.extend_from_slice(&payload);
+
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+/// Reads a message from a stream, blocking until a whole message is available
+///
+/// # Arguments
+/// * `stream` - A &mut R which specifies where to read the message from
+///
+/// # Returns
+/// An io::Result<Message> which is the message read, or an error if the stream is closed or the data is invalid
+///
+pub fn read_message<R: Read>(stream: &mut R) -> io::Result<Message> {
+    let mut magic: [u8; 4] = [0; 4];
+    stream.read_exact(&mut magic)?;
+
+    if magic != NETWORK_MAGIC {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Wrong network magic"));
+    }
+
+    let length: usize = stream.read_u32::<LittleEndian>()? as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Message is too big"));
+    }
+
+    let mut payload: Vec<u8> = vec![0; length];
+    stream.read_exact(&mut payload)?;
+
+    bincode::deserialize(&payload).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,35 @@
+//! Peer-to-peer networking between nodes.
+//! Nodes talk over TCP with bincode-serialized messages, see message.rs for the wire format.
+
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 7450;
+const PROTOCOL_VERSION: u32 = 1;
+const NETWORK_MAGIC: [u8; 4] = *b"SNLN";
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024; // 16 MB, a full block fits with room to spare
+
+const MAX_OUTBOUND_PEERS: usize = 8;
+const MAX_INBOUND_PEERS: usize = 32;
+const MAX_KNOWN_ADDRS: usize = 1000;
+const MAX_LOCATOR_SIZE: usize = 101;
+const MAX_HEADERS: usize = 2000;
+const MAX_BLOCKS_IN_FLIGHT_PER_PEER: usize = 16;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+const TICK_INTERVAL: Duration = Duration::from_secs(10);
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const PEER_TIMEOUT: Duration = Duration::from_secs(90);
+const HEADERS_TIMEOUT: Duration = Duration::from_secs(60);
+const BLOCK_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+const GET_DATA_TIMEOUT: Duration = Duration::from_secs(60);
+
+mod message;
+pub use message::{Inventory, Message, read_message, write_message};
+
+mod node;
+pub use node::{Event, Node};
+
+mod sync;
+pub use sync::ChainSync;
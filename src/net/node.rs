@@ -0,0 +1,952 @@
+use std::{collections::{HashMap, HashSet}, io::{self, BufReader}, net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+          sync::mpsc::{self, Receiver, Sender}, thread, time::Instant};
+use rblock::{Address, Block, BlockHeader, Transaction};
+use crate::{db::BlocksDB, mempool::{Mempool, MempoolError, MAX_MEMPOOL_SIZE}, miner::Miner, validation::BlockError};
+use super::{ChainSync, Inventory, Message, read_message, write_message, CONNECT_TIMEOUT, GET_DATA_TIMEOUT, HANDSHAKE_TIMEOUT, MAX_HEADERS,
+            MAX_INBOUND_PEERS, MAX_KNOWN_ADDRS, MAX_LOCATOR_SIZE, MAX_OUTBOUND_PEERS, PEER_TIMEOUT, PING_INTERVAL,
+            PROTOCOL_VERSION, TICK_INTERVAL, WRITE_TIMEOUT};
+
+/// Everything that the node reacts to.
+/// Network threads and other subsystems send events to the node, which handles them one at a time
+/// on its own thread since the db can't be shared between threads.
+pub enum Event {
+    /// A TCP connection to a peer was opened
+    Connected { addr: SocketAddr, stream: TcpStream, inbound: bool },
+
+    /// An outbound connection attempt failed
+    ConnectFailed { addr: SocketAddr },
+
+    /// A message was received from a peer
+    Received { addr: SocketAddr, message: Message },
+
+    /// The connection to a peer was closed
+    Disconnected { addr: SocketAddr },
+
+    /// A block was solved by our miner
+    MinedBlock(Block),
+
+    /// Sent periodically to run housekeeping (pings, timeouts, new connections)
+    Tick
+}
+
+/// State of a connection to a peer
+struct Peer {
+    /// Write half of the connection, the read half is owned by the peer's reader thread
+    stream: TcpStream,
+
+    /// Whether the peer connected to us
+    inbound: bool,
+
+    /// The address the peer listens on, known once its version is received
+    listen_addr: Option<SocketAddr>,
+
+    /// The height of the peer's latest block when it sent its version
+    height: u64,
+
+    version_received: bool,
+    verack_received: bool,
+
+    /// When the connection was opened
+    connected_at: Instant,
+
+    /// The nonce and time of the last ping that wasn't answered yet
+    pending_ping: Option<(u64, Instant)>,
+
+    /// When the last ping was sent
+    last_ping: Instant
+}
+
+impl Peer {
+    /// returns true once both sides have exchanged version and verack
+    fn is_ready(&self) -> bool {
+        self.version_received && self.verack_received
+    }
+}
+
+/// A node of the network.
+/// Owns the block db and the connections to peers, relays blocks and transactions between them.
+pub struct Node {
+    db: BlocksDB,
+
+    /// The port this node listens on
+    listen_port: u16,
+
+    /// Random number sent in our version to detect connections to self
+    nonce: u64,
+
+    peers: HashMap<SocketAddr, Peer>,
+
+    /// Listening addresses of nodes that we heard of
+    known_addrs: HashSet<SocketAddr>,
+
+    /// Addresses that we are currently trying to connect to
+    connecting: HashSet<SocketAddr>,
+
+    /// Verified transactions that aren't in a block yet
+    mempool: Mempool,
+
+    /// Objects requested from peers that haven't arrived yet, with the peer they were asked to and when.
+    /// An object that doesn't arrive in time can be asked to the next peer that announces it
+    requested: HashMap<Inventory, (SocketAddr, Instant)>,
+
+    /// Downloads the chain when we are behind our peers
+    sync: ChainSync,
+
+    /// Mines blocks on top of our latest block if mining is enabled
+    miner: Option<Miner>,
+
+    /// Set when the miner's job is out of date, it is rebuilt on the next tick
+    mining_job_stale: bool,
+
+    sender: Sender<Event>,
+    events: Receiver<Event>
+}
+
+impl Node {
+    /// Creates a new node that uses the given db
+    ///
+    /// # Arguments
+    /// * `db` - A BlocksDB which specifies the db of the node
+    /// * `listen_port` - A u16 which specifies the port to listen on for peers
+    ///
+    pub fn new(db: BlocksDB, listen_port: u16) -> Self {
+        let (sender, events) = mpsc::channel();
+
+        Node {
+            db,
+            listen_port,
+            nonce: rand::random(),
+            peers: HashMap::new(),
+            known_addrs: HashSet::new(),
+            connecting: HashSet::new(),
+            mempool: Mempool::new(MAX_MEMPOOL_SIZE),
+            requested: HashMap::new(),
+            sync: ChainSync::new(),
+            miner: None,
+            mining_job_stale: false,
+            sender,
+            events
+        }
+    }
+
+    /// Starts mining blocks that pay their reward to the given address
+    ///
+    /// # Arguments
+    /// * `address` - An Address which specifies the address that gets the block rewards
+    /// * `threads` - A usize which specifies the number of threads to mine with
+    ///
+    pub fn start_miner(&mut self, address: Address, threads: usize) {
+        println!("Mining with {} threads", threads);
+        self.miner = Some(Miner::start(address, threads, self.get_sender()));
+    }
+
+    /// returns a sender that can be used to send events to the node from other threads
+    pub fn get_sender(&self) -> Sender<Event> {
+        self.sender.clone()
+    }
+
+    /// Starts listening for peers, connects to the given addresses and handles events until the process stops
+    ///
+    /// # Arguments
+    /// * `addrs` - A Vec<SocketAddr> which specifies the peers to connect to at startup
+    ///
+    /// # Returns
+    /// An io::Result<()> which is an error if the node couldn't start listening
+    ///
+    pub fn run(mut self, addrs: Vec<SocketAddr>) -> io::Result<()> {
+        self.start(addrs)?;
+
+        // the node holds a sender itself so this never ends
+        while let Ok(event) = self.events.recv() {
+            self.handle_event(event);
+        }
+
+        Ok(())
+    }
+
+    /// Starts listening for peers and the periodic housekeeping, then connects to the given addresses.
+    /// If the listening port is 0, the port picked by the OS becomes the one announced to peers
+    ///
+    /// # Arguments
+    /// * `addrs` - A Vec<SocketAddr> which specifies the peers to connect to at startup
+    ///
+    /// # Returns
+    /// An io::Result<()> which is an error if the node couldn't start listening
+    ///
+    fn start(&mut self, addrs: Vec<SocketAddr>) -> io::Result<()> {
+        let listener: TcpListener = TcpListener::bind(("0.0.0.0", self.listen_port))?;
+        self.listen_port = listener.local_addr()?.port();
+        println!("Listening for peers on port {}", self.listen_port);
+
+        // accept inbound connections
+        let sender: Sender<Event> = self.get_sender();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Ok(addr) = stream.peer_addr() {
+                    if sender.send(Event::Connected { addr, stream, inbound: true }).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        // periodic housekeeping
+        let sender: Sender<Event> = self.get_sender();
+        thread::spawn(move || {
+            while sender.send(Event::Tick).is_ok() {
+                thread::sleep(TICK_INTERVAL);
+            }
+        });
+
+        for addr in addrs {
+            self.known_addrs.insert(addr);
+            self.connect(addr);
+        }
+
+        self.update_mining_job();
+
+        Ok(())
+    }
+
+    /// Handles a single event
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Connected { addr, stream, inbound } => self.on_connected(addr, stream, inbound),
+            Event::ConnectFailed { addr } => {
+                self.connecting.remove(&addr);
+                eprintln!("Could not connect to {}", addr);
+            },
+            Event::Received { addr, message } => self.on_message(addr, message),
+            Event::Disconnected { addr } => {
+                if self.peers.remove(&addr).is_some() {
+                    println!("Peer {} disconnected", addr);
+                    self.on_peer_removed(addr);
+                }
+            },
+            Event::MinedBlock(block) => {
+                println!("Mined block {}", block.get_hash());
+                self.process_block(block, None);
+            },
+            Event::Tick => self.on_tick(Instant::now())
+        }
+    }
+
+    /// Opens a connection to a peer on another thread, the node is notified with a Connected event
+    ///
+    /// # Arguments
+    /// * `addr` - A SocketAddr which specifies the listening address of the peer
+    ///
+    fn connect(&mut self, addr: SocketAddr) {
+        let already_connected: bool = self.peers.values().any(|peer| peer.listen_addr == Some(addr));
+
+        if already_connected || !self.connecting.insert(addr) {
+            return;
+        }
+
+        let sender: Sender<Event> = self.get_sender();
+        thread::spawn(move || {
+            let event: Event = match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => Event::Connected { addr, stream, inbound: false },
+                Err(_) => Event::ConnectFailed { addr }
+            };
+
+            let _ = sender.send(event);
+        });
+    }
+
+    /// Registers a new connection and starts reading messages from it.
+    /// Outbound connections start the handshake by sending our version.
+    fn on_connected(&mut self, addr: SocketAddr, stream: TcpStream, inbound: bool) {
+        if !inbound {
+            self.connecting.remove(&addr);
+        }
+
+        let count: usize = self.peers.values().filter(|peer| peer.inbound == inbound).count();
+        let limit: usize = if inbound { MAX_INBOUND_PEERS } else { MAX_OUTBOUND_PEERS };
+
+        if count >= limit {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        }
+
+        let reader: TcpStream = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Could not read from {}: {}", addr, e);
+                return;
+            }
+        };
+
+        // a peer that doesn't read shouldn't block the node forever
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+
+        let now: Instant = Instant::now();
+        self.peers.insert(addr, Peer {
+            stream,
+            inbound,
+            listen_addr: if inbound { None } else { Some(addr) },
+            height: 0,
+            version_received: false,
+            verack_received: false,
+            connected_at: now,
+            pending_ping: None,
+            last_ping: now
+        });
+
+        let sender: Sender<Event> = self.get_sender();
+        thread::spawn(move || {
+            let mut reader: BufReader<TcpStream> = BufReader::new(reader);
+
+            loop {
+                match read_message(&mut reader) {
+                    Ok(message) => {
+                        if sender.send(Event::Received { addr, message }).is_err() {
+                            return;
+                        }
+                    },
+                    Err(e) => {
+                        // a message that can't be decoded, such as one with a public key that isn't on the curve,
+                        // disconnects the peer like a closed stream
+                        if e.kind() == io::ErrorKind::InvalidData {
+                            eprintln!("Invalid message from {}: {}", addr, e);
+                        }
+                        break;
+                    }
+                }
+            }
+
+            let _ = sender.send(Event::Disconnected { addr });
+        });
+
+        println!("Connected to {} ({})", addr, if inbound { "inbound" } else { "outbound" });
+
+        if !inbound {
+            self.send_version(addr);
+        }
+    }
+
+    /// Sends our version to a peer
+    fn send_version(&mut self, addr: SocketAddr) {
+        let height: u64 = self.db.get_latest_block().map(|block| block.get_height()).unwrap_or(0);
+
+        self.send(addr, &Message::Version {
+            version: PROTOCOL_VERSION,
+            height,
+            listen_port: self.listen_port,
+            nonce: self.nonce
+        });
+    }
+
+    /// Sends a message to a peer, the peer is disconnected if the message can't be written
+    fn send(&mut self, addr: SocketAddr, message: &Message) {
+        let peer: &mut Peer = match self.peers.get_mut(&addr) {
+            Some(peer) => peer,
+            None => return
+        };
+
+        if let Err(e) = write_message(&mut peer.stream, message) {
+            eprintln!("Could not send {} to {}: {}", message.name(), addr, e);
+            self.disconnect(addr);
+        }
+    }
+
+    /// Sends a message to every peer that completed the handshake
+    ///
+    /// # Arguments
+    /// * `message` - A &Message which specifies the message to send
+    /// * `except` - An Option<SocketAddr> which specifies a peer to skip, usually the one the data came from
+    ///
+    fn broadcast(&mut self, message: &Message, except: Option<SocketAddr>) {
+        let addrs: Vec<SocketAddr> = self.peers.iter()
+            .filter(|(addr, peer)| peer.is_ready() && Some(**addr) != except)
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        for addr in addrs {
+            self.send(addr, message);
+        }
+    }
+
+    /// Closes the connection to a peer
+    fn disconnect(&mut self, addr: SocketAddr) {
+        if let Some(peer) = self.peers.remove(&addr) {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+            println!("Disconnected from {}", addr);
+
+            self.on_peer_removed(addr);
+        }
+    }
+
+    /// Forgets what was asked to a peer that is gone so that other peers can be asked
+    fn on_peer_removed(&mut self, addr: SocketAddr) {
+        self.requested.retain(|_, (peer, _)| *peer != addr);
+
+        self.sync.on_disconnect(addr);
+        self.request_blocks();
+    }
+
+    /// Handles a message received from a peer
+    fn on_message(&mut self, addr: SocketAddr, message: Message) {
+        let ready: bool = match self.peers.get(&addr) {
+            Some(peer) => peer.is_ready(),
+            None => return // message arrived after the peer was dropped
+        };
+
+        match message {
+            Message::Version { version, height, listen_port, nonce } =>
+                self.on_version(addr, version, height, listen_port, nonce),
+            Message::Verack => self.on_verack(addr),
+
+            // nothing else is accepted before the handshake is done
+            _ if !ready => {
+                eprintln!("Peer {} sent {} before handshake", addr, message.name());
+                self.disconnect(addr);
+            },
+
+            Message::Ping(nonce) => self.send(addr, &Message::Pong(nonce)),
+            Message::Pong(nonce) => {
+                if let Some(peer) = self.peers.get_mut(&addr) {
+                    if peer.pending_ping.map(|(sent, _)| sent) == Some(nonce) {
+                        peer.pending_ping = None;
+                    }
+                }
+            },
+            Message::GetAddr => self.on_get_addr(addr),
+            Message::Addr(addrs) => self.on_addr(addrs),
+            Message::Inv(inventory) => self.on_inv(addr, inventory),
+            Message::GetData(inventory) => self.on_get_data(addr, inventory),
+            Message::NotFound(inventory) => {
+                for item in inventory.iter() {
+                    if self.requested.get(item).is_some_and(|(peer, _)| *peer == addr) {
+                        self.requested.remove(item);
+                    }
+                }
+
+                self.sync.on_not_found(addr, &inventory);
+                self.request_blocks();
+            },
+            Message::GetHeaders(locator) => self.on_get_headers(addr, locator),
+            Message::Headers(headers) => self.on_headers(addr, headers),
+            Message::Block(block) => self.process_block(block, Some(addr)),
+            Message::Transaction(transaction) => self.process_transaction(transaction, Some(addr))
+        }
+    }
+
+    /// Handles the version of a peer, replies with a verack (and our version if the peer connected to us)
+    fn on_version(&mut self, addr: SocketAddr, version: u32, height: u64, listen_port: u16, nonce: u64) {
+        if nonce == self.nonce {
+            // connected to ourselves, forget the address. the version is received on the inbound side,
+            // where the address we connected to is our own listening port
+            self.known_addrs.remove(&SocketAddr::new(addr.ip(), listen_port));
+            if let Some(listen_addr) = self.peers.get(&addr).and_then(|peer| peer.listen_addr) {
+                self.known_addrs.remove(&listen_addr);
+            }
+
+            self.disconnect(addr);
+            return;
+        }
+
+        if version != PROTOCOL_VERSION {
+            eprintln!("Peer {} uses unsupported protocol version {}", addr, version);
+            self.disconnect(addr);
+            return;
+        }
+
+        let listen_addr: SocketAddr = SocketAddr::new(addr.ip(), listen_port);
+
+        // a second connection to the same node isn't needed
+        let duplicate: bool = self.peers.iter()
+            .any(|(other, peer)| *other != addr && peer.listen_addr == Some(listen_addr));
+
+        let inbound: bool = match self.peers.get_mut(&addr) {
+            Some(peer) if !peer.version_received && !duplicate => {
+                peer.version_received = true;
+                peer.height = height;
+                peer.listen_addr = Some(listen_addr);
+                peer.inbound
+            },
+            _ => {
+                self.disconnect(addr);
+                return;
+            }
+        };
+
+        self.add_known_addr(listen_addr);
+
+        if inbound {
+            self.send_version(addr);
+        }
+
+        self.send(addr, &Message::Verack);
+        self.on_handshake_progress(addr);
+    }
+
+    /// Handles the verack of a peer
+    fn on_verack(&mut self, addr: SocketAddr) {
+        if let Some(peer) = self.peers.get_mut(&addr) {
+            peer.verack_received = true;
+        }
+
+        self.on_handshake_progress(addr);
+    }
+
+    /// Called when part of the handshake is done, asks the peer for addresses once the handshake is complete
+    fn on_handshake_progress(&mut self, addr: SocketAddr) {
+        let height: u64 = match self.peers.get(&addr) {
+            Some(peer) if peer.is_ready() => peer.height,
+            _ => return
+        };
+
+        println!("Handshake done with {} at height {}", addr, height);
+        self.send(addr, &Message::GetAddr);
+
+        let our_height: u64 = self.db.get_latest_block().map(|block| block.get_height()).unwrap_or(0);
+        if height > our_height {
+            self.start_sync(addr);
+        }
+    }
+
+    /// Starts downloading the chain's headers from a peer
+    fn start_sync(&mut self, addr: SocketAddr) {
+        match self.sync.start(addr, &mut self.db) {
+            Ok(Some(request)) => {
+                println!("Syncing headers from {}", addr);
+                self.send(addr, &request);
+            },
+            Ok(None) => {},
+            Err(e) => eprintln!("Could not start sync: {}", e)
+        }
+    }
+
+    /// Sends a peer the headers of our main chain that come after its locator
+    fn on_get_headers(&mut self, addr: SocketAddr, locator: Vec<String>) {
+        if locator.len() > MAX_LOCATOR_SIZE {
+            self.disconnect(addr);
+            return;
+        }
+
+        match self.db.get_headers_after(&locator, MAX_HEADERS) {
+            Ok(headers) => self.send(addr, &Message::Headers(headers)),
+            Err(e) => eprintln!("Could not read headers: {}", e)
+        }
+    }
+
+    /// Passes headers to the sync and downloads their blocks
+    fn on_headers(&mut self, addr: SocketAddr, headers: Vec<BlockHeader>) {
+        match self.sync.on_headers(addr, headers, &mut self.db) {
+            Ok(Some(request)) => self.send(addr, &request),
+            Ok(None) => {},
+            Err(e) => {
+                eprintln!("Peer {} sent bad headers: {}", addr, e);
+                self.disconnect(addr);
+            }
+        }
+
+        self.request_blocks();
+    }
+
+    /// Asks peers for the blocks that the sync needs
+    fn request_blocks(&mut self) {
+        let peers: Vec<SocketAddr> = self.peers.iter()
+            .filter(|(_, peer)| peer.is_ready())
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        for (addr, request) in self.sync.request_blocks(&peers) {
+            self.send(addr, &request);
+        }
+    }
+
+    /// Sends the listening addresses of our peers to a peer
+    fn on_get_addr(&mut self, addr: SocketAddr) {
+        let addrs: Vec<SocketAddr> = self.peers.iter()
+            .filter(|(other, peer)| **other != addr && peer.is_ready())
+            .filter_map(|(_, peer)| peer.listen_addr)
+            .collect();
+
+        self.send(addr, &Message::Addr(addrs));
+    }
+
+    /// Remembers the addresses sent by a peer and connects to them if we need more peers
+    fn on_addr(&mut self, addrs: Vec<SocketAddr>) {
+        for addr in addrs.into_iter().take(MAX_KNOWN_ADDRS) {
+            if self.add_known_addr(addr) {
+                self.connect_if_needed(addr);
+            }
+        }
+    }
+
+    /// Remembers the listening address of a node
+    ///
+    /// # Returns
+    /// A bool which is true if the address wasn't known before
+    ///
+    fn add_known_addr(&mut self, addr: SocketAddr) -> bool {
+        if self.known_addrs.len() >= MAX_KNOWN_ADDRS {
+            return false;
+        }
+
+        self.known_addrs.insert(addr)
+    }
+
+    /// Connects to an address if we have room for more outbound peers
+    fn connect_if_needed(&mut self, addr: SocketAddr) {
+        let outbound: usize = self.peers.values().filter(|peer| !peer.inbound).count() + self.connecting.len();
+
+        if outbound < MAX_OUTBOUND_PEERS {
+            self.connect(addr);
+        }
+    }
+
+    /// Asks a peer for the announced blocks and transactions that we don't have yet
+    fn on_inv(&mut self, addr: SocketAddr, inventory: Vec<Inventory>) {
+        let mut wanted: Vec<Inventory> = Vec::new();
+        let now: Instant = Instant::now();
+
+        for item in inventory {
+            let known: bool = match &item {
+                Inventory::Block(hash) => self.db.contains_block(hash),
+                Inventory::Transaction(hash) => self.mempool.contains(hash)
+            };
+
+            if !known && !self.requested.contains_key(&item) {
+                self.requested.insert(item.clone(), (addr, now));
+                wanted.push(item);
+            }
+        }
+
+        if !wanted.is_empty() {
+            self.send(addr, &Message::GetData(wanted));
+        }
+    }
+
+    /// Sends the requested blocks and transactions to a peer
+    fn on_get_data(&mut self, addr: SocketAddr, inventory: Vec<Inventory>) {
+        let mut not_found: Vec<Inventory> = Vec::new();
+
+        for item in inventory {
+            let message: Option<Message> = match &item {
+                Inventory::Block(hash) => self.db.get_block_by_hash(hash).ok().map(Message::Block),
+                Inventory::Transaction(hash) => self.mempool.get(hash).cloned().map(Message::Transaction)
+            };
+
+            match message {
+                Some(message) => self.send(addr, &message),
+                None => not_found.push(item)
+            }
+        }
+
+        if !not_found.is_empty() {
+            self.send(addr, &Message::NotFound(not_found));
+        }
+    }
+
+    /// Adds a block to the db and announces it to the other peers if it's accepted
+    ///
+    /// # Arguments
+    /// * `block` - A Block which specifies the block to add
+    /// * `source` - An Option<SocketAddr> which specifies the peer that sent the block, None if it's ours
+    ///
+    fn process_block(&mut self, block: Block, source: Option<SocketAddr>) {
+        let from_sync: bool = self.sync.on_block(&block.get_hash());
+        let mut pending: Vec<Block> = vec![block];
+        let latest: Option<String> = self.get_latest_hash();
+
+        // adding a block can connect downloaded blocks that were waiting on it
+        while let Some(block) = pending.pop() {
+            let hash: String = block.get_hash();
+            self.requested.remove(&Inventory::Block(hash.clone()));
+
+            match self.db.add_block(&block) {
+                Ok(update) => {
+                    println!("Accepted block {} at height {}", hash, block.get_height());
+
+                    if !update.disconnected.is_empty() {
+                        println!("Reorganized {} blocks onto {}", update.disconnected.len(), hash);
+                    }
+
+                    // transactions of the new main chain don't need to be relayed anymore, the ones of the old branch do
+                    self.mempool.on_chain_update(&update, &mut self.db);
+
+                    // peers don't need every block we download while catching up
+                    if !self.sync.is_syncing() {
+                        self.broadcast(&Message::Inv(vec![Inventory::Block(hash.clone())]), source);
+                    }
+
+                    pending.extend(self.sync.take_children(&hash));
+                },
+                Err(BlockError::AlreadyExists) => pending.extend(self.sync.take_children(&hash)),
+                Err(BlockError::UnknownParent { .. }) => {
+                    if from_sync {
+                        self.sync.hold(block);
+                    } else if let Some(addr) = source {
+                        // we are missing blocks that the peer has
+                        self.start_sync(addr);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Rejected block {}: {}", hash, e);
+                    self.sync.on_rejected(&hash);
+                }
+            }
+        }
+
+        self.request_blocks();
+
+        // mine on top of the new latest block, a block on a shorter branch doesn't change the job
+        if self.get_latest_hash() != latest {
+            self.update_mining_job();
+        }
+    }
+
+    /// returns the hash of our latest block
+    fn get_latest_hash(&mut self) -> Option<String> {
+        self.db.get_latest_block().map(|block| block.get_hash()).ok()
+    }
+
+    /// Gives the miner a new block to mine on top of our latest block, mining waits while we sync
+    fn update_mining_job(&mut self) {
+        let miner: &mut Miner = match self.miner.as_mut() {
+            Some(miner) => miner,
+            None => return
+        };
+
+        if self.sync.is_syncing() {
+            miner.pause();
+            self.mining_job_stale = true;
+            return;
+        }
+
+        self.mining_job_stale = false;
+        if let Err(e) = miner.update_job(&mut self.db, &self.mempool) {
+            eprintln!("Could not build block to mine: {}", e);
+        }
+    }
+
+    /// Adds a transaction to the mempool and announces it to the other peers if it's accepted
+    ///
+    /// # Arguments
+    /// * `transaction` - A Transaction which specifies the transaction to add
+    /// * `source` - An Option<SocketAddr> which specifies the peer that sent the transaction, None if it's ours
+    ///
+    fn process_transaction(&mut self, transaction: Transaction, source: Option<SocketAddr>) {
+        let hash: String = transaction.get_hash();
+        self.requested.remove(&Inventory::Transaction(hash.clone()));
+
+        match self.mempool.add(transaction, &mut self.db) {
+            Ok(()) => {
+                println!("Accepted transaction {} ({} pending)", hash, self.mempool.len());
+                self.mining_job_stale = true;
+                self.broadcast(&Message::Inv(vec![Inventory::Transaction(hash)]), source);
+            },
+            Err(MempoolError::Duplicate) => {},
+            Err(e) => eprintln!("Rejected transaction {}: {}", hash, e)
+        }
+    }
+
+    /// Pings peers, drops the ones that stopped answering and looks for new peers if needed
+    ///
+    /// # Arguments
+    /// * `now` - An Instant which specifies the time of the tick, the timeouts are measured up to it
+    ///
+    fn on_tick(&mut self, now: Instant) {
+        let mut dead: Vec<SocketAddr> = Vec::new();
+        let mut to_ping: Vec<SocketAddr> = Vec::new();
+
+        for (addr, peer) in self.peers.iter() {
+            let handshake_expired: bool = !peer.is_ready() && now - peer.connected_at > HANDSHAKE_TIMEOUT;
+            let ping_expired: bool = peer.pending_ping.is_some_and(|(_, sent)| now - sent > PEER_TIMEOUT);
+
+            if handshake_expired || ping_expired {
+                dead.push(*addr);
+            } else if peer.is_ready() && peer.pending_ping.is_none() && now - peer.last_ping > PING_INTERVAL {
+                to_ping.push(*addr);
+            }
+        }
+
+        for addr in dead {
+            self.disconnect(addr);
+        }
+
+        // objects that didn't arrive in time are asked again when another peer announces them
+        self.requested.retain(|_, (_, requested_at)| now - *requested_at <= GET_DATA_TIMEOUT);
+
+        // give up on a sync peer that doesn't answer and try with another peer that has more blocks
+        if let Some(addr) = self.sync.on_tick() {
+            eprintln!("Sync peer {} timed out", addr);
+            self.disconnect(addr);
+
+            let our_height: u64 = self.db.get_latest_block().map(|block| block.get_height()).unwrap_or(0);
+            let other: Option<SocketAddr> = self.peers.iter()
+                .find(|(_, peer)| peer.is_ready() && peer.height > our_height)
+                .map(|(addr, _)| *addr);
+
+            if let Some(other) = other {
+                self.start_sync(other);
+            }
+        }
+
+        self.request_blocks();
+
+        // new transactions and the end of a sync are picked up by the miner at most once per tick
+        if self.mining_job_stale {
+            self.update_mining_job();
+        }
+
+        for addr in to_ping {
+            let nonce: u64 = rand::random();
+
+            if let Some(peer) = self.peers.get_mut(&addr) {
+                peer.pending_ping = Some((nonce, now));
+                peer.last_ping = now;
+            }
+
+            self.send(addr, &Message::Ping(nonce));
+        }
+
+        // try known addresses that we aren't connected to
+        let connected: HashSet<SocketAddr> = self.peers.values().filter_map(|peer| peer.listen_addr).collect();
+        let candidates: Vec<SocketAddr> = self.known_addrs.iter()
+            .filter(|addr| !connected.contains(addr) && !self.connecting.contains(addr))
+            .copied()
+            .collect();
+
+        for addr in candidates {
+            self.connect_if_needed(addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use crate::test_utils::{mine_block, temp_db};
+
+    /// returns a node listening on a port picked by the OS, connected to the given addresses
+    fn start_node(addrs: Vec<SocketAddr>) -> (TempDir, Node) {
+        let (dir, db) = temp_db();
+        let mut node: Node = Node::new(db, 0);
+        node.start(addrs).unwrap();
+
+        (dir, node)
+    }
+
+    fn listen_addr(node: &Node) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], node.listen_port))
+    }
+
+    /// handles the events of every node on this thread until the condition is true
+    ///
+    /// # Returns
+    /// A bool which is false if the condition still wasn't true after a few seconds
+    ///
+    fn run_until<F: Fn(&mut [Node]) -> bool>(nodes: &mut [Node], condition: F) -> bool {
+        let deadline: Instant = Instant::now() + Duration::from_secs(20);
+
+        while Instant::now() < deadline {
+            for node in nodes.iter_mut() {
+                while let Ok(event) = node.events.try_recv() {
+                    node.handle_event(event);
+                }
+            }
+
+            if condition(nodes) {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        false
+    }
+
+    fn ready_peers(node: &Node) -> usize {
+        node.peers.values().filter(|peer| peer.is_ready()).count()
+    }
+
+    #[test]
+    fn handshake() {
+        let (_a_dir, a) = start_node(Vec::new());
+        let (_b_dir, b) = start_node(vec![listen_addr(&a)]);
+        let mut nodes: [Node; 2] = [a, b];
+
+        assert!(run_until(&mut nodes, |nodes| nodes.iter().all(|node| ready_peers(node) == 1)));
+
+        let [a, b] = &nodes;
+        let (_, a_peer) = a.peers.iter().next().unwrap();
+        let (b_peer_addr, b_peer) = b.peers.iter().next().unwrap();
+
+        assert!(a_peer.inbound);
+        assert_eq!(a_peer.listen_addr, Some(listen_addr(b)));
+        assert!(!b_peer.inbound);
+        assert_eq!(*b_peer_addr, listen_addr(a));
+
+        // both learned the other's listening address
+        assert!(a.known_addrs.contains(&listen_addr(b)));
+        assert!(b.known_addrs.contains(&listen_addr(a)));
+    }
+
+    #[test]
+    fn block_relay() {
+        let (_a_dir, a) = start_node(Vec::new());
+        let (_b_dir, b) = start_node(vec![listen_addr(&a)]);
+        let (_c_dir, c) = start_node(vec![listen_addr(&b)]);
+        let mut nodes: [Node; 3] = [a, b, c];
+
+        assert!(run_until(&mut nodes, |nodes| ready_peers(&nodes[0]) >= 1 && ready_peers(&nodes[2]) >= 1));
+
+        let genesis: Block = nodes[0].db.get_latest_block().unwrap();
+        let block: Block = mine_block(&genesis, &Vec::new());
+        nodes[0].get_sender().send(Event::MinedBlock(block.clone())).unwrap();
+
+        let hash: String = block.get_hash();
+        assert!(run_until(&mut nodes, |nodes| nodes.iter_mut().all(|node| node.get_latest_hash().as_ref() == Some(&hash))));
+
+        // nothing is left waiting once the block arrived
+        assert!(nodes.iter().all(|node| node.requested.is_empty()));
+    }
+
+    #[test]
+    fn self_connection() {
+        let (_dir, mut node) = start_node(Vec::new());
+        let own: SocketAddr = listen_addr(&node);
+
+        node.known_addrs.insert(own);
+        node.connect(own);
+        let mut nodes: [Node; 1] = [node];
+
+        // the connection is made, then dropped on both ends once our own nonce comes back
+        assert!(run_until(&mut nodes, |nodes| {
+            let node: &Node = &nodes[0];
+            node.connecting.is_empty() && node.peers.is_empty() && !node.known_addrs.contains(&own)
+        }));
+    }
+
+    #[test]
+    fn requested_objects() {
+        let (_dir, mut node) = start_node(Vec::new());
+        let peers: [SocketAddr; 2] = ["127.0.0.1:7501".parse().unwrap(), "127.0.0.1:7502".parse().unwrap()];
+        let requested_at: Instant = Instant::now();
+        let tick: Instant = requested_at + GET_DATA_TIMEOUT * 2;
+
+        node.requested.insert(Inventory::Block("a".to_owned()), (peers[0], requested_at));
+        node.requested.insert(Inventory::Block("b".to_owned()), (peers[1], tick - GET_DATA_TIMEOUT));
+        node.requested.insert(Inventory::Transaction("c".to_owned()), (peers[1], requested_at));
+
+        // what was asked to a peer that is gone can be asked to another one
+        node.on_peer_removed(peers[0]);
+        assert!(!node.requested.contains_key(&Inventory::Block("a".to_owned())));
+        assert_eq!(node.requested.len(), 2);
+
+        // and so can what took too long
+        node.on_tick(tick);
+        assert!(node.requested.contains_key(&Inventory::Block("b".to_owned())));
+        assert_eq!(node.requested.len(), 1);
+
+        // announcing it again asks for it again
+        node.on_inv(peers[0], vec![Inventory::Transaction("c".to_owned())]);
+        assert_eq!(node.requested.get(&Inventory::Transaction("c".to_owned())).map(|(peer, _)| *peer), Some(peers[0]));
+    }
+}
@@ -0,0 +1,372 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, net::SocketAddr, time::Instant};
+use rblock::{Block, BlockHeader};
+use rusty_leveldb::Status;
+use crate::{db::BlocksDB, validation::validate_header};
+use super::{Inventory, Message, BLOCK_DOWNLOAD_TIMEOUT, HEADERS_TIMEOUT, MAX_BLOCKS_IN_FLIGHT_PER_PEER, MAX_HEADERS};
+
+/// Downloads the chain from peers, used when a node starts behind the network.
+///
+/// Sync is done headers first: the headers after our latest block are asked to a single peer,
+/// checked, then the full blocks are downloaded in parallel from every peer and handed to the
+/// node in chain order so that they go through BlocksDB::add_block.
+///
+/// Nothing is kept on disk, blocks are only written once they're validated and every block is
+/// written atomically. After a crash the db ends at the last block added, and since headers are
+/// asked starting from our latest block, sync resumes from there.
+pub struct ChainSync {
+    /// The peer headers are downloaded from
+    sync_peer: Option<SocketAddr>,
+
+    /// When headers were last asked to the sync peer, None if no answer is expected
+    headers_requested_at: Option<Instant>,
+
+    /// The last header received, the next headers must build on it
+    last_header: Option<BlockHeader>,
+
+    /// Hashes of blocks to download in chain order
+    queue: VecDeque<String>,
+
+    /// Blocks asked to a peer and not received yet
+    in_flight: HashMap<String, (SocketAddr, Instant)>,
+
+    /// Downloaded blocks whose parent isn't in the db yet, indexed by their parent's hash.
+    /// Competing blocks can build on the same parent, so a parent can have several children
+    downloaded: HashMap<String, Vec<Block>>,
+
+    /// Hashes of downloaded blocks that were rejected or descend from a rejected block, their children aren't kept
+    rejected: HashSet<String>
+}
+
+impl ChainSync {
+    /// Creates an idle sync
+    pub fn new() -> Self {
+        ChainSync {
+            sync_peer: None,
+            headers_requested_at: None,
+            last_header: None,
+            queue: VecDeque::new(),
+            in_flight: HashMap::new(),
+            downloaded: HashMap::new(),
+            rejected: HashSet::new()
+        }
+    }
+
+    /// returns true if headers are being downloaded or blocks are left to download
+    pub fn is_syncing(&self) -> bool {
+        self.sync_peer.is_some() || !self.queue.is_empty() || !self.in_flight.is_empty()
+    }
+
+    /// Starts downloading headers from a peer if no other peer is used for it
+    ///
+    /// # Arguments
+    /// * `peer` - A SocketAddr which specifies the peer to download from, it should have more blocks than us
+    /// * `db` - A &mut BlocksDB which specifies the db used to know where our chain ends
+    ///
+    /// # Returns
+    /// An Result<Option<Message>, Status> which is the request to send to the peer, if one is needed
+    ///
+    pub fn start(&mut self, peer: SocketAddr, db: &mut BlocksDB) -> Result<Option<Message>, Status> {
+        if self.sync_peer.is_some() {
+            return Ok(None);
+        }
+
+        // continue from the last header if blocks are still being downloaded, otherwise from our latest block
+        let downloading: bool = !self.queue.is_empty() || !self.in_flight.is_empty();
+        let locator: Vec<String> = match &self.last_header {
+            Some(header) if downloading => vec![header.hash.clone()],
+            _ => {
+                self.last_header = None;
+                self.rejected.clear();
+                db.get_locator()?
+            }
+        };
+
+        self.sync_peer = Some(peer);
+        self.headers_requested_at = Some(Instant::now());
+
+        Ok(Some(Message::GetHeaders(locator)))
+    }
+
+    /// Checks headers received from the sync peer and queues their blocks for download
+    ///
+    /// # Arguments
+    /// * `peer` - A SocketAddr which specifies the peer that sent the headers
+    /// * `headers` - A Vec<BlockHeader> which specifies the headers, in increasing height
+    /// * `db` - A &mut BlocksDB which specifies the db used to know which blocks we have
+    ///
+    /// # Returns
+    /// An Result<Option<Message>, &str> which is the request for the next headers if there are more,
+    /// or an error if the headers are invalid and the peer shouldn't be trusted
+    ///
+    pub fn on_headers(&mut self, peer: SocketAddr, headers: Vec<BlockHeader>, db: &mut BlocksDB) -> Result<Option<Message>, &'static str> {
+        if self.sync_peer != Some(peer) || self.headers_requested_at.is_none() {
+            return Err("unrequested headers");
+        }
+
+        self.headers_requested_at = None;
+        let more: bool = headers.len() >= MAX_HEADERS;
+
+        if headers.len() > MAX_HEADERS {
+            return Err("too many headers");
+        }
+
+        for header in headers {
+            // headers must make a chain, starting on a block that we have or on the previous batch
+            let parent: Option<BlockHeader> = match &self.last_header {
+                Some(last) if last.hash == header.prev_hash => Some(last.clone()),
+                _ => db.get_block_by_hash(&header.prev_hash).ok().map(|parent| parent.get_header())
+            };
+
+            if parent.is_none_or(|parent| validate_header(&parent, &header).is_err()) {
+                self.sync_peer = None;
+                return Err("invalid header");
+            }
+
+            if !db.contains_block(&header.hash) && !self.in_flight.contains_key(&header.hash) {
+                self.queue.push_back(header.hash.clone());
+            }
+
+            self.last_header = Some(header);
+        }
+
+        if more {
+            self.headers_requested_at = Some(Instant::now());
+            let last: String = self.last_header.as_ref().map(|header| header.hash.clone()).unwrap_or_default();
+            return Ok(Some(Message::GetHeaders(vec![last])));
+        }
+
+        // the peer has nothing more, blocks are still downloaded from every peer
+        self.sync_peer = None;
+        Ok(None)
+    }
+
+    /// Assigns queued blocks to peers that have room for more requests
+    ///
+    /// # Arguments
+    /// * `peers` - A &[SocketAddr] which specifies the peers that can be asked for blocks
+    ///
+    /// # Returns
+    /// A Vec<(SocketAddr, Message)> which is the requests to send
+    ///
+    pub fn request_blocks(&mut self, peers: &[SocketAddr]) -> Vec<(SocketAddr, Message)> {
+        let mut requests: Vec<(SocketAddr, Message)> = Vec::new();
+        let now: Instant = Instant::now();
+
+        for peer in peers {
+            let in_flight: usize = self.in_flight.values().filter(|(addr, _)| addr == peer).count();
+            let mut wanted: Vec<Inventory> = Vec::new();
+
+            while in_flight + wanted.len() < MAX_BLOCKS_IN_FLIGHT_PER_PEER {
+                let hash: String = match self.queue.pop_front() {
+                    Some(hash) => hash,
+                    None => break
+                };
+
+                self.in_flight.insert(hash.clone(), (*peer, now));
+                wanted.push(Inventory::Block(hash));
+            }
+
+            if !wanted.is_empty() {
+                requests.push((*peer, Message::GetData(wanted)));
+            }
+        }
+
+        requests
+    }
+
+    /// Called when a block arrives from a peer
+    ///
+    /// # Returns
+    /// A bool which is true if the block was downloaded by the sync
+    ///
+    pub fn on_block(&mut self, hash: &str) -> bool {
+        self.in_flight.remove(hash).is_some()
+    }
+
+    /// Keeps a downloaded block until its parent is added to the db, unless its parent was rejected
+    pub fn hold(&mut self, block: Block) {
+        if self.rejected.contains(&block.get_prev_hash()) {
+            self.rejected.insert(block.get_hash());
+            return;
+        }
+
+        self.downloaded.entry(block.get_prev_hash()).or_default().push(block);
+    }
+
+    /// Returns the downloaded blocks that build on the given block
+    pub fn take_children(&mut self, hash: &str) -> Vec<Block> {
+        self.downloaded.remove(hash).unwrap_or_default()
+    }
+
+    /// Drops the downloaded blocks that descend from a rejected block, they can't be added either.
+    /// While blocks are still downloaded, the rejected hashes are kept so that the descendants that arrive later are dropped too
+    pub fn on_rejected(&mut self, hash: &str) {
+        let mut rejected: Vec<String> = vec![hash.to_owned()];
+
+        while let Some(hash) = rejected.pop() {
+            rejected.extend(self.take_children(&hash).iter().map(|child| child.get_hash()));
+
+            if self.is_syncing() {
+                self.rejected.insert(hash);
+            }
+        }
+    }
+
+    /// Puts blocks that a peer doesn't have back in the queue so that another peer is asked
+    pub fn on_not_found(&mut self, peer: SocketAddr, inventory: &[Inventory]) {
+        for item in inventory.iter().rev() {
+            if let Inventory::Block(hash) = item {
+                if self.in_flight.get(hash).is_some_and(|(addr, _)| *addr == peer) {
+                    self.in_flight.remove(hash);
+                    self.queue.push_front(hash.clone());
+                }
+            }
+        }
+    }
+
+    /// Puts the requests of a disconnected peer back in the queue
+    pub fn on_disconnect(&mut self, peer: SocketAddr) {
+        if self.sync_peer == Some(peer) {
+            self.sync_peer = None;
+            self.headers_requested_at = None;
+        }
+
+        self.requeue(|addr, _| addr == peer);
+    }
+
+    /// Puts requests that took too long back in the queue and gives up on a sync peer that doesn't answer
+    ///
+    /// # Returns
+    /// An Option<SocketAddr> which is the sync peer if it timed out
+    ///
+    pub fn on_tick(&mut self) -> Option<SocketAddr> {
+        let now: Instant = Instant::now();
+        self.requeue(|_, requested_at| now - requested_at > BLOCK_DOWNLOAD_TIMEOUT);
+
+        if self.headers_requested_at.is_some_and(|requested_at| now - requested_at > HEADERS_TIMEOUT) {
+            self.headers_requested_at = None;
+            return self.sync_peer.take();
+        }
+
+        None
+    }
+
+    /// Moves in-flight requests matching a condition back to the front of the queue.
+    /// Order doesn't matter here since downloaded blocks are held until their parent is added.
+    fn requeue<F: Fn(SocketAddr, Instant) -> bool>(&mut self, condition: F) {
+        let hashes: Vec<String> = self.in_flight.iter()
+            .filter(|(_, (addr, requested_at))| condition(*addr, *requested_at))
+            .map(|(hash, _)| hash.clone())
+            .collect();
+
+        for hash in hashes {
+            self.in_flight.remove(&hash);
+            self.queue.push_front(hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mine_block, miner, solve, temp_db};
+    use crate::validation::MAX_FUTURE_BLOCK_TIME;
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:7500".parse().unwrap()
+    }
+
+    /// returns a block on top of prev_block with the given timestamp and difficulty, solved for that difficulty
+    fn forged(prev_block: &Block, timestamp: u64, difficulty: Option<u32>) -> Block {
+        let mut block: Block = Block::new(prev_block, &Vec::new());
+        block.reward_miner(&miner());
+        block.set_timestamp(timestamp);
+        solve(prev_block, &mut block);
+
+        if let Some(difficulty) = difficulty {
+            block.set_difficulty(difficulty);
+            while !block.confirm_difficulty() {
+                block.increment_and_hash();
+            }
+        }
+
+        block
+    }
+
+    /// sends headers to a new sync started on a fresh db
+    fn receive(db: &mut BlocksDB, headers: Vec<BlockHeader>) -> (ChainSync, Result<Option<Message>, &'static str>) {
+        let mut sync: ChainSync = ChainSync::new();
+        sync.start(peer(), db).unwrap();
+
+        let result: Result<Option<Message>, &'static str> = sync.on_headers(peer(), headers, db);
+        (sync, result)
+    }
+
+    #[test]
+    fn headers_follow_their_parent() {
+        let (_dir, mut db) = temp_db();
+        let genesis: Block = db.get_latest_block().unwrap();
+        let b1: Block = mine_block(&genesis, &Vec::new());
+        let b2: Block = mine_block(&b1, &Vec::new());
+
+        let (sync, result) = receive(&mut db, vec![b1.get_header(), b2.get_header()]);
+        assert!(matches!(result, Ok(None)));
+        assert_eq!(sync.queue, [b1.get_hash(), b2.get_hash()]);
+
+        // right after its parent the difficulty goes up, an easier one is refused even with a valid hash
+        let easier: Block = forged(&b1, b1.get_timestamp(), Some(0xffffffff));
+        assert_ne!(Block::get_supposed_difficulty(&b1, &easier), 0xffffffff);
+        assert!(easier.confirm_hash() && easier.confirm_difficulty());
+
+        let (sync, result) = receive(&mut db, vec![b1.get_header(), easier.get_header()]);
+        assert_eq!(result.err(), Some("invalid header"));
+        assert!(sync.sync_peer.is_none());
+
+        // a timestamp before the parent's
+        let older: Block = forged(&b1, b1.get_timestamp() - 1, None);
+        let (_, result) = receive(&mut db, vec![b1.get_header(), older.get_header()]);
+        assert_eq!(result.err(), Some("invalid header"));
+
+        // a timestamp too far in the future, as validate_block refuses it
+        let future: Block = forged(&b1, rblock::get_unix_time() + MAX_FUTURE_BLOCK_TIME + 60, None);
+        let (_, result) = receive(&mut db, vec![b1.get_header(), future.get_header()]);
+        assert_eq!(result.err(), Some("invalid header"));
+
+        // a header that doesn't build on anything we know
+        let (_, result) = receive(&mut db, vec![b2.get_header()]);
+        assert_eq!(result.err(), Some("invalid header"));
+    }
+
+    #[test]
+    fn descendants_of_rejected_blocks() {
+        let genesis: Block = Block::new_genesis();
+        let b1: Block = mine_block(&genesis, &Vec::new());
+        let b2: Block = mine_block(&b1, &Vec::new());
+        let b3: Block = mine_block(&b2, &Vec::new());
+        let sibling: Block = forged(&b1, b2.get_timestamp() + 1, None);
+        let late: Block = mine_block(&b3, &Vec::new());
+
+        let mut sync: ChainSync = ChainSync::new();
+        sync.queue.push_back(late.get_hash());
+
+        // two children of the same parent are both kept
+        sync.hold(b2.clone());
+        sync.hold(sibling.clone());
+        sync.hold(b3.clone());
+        assert_eq!(sync.downloaded[&b1.get_hash()].len(), 2);
+
+        // b1 is added, then b2 is rejected
+        let children: Vec<Block> = sync.take_children(&b1.get_hash());
+        assert_eq!(children.len(), 2);
+
+        sync.on_rejected(&b2.get_hash());
+        assert!(!sync.downloaded.contains_key(&b2.get_hash()));
+        assert!(sync.rejected.contains(&b3.get_hash()));
+        assert!(!sync.rejected.contains(&sibling.get_hash()));
+
+        // a descendant that arrives after the rejection isn't kept
+        sync.hold(late.clone());
+        assert!(sync.downloaded.is_empty());
+        assert!(sync.rejected.contains(&late.get_hash()));
+    }
+}
@@ -0,0 +1,57 @@
+//! helpers for the unit tests of the node: dbs in temporary directories and blocks that are valid on top of any other block.
+//! blocks above genesis have an easy difficulty, so a nonce is found after a few hashes
+
+use ecdsa::secp256k1::PrivateKey;
+use rblock::{Address, Block, Transaction};
+use tempfile::TempDir;
+use crate::db::BlocksDB;
+
+/// returns the private key of miner(), it is 1
+pub fn miner_key() -> PrivateKey {
+    let mut bytes: [u8; 32] = [0; 32];
+    bytes[31] = 1;
+
+    PrivateKey::from_bytes(&bytes).unwrap()
+}
+
+/// returns the address that the rewards of test blocks are paid to
+pub fn miner() -> Address {
+    Address::from(&miner_key().get_public_key())
+}
+
+/// opens a new db in a temporary directory, which is deleted when the returned TempDir is dropped
+pub fn temp_db() -> (TempDir, BlocksDB) {
+    let dir: TempDir = tempfile::tempdir().unwrap();
+    let db: BlocksDB = BlocksDB::open(dir.path().join("blocks")).unwrap();
+
+    (dir, db)
+}
+
+/// returns a valid block on top of prev_block with the given transactions, rewarded to miner()
+///
+/// # Arguments
+/// * `prev_block` - A &Block which specifies the block to build on
+/// * `transactions` - A &Vec<Transaction> which specifies the block's transactions, the reward excluded
+///
+pub fn mine_block(prev_block: &Block, transactions: &Vec<Transaction>) -> Block {
+    let mut block: Block = Block::new(prev_block, transactions);
+    block.set_timestamp(block.get_timestamp().max(prev_block.get_timestamp()));
+    block.reward_miner(&miner());
+
+    solve(prev_block, &mut block);
+    block
+}
+
+/// gives a block the difficulty it is supposed to have and searches a nonce that satisfies it
+///
+/// # Arguments
+/// * `prev_block` - A &Block which specifies the block that `block` builds on
+/// * `block` - A &mut Block which specifies the block to solve
+///
+pub fn solve(prev_block: &Block, block: &mut Block) {
+    block.set_difficulty(Block::get_supposed_difficulty(prev_block, block));
+
+    while !block.confirm_difficulty() {
+        assert!(block.increment_and_hash(), "no nonce satisfies the difficulty");
+    }
+}
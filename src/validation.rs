@@ -0,0 +1,373 @@
+use core::fmt;
+use ecdsa::secp256k1::Point;
+use rblock::{Address, Amount, Block, BlockHeader};
+use rusty_leveldb::Status;
+
+/// How far in the future (in seconds) a block's timestamp is allowed to be compared to local time
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 7200; // 2 hours
+
+/// Reasons for which a block can be rejected by the node.
+///
+/// Every check made before a block is written to the db maps to one variant so
+/// that callers (peers, miner) know exactly why a block was refused.
+///
+#[derive(Debug)]
+pub enum BlockError {
+    /// Genesis blocks can't be added after the db is initialized
+    Genesis,
+
+    /// The block is already stored in the db
+    AlreadyExists,
+
+    /// The block's parent is not stored in the db
+    UnknownParent { prev_hash: String },
+
+    /// The block builds on a block that was found to be invalid
+    InvalidAncestor { hash: String },
+
+    /// The block itself was already found to be invalid
+    KnownInvalid,
+
+    /// The block is not the next one above the latest block
+    InvalidHeight { expected: u64, found: u64 },
+
+    /// The block's previous hash doesn't match the hash of the block it builds on
+    PrevHashMismatch { expected: String, found: String },
+
+    /// The block's timestamp is before its parent's or too far in the future
+    InvalidTimestamp { timestamp: u64 },
+
+    /// The block's merkel root doesn't match its transactions
+    InvalidMerkelRoot,
+
+    /// The block's hash doesn't match its data
+    InvalidHash,
+
+    /// The block's difficulty isn't the one given by the difficulty adjustment
+    InvalidDifficulty { expected: u32, found: u32 },
+
+    /// The block's hash doesn't satisfy its difficulty
+    InsufficientWork,
+
+    /// The block doesn't start with exactly one reward, or the reward is too big
+    InvalidCoinbase,
+
+    /// One of the block's transactions is invalid or there are too many of them
+    InvalidTransactions,
+
+    /// An account sends more than its balance at that point of the block's transactions
+    InsufficientBalance(Address),
+
+    /// An account's balance would overflow after the block's transactions
+    BalanceOverflow(Address),
+
+    /// A transaction's sender isn't a point of the curve
+    InvalidPublicKey(Point),
+
+    /// The rewards of the block are more than the block reward plus the fees
+    InvalidReward { max: Amount, found: Amount },
+
+    /// A transaction doesn't use the next nonce of its sender, it was already applied or skips nonces
+    InvalidNonce { address: Address, expected: u64, found: u64 },
+
+    /// The db failed while reading or writing
+    Db(Status)
+}
+
+/// implement display for BlockError for easy printing of rejection reasons
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::Genesis => write!(f, "cannot add another genesis block"),
+            BlockError::AlreadyExists => write!(f, "block already exists in db"),
+            BlockError::UnknownParent { prev_hash } => write!(f, "unknown parent block: {}", prev_hash),
+            BlockError::InvalidAncestor { hash } => write!(f, "block builds on invalid block: {}", hash),
+            BlockError::KnownInvalid => write!(f, "block was already found to be invalid"),
+            BlockError::InvalidHeight { expected, found } =>
+                write!(f, "invalid block height: expected {}, found {}", expected, found),
+            BlockError::PrevHashMismatch { expected, found } =>
+                write!(f, "previous hash mismatch: expected {}, found {}", expected, found),
+            BlockError::InvalidTimestamp { timestamp } =>
+                write!(f, "invalid block timestamp: {}", timestamp),
+            BlockError::InvalidMerkelRoot => write!(f, "merkel root does not match transactions"),
+            BlockError::InvalidHash => write!(f, "block hash does not match block data"),
+            BlockError::InvalidDifficulty { expected, found } =>
+                write!(f, "invalid difficulty: expected {:08x}, found {:08x}", expected, found),
+            BlockError::InsufficientWork => write!(f, "block hash does not satisfy difficulty"),
+            BlockError::InvalidCoinbase => write!(f, "block has an invalid reward"),
+            BlockError::InvalidTransactions => write!(f, "block contains invalid transactions"),
+            BlockError::InsufficientBalance(address) =>
+                write!(f, "address: {} sends more than its balance", address),
+            BlockError::BalanceOverflow(address) =>
+                write!(f, "address: {} has a balance that overflows", address),
+            // written uncompressed, the compressed encoding of a point off the curve would be another point
+            BlockError::InvalidPublicKey(public_key) =>
+                write!(f, "public key: {} is not on the curve", sha256::to_hex(&public_key.to_sec1(false))),
+            BlockError::InvalidReward { max, found } =>
+                write!(f, "invalid reward: at most {}, found {}", max, found),
+            BlockError::InvalidNonce { address, expected, found } =>
+                write!(f, "address: {} has invalid nonce: expected {}, found {}", address, expected, found),
+            BlockError::Db(status) => write!(f, "db error: {}", status)
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// allows the use of ? on db operations in methods returning a BlockError
+impl From<Status> for BlockError {
+    fn from(status: Status) -> Self {
+        BlockError::Db(status)
+    }
+}
+
+/// Validates a block against the block it builds on.
+/// Checks are ordered from cheapest to most expensive so that forged blocks are rejected early.
+/// Balances are not checked here since they depend on the chainstate, see BlocksDB::add_block.
+///
+/// # Arguments
+/// * `prev_block` - A &Block which specifies a reference to the block that `block` builds on
+/// * `block` - A &Block which specifies a reference to the block to validate
+///
+/// # Returns
+/// A Result<(), BlockError> which is Ok(()) if the block is valid, or the reason of the rejection if it is not.
+///
+pub fn validate_block(prev_block: &Block, block: &Block) -> Result<(), BlockError> {
+    validate_header(&prev_block.get_header(), &block.get_header())?;
+
+    if !block.confirm_merkel_root() {
+        return Err(BlockError::InvalidMerkelRoot);
+    }
+
+    if !block.confirm_coinbase() {
+        return Err(BlockError::InvalidCoinbase);
+    }
+
+    // signature checks are the most expensive so they come last
+    if !block.confirm_transactions() {
+        return Err(BlockError::InvalidTransactions);
+    }
+
+    Ok(())
+}
+
+/// Validates a header against the header of the block it builds on, every check of validate_block
+/// that doesn't need the transactions. Used on its own for the headers received while syncing.
+///
+/// # Arguments
+/// * `parent` - A &BlockHeader which specifies a reference to the header that `header` builds on
+/// * `header` - A &BlockHeader which specifies a reference to the header to validate
+///
+/// # Returns
+/// A Result<(), BlockError> which is Ok(()) if the header is valid, or the reason of the rejection if it is not.
+///
+pub fn validate_header(parent: &BlockHeader, header: &BlockHeader) -> Result<(), BlockError> {
+    if header.height == 0 {
+        return Err(BlockError::Genesis);
+    }
+
+    if header.height != parent.height + 1 {
+        return Err(BlockError::InvalidHeight {
+            expected: parent.height + 1,
+            found: header.height
+        });
+    }
+
+    if header.prev_hash != parent.hash {
+        return Err(BlockError::PrevHashMismatch {
+            expected: parent.hash.clone(),
+            found: header.prev_hash.clone()
+        });
+    }
+
+    // timestamp can't go back in time, which also keeps the difficulty computation from underflowing
+    let max_timestamp: u64 = rblock::get_unix_time() + MAX_FUTURE_BLOCK_TIME;
+    if header.timestamp < parent.timestamp || header.timestamp > max_timestamp {
+        return Err(BlockError::InvalidTimestamp { timestamp: header.timestamp });
+    }
+
+    // hash needs to be checked first since the difficulty check reads it
+    if !header.confirm_hash() {
+        return Err(BlockError::InvalidHash);
+    }
+
+    let supposed_difficulty: u32 = BlockHeader::get_supposed_difficulty(parent, header);
+    if header.difficulty != supposed_difficulty {
+        return Err(BlockError::InvalidDifficulty {
+            expected: supposed_difficulty,
+            found: header.difficulty
+        });
+    }
+
+    if !header.confirm_difficulty() {
+        return Err(BlockError::InsufficientWork);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mine_block, miner, solve};
+    use rblock::Transaction;
+
+    /// returns the block with one of its fields replaced, the hash is not recomputed
+    fn tamper(block: &Block, field: &str, value: serde_json::Value) -> Block {
+        let mut json: serde_json::Value = serde_json::to_value(block).unwrap();
+        json[field] = value;
+        serde_json::from_value(json).unwrap()
+    }
+
+    /// returns a block on top of prev_block with the given timestamp, solved for it
+    fn at_time(prev_block: &Block, timestamp: u64) -> Block {
+        let mut block: Block = Block::new(prev_block, &Vec::new());
+        block.reward_miner(&miner());
+        block.set_timestamp(timestamp);
+
+        solve(prev_block, &mut block);
+        block
+    }
+
+    #[test]
+    fn valid_blocks() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+        assert!(validate_block(&genesis, &block).is_ok());
+
+        let next: Block = mine_block(&block, &Vec::new());
+        assert!(validate_block(&block, &next).is_ok());
+
+        // a block in the same second as its parent
+        let same_time: Block = at_time(&block, block.get_timestamp());
+        assert!(validate_block(&block, &same_time).is_ok());
+    }
+
+    #[test]
+    fn genesis() {
+        let genesis: Block = Block::new_genesis();
+        assert!(matches!(validate_block(&genesis, &genesis), Err(BlockError::Genesis)));
+    }
+
+    #[test]
+    fn bad_height() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+        let next: Block = mine_block(&block, &Vec::new());
+
+        assert!(matches!(validate_block(&genesis, &next), Err(BlockError::InvalidHeight { expected: 1, found: 2 })));
+        assert!(matches!(validate_block(&next, &block), Err(BlockError::InvalidHeight { expected: 3, found: 1 })));
+    }
+
+    #[test]
+    fn prev_hash_mismatch() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+        let sibling: Block = at_time(&genesis, block.get_timestamp() + 1);
+        let next: Block = mine_block(&sibling, &Vec::new());
+
+        match validate_block(&block, &next) {
+            Err(BlockError::PrevHashMismatch { expected, found }) => {
+                assert_eq!(expected, block.get_hash());
+                assert_eq!(found, sibling.get_hash());
+            },
+            result => panic!("expected PrevHashMismatch, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+
+        let before: u64 = block.get_timestamp() - 1;
+        assert!(matches!(validate_block(&block, &at_time(&block, before)),
+            Err(BlockError::InvalidTimestamp { timestamp }) if timestamp == before));
+
+        // the limit is relative to the local time, keep a margin for the time the test takes
+        let limit: u64 = rblock::get_unix_time() + MAX_FUTURE_BLOCK_TIME;
+        assert!(validate_block(&block, &at_time(&block, limit - 60)).is_ok());
+
+        let future: u64 = limit + 60;
+        assert!(matches!(validate_block(&block, &at_time(&block, future)),
+            Err(BlockError::InvalidTimestamp { timestamp }) if timestamp == future));
+    }
+
+    #[test]
+    fn bad_merkel_root() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+
+        // a merkel root that hashes correctly but doesn't match the transactions
+        let mut forged: Block = tamper(&block, "merkel_root", serde_json::json!("00".repeat(32)));
+        solve(&genesis, &mut forged);
+        assert!(matches!(validate_block(&genesis, &forged), Err(BlockError::InvalidMerkelRoot)));
+
+        // a transaction removed after the block was built
+        let forged: Block = tamper(&block, "transactions", serde_json::json!([]));
+        assert!(matches!(validate_block(&genesis, &forged), Err(BlockError::InvalidMerkelRoot)));
+    }
+
+    #[test]
+    fn bad_hash() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+
+        let forged: Block = tamper(&block, "hash", serde_json::json!("00".repeat(32)));
+        assert!(matches!(validate_block(&genesis, &forged), Err(BlockError::InvalidHash)));
+    }
+
+    #[test]
+    fn wrong_difficulty() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+        let supposed: u32 = Block::get_supposed_difficulty(&genesis, &block);
+
+        let mut other: Block = block.clone();
+        other.set_difficulty(supposed ^ 1);
+        match validate_block(&genesis, &other) {
+            Err(BlockError::InvalidDifficulty { expected, found }) => {
+                assert_eq!(expected, supposed);
+                assert_eq!(found, supposed ^ 1);
+            },
+            result => panic!("expected InvalidDifficulty, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn insufficient_work() {
+        let genesis: Block = Block::new_genesis();
+        let block: Block = mine_block(&genesis, &Vec::new());
+
+        // a block right after its parent has a harder difficulty, find a nonce that doesn't satisfy it
+        let mut next: Block = at_time(&block, block.get_timestamp());
+        assert_eq!(next.get_difficulty(), 0xfffffffe);
+        while next.confirm_difficulty() {
+            assert!(next.increment_and_hash());
+        }
+
+        assert!(matches!(validate_block(&block, &next), Err(BlockError::InsufficientWork)));
+    }
+
+    #[test]
+    fn bad_coinbase() {
+        let genesis: Block = Block::new_genesis();
+
+        // no reward
+        let mut unrewarded: Block = Block::new(&genesis, &Vec::new());
+        solve(&genesis, &mut unrewarded);
+        assert!(matches!(validate_block(&genesis, &unrewarded), Err(BlockError::InvalidCoinbase)));
+
+        // more than the subsidy
+        let reward: Transaction = Transaction::reward_transaction(&miner(), 1, Amount::from_snails(1)).unwrap();
+        let mut greedy: Block = Block::new(&genesis, &vec![reward]);
+        solve(&genesis, &mut greedy);
+        assert!(matches!(validate_block(&genesis, &greedy), Err(BlockError::InvalidCoinbase)));
+
+        // two rewards
+        let rewards: Vec<Transaction> = vec![Transaction::reward_transaction(&miner(), 1, Amount::ZERO).unwrap(); 2];
+        let mut doubled: Block = Block::new(&genesis, &rewards);
+        solve(&genesis, &mut doubled);
+        assert!(matches!(validate_block(&genesis, &doubled), Err(BlockError::InvalidCoinbase)));
+    }
+}